@@ -0,0 +1,352 @@
+use bitcoin::bech32::primitives::decode::CheckedHrpstring;
+use bitcoin::bech32::{Bech32, Bech32m};
+use bitcoin::{Address, Network, ScriptBuf};
+use core::str::FromStr;
+use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+
+// ===== Address types understood by getnewaddress / getrawchangeaddress =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressType {
+    Legacy,
+    P2shSegwit,
+    Bech32,
+    Bech32m,
+}
+
+impl AddressType {
+    pub const ALL: [AddressType; 4] = [
+        AddressType::Legacy,
+        AddressType::P2shSegwit,
+        AddressType::Bech32,
+        AddressType::Bech32m,
+    ];
+
+    /// Value of the `address_type` RPC argument.
+    pub fn rpc_name(self) -> &'static str {
+        match self {
+            AddressType::Legacy => "legacy",
+            AddressType::P2shSegwit => "p2sh-segwit",
+            AddressType::Bech32 => "bech32",
+            AddressType::Bech32m => "bech32m",
+        }
+    }
+
+    /// Short name for the address list and selector.
+    pub fn label(self) -> &'static str {
+        match self {
+            AddressType::Legacy => "legacy",
+            AddressType::P2shSegwit => "p2sh-segwit",
+            AddressType::Bech32 => "bech32",
+            AddressType::Bech32m => "taproot",
+        }
+    }
+
+    /// Cycles node default (None) -> legacy -> ... -> bech32m -> node default.
+    pub fn cycle(current: Option<AddressType>) -> Option<AddressType> {
+        match current {
+            None => Some(AddressType::ALL[0]),
+            Some(t) => {
+                let i = AddressType::ALL.iter().position(|x| *x == t).unwrap_or(0);
+                AddressType::ALL.get(i + 1).copied()
+            }
+        }
+    }
+
+    /// Infers the wallet address type from an encoded address. P2SH is
+    /// assumed to be wrapped segwit, which is all the wallet hands out.
+    pub fn of_address(addr: &str) -> Option<AddressType> {
+        let a = Address::from_str(addr.trim()).ok()?.assume_checked();
+        match a.address_type()? {
+            bitcoin::AddressType::P2pkh => Some(AddressType::Legacy),
+            bitcoin::AddressType::P2sh => Some(AddressType::P2shSegwit),
+            bitcoin::AddressType::P2wpkh | bitcoin::AddressType::P2wsh => {
+                Some(AddressType::Bech32)
+            }
+            bitcoin::AddressType::P2tr => Some(AddressType::Bech32m),
+            _ => None,
+        }
+    }
+}
+
+// ===== Address validation =====
+
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Why an address failed to parse, with the char index of the offending
+/// character when it can be located.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddrError {
+    pub message: String,
+    pub position: Option<usize>,
+}
+
+impl AddrError {
+    fn at(message: impl Into<String>, position: Option<usize>) -> AddrError {
+        AddrError { message: message.into(), position }
+    }
+}
+
+/// What a valid address encodes.
+#[derive(Clone, Debug)]
+pub struct AddrInfo {
+    /// Every network the encoding is valid on. Testnet, testnet4 and signet
+    /// share prefixes (and base58 regtest too), so this can have several.
+    pub networks: Vec<Network>,
+    pub script: Option<bitcoin::AddressType>,
+    pub script_pubkey: ScriptBuf,
+}
+
+impl AddrInfo {
+    /// "P2WPKH", "P2TR", ...
+    pub fn script_label(&self) -> String {
+        self.script
+            .map(|t| t.to_string().to_uppercase())
+            .unwrap_or_else(|| "unknown script".to_string())
+    }
+
+    pub fn script_hex(&self) -> String {
+        self.script_pubkey.to_hex_string()
+    }
+
+    pub fn valid_on(&self, net: Network) -> bool {
+        self.networks.contains(&net)
+    }
+
+    /// The node's chain if the address is valid there, else the first match.
+    pub fn pick(&self, node_chain: Option<Network>) -> Network {
+        node_chain
+            .filter(|n| self.valid_on(*n))
+            .unwrap_or(self.networks[0])
+    }
+
+    /// "mainnet", "testnet/signet", "regtest", ...
+    pub fn network_label(&self) -> String {
+        if self.valid_on(Network::Bitcoin) {
+            return "mainnet".to_string();
+        }
+        let mut names = Vec::new();
+        if self.valid_on(Network::Testnet) {
+            names.push("testnet/signet");
+        }
+        if self.valid_on(Network::Regtest) {
+            names.push("regtest");
+        }
+        names.join("/")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AddrValidity {
+    Empty,
+    Invalid(AddrError),
+    Valid(AddrInfo),
+}
+
+pub fn check_address(addr: &str) -> AddrValidity {
+    let s = addr.trim();
+    if s.is_empty() {
+        return AddrValidity::Empty;
+    }
+    match Address::from_str(s) {
+        Ok(a) => {
+            let networks: Vec<Network> = [
+                Network::Bitcoin,
+                Network::Testnet,
+                Network::Testnet4,
+                Network::Signet,
+                Network::Regtest,
+            ]
+            .into_iter()
+            .filter(|n| a.is_valid_for_network(*n))
+            .collect();
+            if networks.is_empty() {
+                return AddrValidity::Invalid(AddrError::at("unknown network", None));
+            }
+            let a = a.assume_checked();
+            AddrValidity::Valid(AddrInfo {
+                networks,
+                script: a.address_type(),
+                script_pubkey: a.script_pubkey(),
+            })
+        }
+        Err(e) => AddrValidity::Invalid(locate_error(s, &e.to_string())),
+    }
+}
+
+/// Finds the character that makes `s` invalid. Bech32 strings are checked
+/// for bad characters and mixed case, then for a single substitution that
+/// would fix the checksum; base58 strings only for bad characters.
+/// Positions are char indices, so any input is safe to report on.
+fn locate_error(s: &str, fallback: &str) -> AddrError {
+    let chars: Vec<char> = s.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let lower: String = chars.iter().collect();
+    let Some(sep) = chars.iter().rposition(|&c| c == '1').filter(|_| {
+        lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1")
+    }) else {
+        return match s.chars().position(|c| !c.is_ascii_alphanumeric() || "0OIl".contains(c)) {
+            Some(i) => AddrError::at(format!("'{}' is not a base58 character", s.chars().nth(i).unwrap_or(' ')), Some(i)),
+            None => match bitcoin::base58::decode_check(s) {
+                Err(e) => AddrError::at(e.to_string(), None),
+                Ok(_) => AddrError::at(fallback, None),
+            },
+        };
+    };
+
+    if let Some(i) = chars.iter().position(|c| !c.is_ascii()) {
+        return AddrError::at(format!("'{}' is not a bech32 character", chars[i]), Some(i));
+    }
+    let upper_count = s.chars().filter(|c| c.is_ascii_uppercase()).count();
+    let lower_count = s.chars().filter(|c| c.is_ascii_lowercase()).count();
+    if upper_count > 0 && lower_count > 0 {
+        let minority_upper = upper_count < lower_count;
+        let pos = s
+            .chars()
+            .position(|c| if minority_upper { c.is_ascii_uppercase() } else { c.is_ascii_lowercase() });
+        return AddrError::at("mixed upper and lower case", pos);
+    }
+    if let Some(i) = chars[sep + 1..].iter().position(|c| !BECH32_CHARSET.contains(*c)) {
+        let pos = sep + 1 + i;
+        return AddrError::at(format!("'{}' is not a bech32 character", s.chars().nth(pos).unwrap_or(' ')), Some(pos));
+    }
+
+    // All ASCII from here on, so byte and char positions agree
+    let checksum_ok = |t: &str| {
+        CheckedHrpstring::new::<Bech32>(t).is_ok() || CheckedHrpstring::new::<Bech32m>(t).is_ok()
+    };
+    if checksum_ok(&lower) {
+        return AddrError::at(fallback, None);
+    }
+    let mut candidates = Vec::new();
+    let mut buf: Vec<u8> = lower.bytes().collect();
+    for pos in sep + 1..buf.len() {
+        let orig = buf[pos];
+        for c in BECH32_CHARSET.bytes().filter(|c| *c != orig) {
+            buf[pos] = c;
+            if checksum_ok(std::str::from_utf8(&buf).unwrap_or("")) {
+                candidates.push(pos);
+                break;
+            }
+        }
+        buf[pos] = orig;
+    }
+    match candidates.as_slice() {
+        [pos] => AddrError::at("checksum error, likely a typo here", Some(*pos)),
+        _ => AddrError::at("invalid bech32 checksum", None),
+    }
+}
+
+/// Remembers the last address checked, so a screen can validate on every
+/// redraw while the checksum search only runs when the input changes.
+#[derive(Default)]
+pub struct AddressCheck {
+    last: RefCell<Option<(String, AddrValidity)>>,
+}
+
+impl AddressCheck {
+    pub fn check(&self, addr: &str) -> AddrValidity {
+        let mut last = self.last.borrow_mut();
+        match &*last {
+            Some((input, validity)) if input == addr => validity.clone(),
+            _ => {
+                let validity = check_address(addr);
+                *last = Some((addr.to_string(), validity.clone()));
+                validity
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn error(addr: &str) -> AddrError {
+        match check_address(addr) {
+            AddrValidity::Invalid(e) => e,
+            other => panic!("{addr} should be invalid, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn address_types() {
+        let mut seen = vec![AddressType::cycle(None)];
+        while let Some(t) = *seen.last().unwrap() {
+            seen.push(AddressType::cycle(Some(t)));
+        }
+        assert_eq!(seen, [AddressType::ALL.map(Some).as_slice(), &[None]].concat());
+        assert_eq!(AddressType::Bech32m.label(), "taproot");
+        assert_eq!(AddressType::P2shSegwit.rpc_name(), "p2sh-segwit");
+        for t in AddressType::ALL {
+            // Config and address book files store the RPC name
+            assert_eq!(serde_json::to_value(t).unwrap(), t.rpc_name());
+        }
+    }
+
+    #[test]
+    fn type_of_address() {
+        for (addr, t) in [
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", Some(AddressType::Legacy)),
+            ("37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf", Some(AddressType::P2shSegwit)),
+            (VALID, Some(AddressType::Bech32)),
+            ("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3", Some(AddressType::Bech32)),
+            ("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr", Some(AddressType::Bech32m)),
+            (" tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx ", Some(AddressType::Bech32)),
+            ("notanaddress", None),
+        ] {
+            assert_eq!(AddressType::of_address(addr), t, "{addr}");
+        }
+    }
+
+    #[test]
+    fn valid_bech32_and_base58() {
+        let AddrValidity::Valid(info) = check_address(VALID) else { panic!("invalid") };
+        assert_eq!(info.script, Some(bitcoin::AddressType::P2wpkh));
+        assert!(info.valid_on(Network::Bitcoin));
+        let AddrValidity::Valid(info) = check_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2") else { panic!("invalid") };
+        assert_eq!(info.script, Some(bitcoin::AddressType::P2pkh));
+        assert!(matches!(check_address("  "), AddrValidity::Empty));
+    }
+
+    #[test]
+    fn non_ascii_is_reported_in_chars() {
+        assert_eq!(error("bc1qé").position, Some(4));
+        assert_eq!(error("bc1q€abc").position, Some(4));
+        assert_eq!(error("tb1qxyzé").position, Some(7));
+        assert_eq!(error("bc1é1qqq").position, Some(3));
+        assert_eq!(error("1BvBMSEé").position, Some(7));
+    }
+
+    #[test]
+    fn single_typo_is_located() {
+        let mut typo: Vec<char> = VALID.chars().collect();
+        typo[10] = if typo[10] == 'q' { 'p' } else { 'q' };
+        let typo: String = typo.into_iter().collect();
+        let e = error(&typo);
+        assert_eq!(e.position, Some(10));
+        assert!(e.message.contains("typo"));
+    }
+
+    #[test]
+    fn bad_characters_and_case() {
+        let e = error("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3tb");
+        assert_eq!(e.position, Some(41));
+        let e = error("bc1qw508d6qejxtdg4y5r3zarvaryOc5xw7kv8f3t4");
+        assert_eq!(e.position, Some(29));
+        let e = error("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3t4");
+        assert_eq!(e.message, "mixed upper and lower case");
+        assert_eq!(e.position, Some(40));
+    }
+
+    #[test]
+    fn check_is_cached_per_input() {
+        let check = AddressCheck::default();
+        assert!(matches!(check.check(VALID), AddrValidity::Valid(_)));
+        assert!(matches!(check.check("bc1qé"), AddrValidity::Invalid(_)));
+        assert!(matches!(check.check(VALID), AddrValidity::Valid(_)));
+    }
+}
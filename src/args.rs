@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+// ===== Command line =====
+
+/// One-shot jobs that run instead of the TUI.
+#[derive(Debug, Clone)]
+pub(crate) enum Task {
+    ExportLabels(PathBuf),
+    ImportLabels(PathBuf),
+    ExportCsv(PathBuf),
+    ImportCsv(PathBuf),
+    EncryptAddressBook,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Args {
+    pub task: Option<Task>,
+    pub config_dir: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub help: bool,
+}
+
+pub(crate) const USAGE: &str = "\
+Usage: bitatui [OPTIONS]
+
+Locations:
+  --config-dir <DIR>       Config dir with commands.json
+                           (env BITATUI_CONFIG_DIR, default: platform config dir)
+  --data-dir <DIR>         Data dir with the address book and labels
+                           (env BITATUI_DATA_DIR, default: platform data dir)
+
+Address book:
+  --export-labels <FILE>   Export labels as BIP-329 JSON Lines
+  --import-labels <FILE>   Import BIP-329 labels (addr, tx, output, xpub)
+  --export-csv <FILE>      Export the address book as CSV
+  --import-csv <FILE>      Import address book entries from CSV
+  --encrypt-address-book   Encrypt the existing plaintext address book
+                           with a passphrase (asked when the overlay opens)
+
+  -h, --help               Show this help";
+
+pub(crate) fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut out = Args::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(PathBuf::from)
+                .ok_or_else(|| format!("{arg} needs a value"))
+        };
+        let task = match arg.as_str() {
+            "-h" | "--help" => {
+                out.help = true;
+                continue;
+            }
+            "--config-dir" => {
+                out.config_dir = Some(value()?);
+                continue;
+            }
+            "--data-dir" => {
+                out.data_dir = Some(value()?);
+                continue;
+            }
+            "--export-labels" => Task::ExportLabels(value()?),
+            "--import-labels" => Task::ImportLabels(value()?),
+            "--export-csv" => Task::ExportCsv(value()?),
+            "--import-csv" => Task::ImportCsv(value()?),
+            "--encrypt-address-book" => Task::EncryptAddressBook,
+            other => return Err(format!("unknown argument: {other}")),
+        };
+        if out.task.replace(task).is_some() {
+            return Err("only one import/export can run at a time".to_string());
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let args = parse(&[]).unwrap();
+        assert!(args.task.is_none() && !args.help);
+
+        let args = parse(&["--data-dir", "/d", "--import-csv", "book.csv", "--config-dir", "c"]).unwrap();
+        assert_eq!(args.data_dir, Some(PathBuf::from("/d")));
+        assert_eq!(args.config_dir, Some(PathBuf::from("c")));
+        assert!(matches!(args.task, Some(Task::ImportCsv(p)) if p == Path::new("book.csv")));
+
+        assert!(matches!(parse(&["--encrypt-address-book"]).unwrap().task, Some(Task::EncryptAddressBook)));
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse(&["--export-labels"]).unwrap_err(), "--export-labels needs a value");
+        assert_eq!(parse(&["--data-dir"]).unwrap_err(), "--data-dir needs a value");
+        assert_eq!(parse(&["--frobnicate"]).unwrap_err(), "unknown argument: --frobnicate");
+        assert_eq!(
+            parse(&["--export-csv", "a.csv", "--import-labels", "b.jsonl"]).unwrap_err(),
+            "only one import/export can run at a time"
+        );
+    }
+
+    #[test]
+    fn usage_lists_every_option() {
+        for option in [
+            "--config-dir",
+            "--data-dir",
+            "--export-labels",
+            "--import-labels",
+            "--export-csv",
+            "--import-csv",
+            "--encrypt-address-book",
+            "--help",
+        ] {
+            assert!(USAGE.contains(option), "{option}");
+        }
+    }
+}
@@ -1,20 +1,83 @@
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 pub(crate) fn run_bitcoin_cli(command: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let rpc_user = std::env::var("RPC_USER").unwrap_or_else(|_| "youruser".to_string());
-    let rpc_password = std::env::var("RPC_PASSWORD").unwrap_or_else(|_| "yourpassword".to_string());
-
     let mut parts = command.split_whitespace();
     let base_cmd = parts.next().unwrap();
     let args: Vec<&str> = parts.collect();
 
-    let output = Command::new("bitcoin-cli")
-        .arg(format!("-rpcuser={}", rpc_user))
-        .arg(format!("-rpcpassword={}", rpc_password))
-        .arg(base_cmd)
-        .args(&args)
-        .output()?;
+    run_bitcoin_cli_args(base_cmd, &args)
+}
+
+/// Like `run_bitcoin_cli`, but takes the arguments pre-split so that values
+/// containing spaces (labels, messages) reach the node intact.
+pub(crate) fn run_bitcoin_cli_args(
+    method: &str,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    run(None, method, args)
+}
+
+/// Runs a wallet RPC against a specific loaded wallet (`-rpcwallet`).
+pub(crate) fn run_bitcoin_cli_wallet(
+    wallet: &str,
+    method: &str,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    run(Some(wallet), method, args)
+}
+
+/// Runs an RPC with its arguments passed on stdin (`-stdin`), so
+/// passphrases don't show up in the process list.
+pub(crate) fn run_bitcoin_cli_stdin(
+    method: &str,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut child = base_command(None)
+        .arg("-stdin")
+        .arg(method)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        for arg in args {
+            writeln!(stdin, "{arg}")?;
+        }
+    }
+    collect(child.wait_with_output()?)
+}
+
+/// The message of a failed call, i.e. output starting with "Error: ".
+pub(crate) fn rpc_error(out: &str) -> Option<String> {
+    let msg = out.strip_prefix("Error: ")?;
+    let msg = msg.split_once("error message:").map(|(_, m)| m).unwrap_or(msg);
+    Some(msg.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Turns a call's output into `Ok(text)` or `Err(message)`.
+pub(crate) fn rpc_result(result: Result<String, Box<dyn std::error::Error>>) -> Result<String, String> {
+    let out = result.map_err(|e| e.to_string())?;
+    match rpc_error(&out) {
+        Some(e) => Err(e),
+        None => Ok(out),
+    }
+}
 
+fn base_command(wallet: Option<&str>) -> Command {
+    let rpc_user = std::env::var("RPC_USER").unwrap_or_else(|_| "youruser".to_string());
+    let rpc_password = std::env::var("RPC_PASSWORD").unwrap_or_else(|_| "yourpassword".to_string());
+
+    let mut cmd = Command::new("bitcoin-cli");
+    cmd.arg(format!("-rpcuser={}", rpc_user))
+        .arg(format!("-rpcpassword={}", rpc_password));
+    if let Some(w) = wallet {
+        cmd.arg(format!("-rpcwallet={}", w));
+    }
+    cmd
+}
+
+fn collect(output: std::process::Output) -> Result<String, Box<dyn std::error::Error>> {
     let out = if output.status.success() {
         String::from_utf8_lossy(&output.stdout).to_string()
     } else {
@@ -23,3 +86,48 @@ pub(crate) fn run_bitcoin_cli(command: &str) -> Result<String, Box<dyn std::erro
 
     Ok(out)
 }
+
+fn run(
+    wallet: Option<&str>,
+    method: &str,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let output = base_command(wallet)
+        .arg(method)
+        .args(args)
+        .output()?;
+
+    collect(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpc_errors() {
+        let out = "Error: error code: -14\nerror message:\nError: The wallet passphrase entered was incorrect.\n";
+        assert_eq!(rpc_error(out).unwrap(), "Error: The wallet passphrase entered was incorrect.");
+        assert_eq!(
+            rpc_error("Error: Could not connect to the server 127.0.0.1:8332\n\nMake sure the bitcoind server is running\n").unwrap(),
+            "Could not connect to the server 127.0.0.1:8332 Make sure the bitcoind server is running"
+        );
+        assert_eq!(rpc_error("{\n  \"name\": \"Error: not one\"\n}"), None);
+    }
+
+    #[test]
+    fn rpc_results() {
+        assert_eq!(rpc_result(Ok("800000\n".to_string())).unwrap(), "800000\n");
+        assert_eq!(rpc_result(Ok("Error: error message:\nWallet not found".to_string())).unwrap_err(), "Wallet not found");
+        assert_eq!(rpc_result(Err("No such file or directory".into())).unwrap_err(), "No such file or directory");
+    }
+
+    #[test]
+    fn wallet_argument() {
+        let args = |cmd: Command| cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        let with = args(base_command(Some("cold storage")));
+        assert_eq!(with.last().unwrap(), "-rpcwallet=cold storage");
+        assert_eq!(with.len(), 3);
+        assert!(!args(base_command(None)).iter().any(|a| a.starts_with("-rpcwallet")));
+    }
+}
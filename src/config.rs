@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fiat::FiatSettings;
+use crate::file::{with_suffix, write_atomic};
+use crate::privacy::PrivacySettings;
+use crate::theme::ThemeSpec;
+use crate::units::Unit;
+
+// ===== User settings (config.json) =====
+
+/// Settings changed from inside the TUI and kept between runs. Missing
+/// fields take their defaults, so older files keep working.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub unit: Unit,
+    pub privacy: PrivacySettings,
+    /// Active theme name; empty means the default (dark).
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub theme: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, ThemeSpec>,
+    /// Fiat values are off unless this section is present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatSettings>,
+    /// Why the file must not be written, when a broken one couldn't be
+    /// backed up.
+    #[serde(skip)]
+    pub read_only: Option<String>,
+}
+
+/// Loads the settings. A missing file gives the defaults. A broken one also
+/// does, with a warning, after copying it to `<file>.broken` so the next
+/// save can't lose it; if that copy fails the settings are never saved.
+pub(crate) fn load_settings(path: &Path) -> (Settings, Option<String>) {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Settings::default(), None),
+        Err(e) => {
+            let reason = format!("{}: {e}", path.display());
+            let settings = Settings { read_only: Some(reason.clone()), ..Settings::default() };
+            return (settings, Some(format!("{reason}, using default settings (not saved)")));
+        }
+    };
+    let error = match serde_json::from_slice(&data) {
+        Ok(s) => return (s, None),
+        Err(e) => format!("{}: {e}", path.display()),
+    };
+    let backup = with_suffix(path, ".broken");
+    match std::fs::write(&backup, &data) {
+        Ok(()) => (
+            Settings::default(),
+            Some(format!("{error}, using default settings (old file kept as {})", backup.display())),
+        ),
+        Err(e) => (
+            Settings { read_only: Some(error.clone()), ..Settings::default() },
+            Some(format!("{error}, using default settings (not saved: {}: {e})", backup.display())),
+        ),
+    }
+}
+
+pub(crate) fn save_settings(path: &Path, settings: &Settings) -> Result<(), String> {
+    if let Some(reason) = &settings.read_only {
+        return Err(format!("{reason}, fix or remove it first"));
+    }
+    let data = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    write_atomic(path, &data).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn round_trips_and_fills_defaults() {
+        let dir = temp_dir("config-ok");
+        let path = dir.join("config.json");
+        let (settings, warning) = load_settings(&path);
+        assert!(warning.is_none());
+        assert_eq!(settings.unit, Unit::Btc);
+
+        std::fs::write(&path, r#"{"unit": "sats", "privacy": {"txids": true}}"#).unwrap();
+        let (mut settings, warning) = load_settings(&path);
+        assert!(warning.is_none());
+        assert_eq!(settings.unit, Unit::Sats);
+        assert!(settings.privacy.txids && !settings.privacy.start_hidden);
+        assert!(settings.fiat.is_none());
+
+        settings.unit = Unit::Bits;
+        save_settings(&path, &settings).unwrap();
+        assert_eq!(load_settings(&path).0.unit, Unit::Bits);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_a_broken_file() {
+        let dir = temp_dir("config-broken");
+        let path = dir.join("config.json");
+        std::fs::write(&path, r#"{"unit": "sats", "fiat": {"source": "#).unwrap();
+        let (mut settings, warning) = load_settings(&path);
+        let warning = warning.unwrap();
+        assert!(warning.contains("old file kept as"), "{warning}");
+        assert_eq!(settings.unit, Unit::Btc);
+        assert!(settings.read_only.is_none());
+
+        settings.unit = Unit::Sats;
+        save_settings(&path, &settings).unwrap();
+        let broken = std::fs::read_to_string(dir.join("config.json.broken")).unwrap();
+        assert_eq!(broken, r#"{"unit": "sats", "fiat": {"source": "#);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_save_without_a_backup() {
+        let dir = temp_dir("config-readonly");
+        let path = dir.join("config.json");
+        std::fs::write(&path, "not json").unwrap();
+        // A directory where the backup would go makes the copy fail
+        std::fs::create_dir(dir.join("config.json.broken")).unwrap();
+        let (settings, warning) = load_settings(&path);
+        assert!(warning.unwrap().contains("not saved"));
+        assert!(save_settings(&path, &settings).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not json");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use bitcoin::hex::{DisplayHex, FromHex};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// ===== Encrypted address book =====
+
+const FORMAT: &str = "bitatui-encrypted-v1";
+const KDF: &str = "argon2id";
+
+// Argon2id defaults recommended by OWASP: 19 MiB, 2 passes, 1 lane.
+const M_COST_KIB: u32 = 19 * 1024;
+const T_COST: u32 = 2;
+const P_COST: u32 = 1;
+
+// Upper bounds for parameters read from a file, so a crafted file can't
+// make unlocking allocate gigabytes or spin for minutes.
+const MAX_M_COST_KIB: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 8;
+
+/// On-disk wrapper. The KDF parameters are stored so they can be raised
+/// later without breaking existing files.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    kdf: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Key derived from the passphrase, kept for the session so saving does
+/// not have to run the KDF again. Every save uses a fresh random nonce.
+#[derive(Clone)]
+pub(crate) struct BookCipher {
+    key: Zeroizing<[u8; 32]>,
+    salt: Vec<u8>,
+    params: (u32, u32, u32),
+}
+
+impl std::fmt::Debug for BookCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BookCipher(..)")
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], (m, t, p): (u32, u32, u32)) -> Result<Zeroizing<[u8; 32]>, String> {
+    let params = Params::new(m, t, p, Some(32)).map_err(|e| e.to_string())?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+/// Rejects KDF parameters outside what this app would ever write.
+fn check_params((m, t, p): (u32, u32, u32)) -> Result<(), String> {
+    if !(1..=MAX_P_COST).contains(&p) || !(1..=MAX_T_COST).contains(&t) || !(8 * p..=MAX_M_COST_KIB).contains(&m) {
+        return Err(format!("unsupported KDF parameters m={m} t={t} p={p}"));
+    }
+    Ok(())
+}
+
+/// True if `data` looks like an encrypted book rather than a JSON array.
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    serde_json::from_slice::<Envelope>(data).is_ok_and(|e| e.format == FORMAT)
+}
+
+impl BookCipher {
+    /// Derives a key with a new random salt, for encrypting a book the
+    /// first time.
+    pub(crate) fn create(passphrase: &str) -> Result<BookCipher, String> {
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let params = (M_COST_KIB, T_COST, P_COST);
+        let key = derive_key(passphrase, &salt, params)?;
+        Ok(BookCipher { key, salt, params })
+    }
+
+    /// Derives the key for an existing encrypted file and decrypts it.
+    /// A wrong passphrase and a tampered file both fail authentication.
+    pub(crate) fn unlock(data: &[u8], passphrase: &str) -> Result<(BookCipher, Vec<u8>), String> {
+        let env: Envelope = serde_json::from_slice(data).map_err(|e| e.to_string())?;
+        if env.format != FORMAT || env.kdf != KDF {
+            return Err(format!("unsupported format {} / {}", env.format, env.kdf));
+        }
+        let salt = Vec::<u8>::from_hex(&env.salt).map_err(|e| e.to_string())?;
+        let nonce = Vec::<u8>::from_hex(&env.nonce).map_err(|e| e.to_string())?;
+        let ciphertext = Vec::<u8>::from_hex(&env.ciphertext).map_err(|e| e.to_string())?;
+        if nonce.len() != 24 {
+            return Err("bad nonce length".to_string());
+        }
+
+        let params = (env.m_cost, env.t_cost, env.p_cost);
+        check_params(params)?;
+        let key = derive_key(passphrase, &salt, params)?;
+        let cipher = BookCipher { key, salt, params };
+        let plain = XChaCha20Poly1305::new(Key::from_slice(cipher.key.as_slice()))
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| "wrong passphrase or corrupted file".to_string())?;
+        Ok((cipher, plain))
+    }
+
+    /// Produces the encrypted envelope for `plain`.
+    pub(crate) fn seal(&self, plain: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; 24];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(self.key.as_slice()))
+            .encrypt(XNonce::from_slice(&nonce), plain)
+            .map_err(|e| e.to_string())?;
+        let env = Envelope {
+            format: FORMAT.to_string(),
+            kdf: KDF.to_string(),
+            m_cost: self.params.0,
+            t_cost: self.params.1,
+            p_cost: self.params.2,
+            salt: self.salt.to_lower_hex_string(),
+            nonce: nonce.to_lower_hex_string(),
+            ciphertext: ciphertext.to_lower_hex_string(),
+        };
+        serde_json::to_vec_pretty(&env).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters so the tests don't spend seconds in the KDF
+    const FAST: (u32, u32, u32) = (8, 1, 1);
+
+    fn cipher(passphrase: &str) -> BookCipher {
+        let salt = b"0123456789abcdef".to_vec();
+        let key = derive_key(passphrase, &salt, FAST).unwrap();
+        BookCipher { key, salt, params: FAST }
+    }
+
+    fn envelope(sealed: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(sealed).unwrap()
+    }
+
+    #[test]
+    fn key_depends_on_passphrase_only() {
+        let a = derive_key("hunter2", b"0123456789abcdef", FAST).unwrap();
+        let b = derive_key("hunter2", b"0123456789abcdef", FAST).unwrap();
+        let c = derive_key("hunter3", b"0123456789abcdef", FAST).unwrap();
+        assert_eq!(*a, *b);
+        assert_ne!(*a, *c);
+    }
+
+    #[test]
+    fn round_trip() {
+        let sealed = cipher("hunter2").seal(b"[]").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!is_encrypted(b"[]"));
+        let env = envelope(&sealed);
+        assert_eq!(env["format"], FORMAT);
+        assert_eq!(env["m_cost"], 8);
+
+        let (again, plain) = BookCipher::unlock(&sealed, "hunter2").unwrap();
+        assert_eq!(plain, b"[]");
+        // Every seal uses a fresh nonce
+        assert_ne!(envelope(&again.seal(b"[]").unwrap())["nonce"], env["nonce"]);
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase_and_tampering() {
+        let sealed = cipher("hunter2").seal(b"[1, 2, 3]").unwrap();
+        assert_eq!(BookCipher::unlock(&sealed, "hunter3").unwrap_err(), "wrong passphrase or corrupted file");
+
+        let mut env = envelope(&sealed);
+        let ct = env["ciphertext"].as_str().unwrap().to_string();
+        let flipped = if ct.starts_with('0') { "1" } else { "0" };
+        env["ciphertext"] = format!("{flipped}{}", &ct[1..]).into();
+        let tampered = serde_json::to_vec(&env).unwrap();
+        assert_eq!(BookCipher::unlock(&tampered, "hunter2").unwrap_err(), "wrong passphrase or corrupted file");
+    }
+
+    #[test]
+    fn rejects_out_of_range_params() {
+        let sealed = cipher("hunter2").seal(b"[]").unwrap();
+        for (key, value) in [
+            ("m_cost", MAX_M_COST_KIB + 1),
+            ("m_cost", 7),
+            ("t_cost", 0),
+            ("t_cost", MAX_T_COST + 1),
+            ("p_cost", 0),
+            ("p_cost", MAX_P_COST + 1),
+        ] {
+            let mut env = envelope(&sealed);
+            env[key] = value.into();
+            let err = BookCipher::unlock(&serde_json::to_vec(&env).unwrap(), "hunter2").unwrap_err();
+            assert!(err.starts_with("unsupported KDF parameters"), "{key}={value}: {err}");
+        }
+        assert!(check_params((M_COST_KIB, T_COST, P_COST)).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let mut env = envelope(&cipher("hunter2").seal(b"[]").unwrap());
+        env["kdf"] = "scrypt".into();
+        assert!(BookCipher::unlock(&serde_json::to_vec(&env).unwrap(), "hunter2").is_err());
+        env["kdf"] = KDF.into();
+        env["nonce"] = "00".into();
+        assert_eq!(BookCipher::unlock(&serde_json::to_vec(&env).unwrap(), "hunter2").unwrap_err(), "bad nonce length");
+    }
+}
@@ -0,0 +1,559 @@
+use core::str::FromStr;
+use std::sync::OnceLock;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath, Fingerprint, Xpub};
+use bitcoin::secp256k1::{PublicKey, Secp256k1, VerifyOnly, XOnlyPublicKey};
+use bitcoin::{Address, CompressedPublicKey, Network, NetworkKind};
+
+// ===== Output descriptor checksum (BIP-380) =====
+
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7_ffff_ffff) << 5) ^ val;
+    for (bit, k) in [
+        0xf5_dee5_1989,
+        0xa9_fdca_3312,
+        0x1b_ab10_e32d,
+        0x37_06b1_677a,
+        0x64_4d62_6ffd,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if c0 & (1 << bit) != 0 {
+            c ^= k;
+        }
+    }
+    c
+}
+
+/// The 8 character checksum of a descriptor without its `#` suffix.
+pub(crate) fn descriptor_checksum(desc: &str) -> Result<String, String> {
+    let mut c = 1u64;
+    let mut cls = 0u64;
+    let mut clscount = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| format!("invalid character '{ch}' in descriptor"))? as u64;
+        c = polymod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        clscount += 1;
+        if clscount == 3 {
+            c = polymod(c, cls);
+            cls = 0;
+            clscount = 0;
+        }
+    }
+    if clscount > 0 {
+        c = polymod(c, cls);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+// ===== Parsed descriptors =====
+
+/// Context shared by every derivation; creating one is not free.
+fn secp() -> &'static Secp256k1<VerifyOnly> {
+    static SECP: OnceLock<Secp256k1<VerifyOnly>> = OnceLock::new();
+    SECP.get_or_init(Secp256k1::verification_only)
+}
+
+/// Single-key script templates that can be derived offline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScriptKind {
+    Pkh,
+    ShWpkh,
+    Wpkh,
+    Tr,
+}
+
+impl ScriptKind {
+    pub const ALL: [ScriptKind; 4] = [ScriptKind::Pkh, ScriptKind::ShWpkh, ScriptKind::Wpkh, ScriptKind::Tr];
+
+    pub(crate) fn describe(self) -> &'static str {
+        match self {
+            ScriptKind::Pkh => "P2PKH (legacy, BIP44)",
+            ScriptKind::ShWpkh => "P2SH-P2WPKH (nested segwit, BIP49)",
+            ScriptKind::Wpkh => "P2WPKH (native segwit, BIP84)",
+            ScriptKind::Tr => "P2TR (taproot key path, BIP86)",
+        }
+    }
+
+    pub(crate) fn next(self) -> ScriptKind {
+        let i = ScriptKind::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        ScriptKind::ALL[(i + 1) % ScriptKind::ALL.len()]
+    }
+
+    fn wrap(self, key: &str) -> String {
+        match self {
+            ScriptKind::Pkh => format!("pkh({key})"),
+            ScriptKind::ShWpkh => format!("sh(wpkh({key}))"),
+            ScriptKind::Wpkh => format!("wpkh({key})"),
+            ScriptKind::Tr => format!("tr({key})"),
+        }
+    }
+}
+
+/// Receive (external) or change (internal) branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Chain {
+    Receive,
+    Change,
+}
+
+impl Chain {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Chain::Receive => "receive",
+            Chain::Change => "change",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum KeySource {
+    Xpub(Xpub),
+    Single(PublicKey),
+    XOnly(XOnlyPublicKey),
+}
+
+/// Result of comparing a pasted `#checksum` with the computed one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ChecksumState {
+    Missing,
+    Valid,
+    Mismatch(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ParsedDescriptor {
+    pub kind: ScriptKind,
+    /// `[fingerprint/path]` key origin, as written.
+    pub origin: Option<(Fingerprint, DerivationPath)>,
+    pub key: KeySource,
+    /// Steps below the key for receive addresses, without the final `*`.
+    pub receive: Vec<ChildNumber>,
+    /// Same for change; None if the descriptor has no change branch.
+    pub change: Option<Vec<ChildNumber>>,
+    pub ranged: bool,
+    /// Canonical form with checksum, usable with `importdescriptors`.
+    pub descriptor: String,
+    pub checksum: String,
+    pub checksum_state: ChecksumState,
+    /// Set when the input was a SLIP-132 key (ypub, zpub, ...) that was
+    /// converted to an xpub/tpub.
+    pub converted_from: Option<String>,
+}
+
+// SLIP-132 version bytes: (prefix, mainnet, implied script).
+const KEY_VERSIONS: [([u8; 4], bool, Option<ScriptKind>); 10] = [
+    ([0x04, 0x88, 0xb2, 0x1e], true, None),
+    ([0x04, 0x9d, 0x7c, 0xb2], true, Some(ScriptKind::ShWpkh)),
+    ([0x04, 0xb2, 0x47, 0x46], true, Some(ScriptKind::Wpkh)),
+    ([0x02, 0x95, 0xb4, 0x3f], true, Some(ScriptKind::ShWpkh)),
+    ([0x02, 0xaa, 0x7e, 0xd3], true, Some(ScriptKind::Wpkh)),
+    ([0x04, 0x35, 0x87, 0xcf], false, None),
+    ([0x04, 0x4a, 0x52, 0x62], false, Some(ScriptKind::ShWpkh)),
+    ([0x04, 0x5f, 0x1c, 0x35], false, Some(ScriptKind::Wpkh)),
+    ([0x02, 0x42, 0x89, 0xef], false, Some(ScriptKind::ShWpkh)),
+    ([0x02, 0x57, 0x54, 0x83], false, Some(ScriptKind::Wpkh)),
+];
+
+/// Decodes an extended public key in any SLIP-132 flavour. Returns the key
+/// and the script type its prefix implies (None for plain xpub/tpub).
+fn decode_xpub(s: &str) -> Result<(Xpub, Option<ScriptKind>), String> {
+    if s.get(1..).is_some_and(|r| r.starts_with("prv")) {
+        return Err("private keys are not accepted; use the public key".to_string());
+    }
+    let mut data = bitcoin::base58::decode_check(s).map_err(|e| format!("{s}: {e}"))?;
+    if data.len() != 78 {
+        return Err(format!("{s}: not an extended public key"));
+    }
+    let (_, mainnet, implied) = KEY_VERSIONS
+        .iter()
+        .find(|(v, _, _)| data[..4] == v[..])
+        .ok_or_else(|| format!("{s}: unknown key version"))?;
+    let standard = if *mainnet { KEY_VERSIONS[0].0 } else { KEY_VERSIONS[5].0 };
+    data[..4].copy_from_slice(&standard);
+    let xpub = Xpub::decode(&data).map_err(|e| e.to_string())?;
+    Ok((xpub, *implied))
+}
+
+/// Receive steps, change steps and whether the path ends in `*`.
+type Branches = (Vec<ChildNumber>, Option<Vec<ChildNumber>>, bool);
+
+/// Parses `/0/*`, `/<0;1>/*` style steps below the key.
+fn parse_steps(path: &str) -> Result<Branches, String> {
+    let mut receive = Vec::new();
+    let mut change: Option<Vec<ChildNumber>> = None;
+    let mut ranged = false;
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        if ranged {
+            return Err("'*' must be the last step".to_string());
+        }
+        if part == "*" {
+            ranged = true;
+            continue;
+        }
+        if part.ends_with(['\'', 'h', 'H']) {
+            return Err("hardened steps cannot be derived from a public key".to_string());
+        }
+        if let Some(multi) = part.strip_prefix('<').and_then(|p| p.strip_suffix('>')) {
+            let alts: Vec<&str> = multi.split(';').collect();
+            if alts.len() != 2 || change.is_some() {
+                return Err("only <receive;change> multipath is supported".to_string());
+            }
+            let step = |s: &str| ChildNumber::from_str(s).map_err(|e| e.to_string());
+            let mut c = receive.clone();
+            receive.push(step(alts[0])?);
+            c.push(step(alts[1])?);
+            change = Some(c);
+            continue;
+        }
+        let n = ChildNumber::from_str(part).map_err(|e| format!("{part}: {e}"))?;
+        receive.push(n);
+        if let Some(c) = change.as_mut() {
+            c.push(n);
+        }
+    }
+    // A plain .../0/* has its change branch at .../1/*
+    if change.is_none() && ranged && receive.last() == Some(&ChildNumber::Normal { index: 0 }) {
+        let mut c = receive.clone();
+        c.pop();
+        c.push(ChildNumber::Normal { index: 1 });
+        change = Some(c);
+    }
+    Ok((receive, change, ranged))
+}
+
+impl ParsedDescriptor {
+    /// Parses a single-key descriptor (pkh, sh(wpkh), wpkh, tr) or a bare
+    /// extended key. Bare keys get `/0/*` and `/1/*` branches and the script
+    /// type implied by their prefix, else `bare_kind`.
+    pub(crate) fn parse(input: &str, bare_kind: ScriptKind) -> Result<ParsedDescriptor, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("enter a descriptor or extended public key".to_string());
+        }
+        let (body, given) = match input.split_once('#') {
+            Some((b, c)) => (b, Some(c)),
+            None => (input, None),
+        };
+
+        let (kind, key_expr, converted_from) = if body.contains('(') {
+            let (kind, inner) = if let Some(k) = body.strip_prefix("sh(wpkh(").and_then(|b| b.strip_suffix("))")) {
+                (ScriptKind::ShWpkh, k)
+            } else if let Some(k) = body.strip_prefix("wpkh(").and_then(|b| b.strip_suffix(')')) {
+                (ScriptKind::Wpkh, k)
+            } else if let Some(k) = body.strip_prefix("pkh(").and_then(|b| b.strip_suffix(')')) {
+                (ScriptKind::Pkh, k)
+            } else if let Some(k) = body.strip_prefix("tr(").and_then(|b| b.strip_suffix(')')) {
+                if k.contains(',') {
+                    return Err("tr() with script paths is not supported".to_string());
+                }
+                (ScriptKind::Tr, k)
+            } else {
+                let func = body.split('(').next().unwrap_or(body);
+                return Err(format!("{func}() is not supported; use pkh, sh(wpkh), wpkh or tr"));
+            };
+            (kind, inner.to_string(), None)
+        } else {
+            // Bare key: normalise SLIP-132 prefixes and add the branches
+            let (xpub, implied) = decode_xpub(body)?;
+            let converted = (!body.starts_with("xpub") && !body.starts_with("tpub")).then(|| body[..4].to_string());
+            (implied.unwrap_or(bare_kind), format!("{xpub}/0/*"), converted)
+        };
+
+        // [fingerprint/origin/path]key/steps
+        let (origin, rest) = match key_expr.strip_prefix('[') {
+            Some(r) => {
+                let (o, rest) = r.split_once(']').ok_or("unterminated key origin")?;
+                let (fp, path) = o.split_once('/').unwrap_or((o, ""));
+                let fp = Fingerprint::from_str(fp).map_err(|e| format!("origin fingerprint: {e}"))?;
+                let path = if path.is_empty() { "m".to_string() } else { format!("m/{path}") };
+                let path = DerivationPath::from_str(&path).map_err(|e| format!("origin path: {e}"))?;
+                (Some((fp, path)), rest.to_string())
+            }
+            None => (None, key_expr.clone()),
+        };
+        let (key_str, steps) = rest.split_once('/').unwrap_or((rest.as_str(), ""));
+
+        let (key, converted_from) = if key_str.len() == 66 && key_str.chars().all(|c| c.is_ascii_hexdigit()) {
+            let pk = PublicKey::from_str(key_str).map_err(|e| e.to_string())?;
+            (KeySource::Single(pk), converted_from)
+        } else if key_str.len() == 64 && key_str.chars().all(|c| c.is_ascii_hexdigit()) {
+            if kind != ScriptKind::Tr {
+                return Err("x-only keys are only valid in tr()".to_string());
+            }
+            let pk = XOnlyPublicKey::from_str(key_str).map_err(|e| e.to_string())?;
+            (KeySource::XOnly(pk), converted_from)
+        } else {
+            let (xpub, _) = decode_xpub(key_str)?;
+            let converted = converted_from.or_else(|| {
+                (!key_str.starts_with("xpub") && !key_str.starts_with("tpub")).then(|| key_str[..4].to_string())
+            });
+            (KeySource::Xpub(xpub), converted)
+        };
+        let (receive, change, ranged) = if matches!(key, KeySource::Xpub(_)) {
+            parse_steps(steps)?
+        } else if steps.is_empty() {
+            (Vec::new(), None, false)
+        } else {
+            return Err("derivation steps need an extended key".to_string());
+        };
+
+        // Rebuild a canonical descriptor with the standard key encoding
+        let key_text = match &key {
+            KeySource::Xpub(x) => x.to_string(),
+            KeySource::Single(pk) => pk.to_string(),
+            KeySource::XOnly(pk) => pk.to_string(),
+        };
+        let origin_text = origin
+            .as_ref()
+            .map(|(fp, p)| match p.is_empty() {
+                true => format!("[{fp}]"),
+                false => format!("[{fp}/{p}]"),
+            })
+            .unwrap_or_default();
+        let steps_text = if steps.is_empty() { String::new() } else { format!("/{steps}") };
+        let canonical = kind.wrap(&format!("{origin_text}{key_text}{steps_text}"));
+        let checksum = descriptor_checksum(&canonical)?;
+        let checksum_state = match given {
+            None => ChecksumState::Missing,
+            // The checksum covers the text as typed, not the canonical form
+            Some(c) if descriptor_checksum(body)? == c => ChecksumState::Valid,
+            Some(c) => ChecksumState::Mismatch(c.to_string()),
+        };
+
+        Ok(ParsedDescriptor {
+            kind,
+            origin,
+            key,
+            receive,
+            change,
+            ranged,
+            descriptor: format!("{canonical}#{checksum}"),
+            checksum,
+            checksum_state,
+            converted_from,
+        })
+    }
+
+    /// Network kind fixed by the key, if it is an extended key.
+    pub(crate) fn network_kind(&self) -> Option<NetworkKind> {
+        match &self.key {
+            KeySource::Xpub(x) => Some(x.network),
+            _ => None,
+        }
+    }
+
+    /// Address at `index` on `chain`. Non-ranged descriptors only have
+    /// index 0 on the receive chain.
+    pub(crate) fn derive(&self, chain: Chain, index: u32, network: Network) -> Result<Address, String> {
+        if !self.ranged && index > 0 {
+            return Err("descriptor is not ranged".to_string());
+        }
+        let pk = match &self.key {
+            KeySource::Xpub(xpub) => {
+                let mut path = self.steps(chain)?.clone();
+                if self.ranged {
+                    path.push(ChildNumber::from_normal_idx(index).map_err(|e| e.to_string())?);
+                }
+                xpub.derive_pub(secp(), &path).map_err(|e| e.to_string())?.public_key
+            }
+            KeySource::Single(pk) => *pk,
+            KeySource::XOnly(x) => return Ok(Address::p2tr(secp(), *x, None, network)),
+        };
+        Ok(self.address(pk, network))
+    }
+
+    fn steps(&self, chain: Chain) -> Result<&Vec<ChildNumber>, String> {
+        match chain {
+            Chain::Receive => Ok(&self.receive),
+            Chain::Change => self.change.as_ref().ok_or_else(|| "descriptor has no change branch".to_string()),
+        }
+    }
+
+    fn address(&self, pk: PublicKey, network: Network) -> Address {
+        let compressed = CompressedPublicKey(pk);
+        match self.kind {
+            ScriptKind::Pkh => Address::p2pkh(compressed, network),
+            ScriptKind::ShWpkh => Address::p2shwpkh(&compressed, network),
+            ScriptKind::Wpkh => Address::p2wpkh(&compressed, network),
+            ScriptKind::Tr => Address::p2tr(secp(), pk.x_only_public_key().0, None, network),
+        }
+    }
+
+    /// Looks for `address` among the first `gap` indexes of both chains.
+    /// Each branch key is derived once, so this costs one step per index.
+    pub(crate) fn find(&self, address: &str, gap: u32, network: Network) -> Option<(Chain, u32)> {
+        let target = address.trim();
+        let matches = |a: Address| a.to_string().eq_ignore_ascii_case(target);
+        let KeySource::Xpub(xpub) = &self.key else {
+            return self.derive(Chain::Receive, 0, network).is_ok_and(matches).then_some((Chain::Receive, 0));
+        };
+        for chain in [Chain::Receive, Chain::Change] {
+            let Ok(steps) = self.steps(chain) else {
+                continue;
+            };
+            let Ok(branch) = xpub.derive_pub(secp(), steps) else {
+                continue;
+            };
+            if !self.ranged {
+                if matches(self.address(branch.public_key, network)) {
+                    return Some((chain, 0));
+                }
+                continue;
+            }
+            for i in 0..gap {
+                let Ok(child) = ChildNumber::from_normal_idx(i).and_then(|c| branch.ckd_pub(secp(), c)) else {
+                    break;
+                };
+                if matches(self.address(child.public_key, network)) {
+                    return Some((chain, i));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Account keys of the "abandon ... about" test mnemonic from BIP44/49/84/86
+    const BIP44_XPUB: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+    const BIP49_YPUB: &str = "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP";
+    const BIP84_ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+    const BIP84_XPUB: &str = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+    const BIP86_XPUB: &str = "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ";
+
+    fn parse(input: &str) -> ParsedDescriptor {
+        ParsedDescriptor::parse(input, ScriptKind::Wpkh).unwrap()
+    }
+
+    fn address(p: &ParsedDescriptor, chain: Chain, index: u32) -> String {
+        p.derive(chain, index, Network::Bitcoin).unwrap().to_string()
+    }
+
+    #[test]
+    fn checksum_vectors() {
+        // BIP-380
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert!(descriptor_checksum("raw(deadbeef)é").unwrap_err().contains("invalid character"));
+    }
+
+    #[test]
+    fn checksum_state() {
+        let desc = format!("wpkh({BIP84_XPUB}/0/*)");
+        let sum = descriptor_checksum(&desc).unwrap();
+        assert_eq!(parse(&desc).checksum_state, ChecksumState::Missing);
+        assert_eq!(parse(&format!("{desc}#{sum}")).checksum_state, ChecksumState::Valid);
+        assert_eq!(parse(&format!("{desc}#{sum}")).descriptor, format!("{desc}#{sum}"));
+        assert_eq!(
+            parse(&format!("{desc}#qqqqqqqq")).checksum_state,
+            ChecksumState::Mismatch("qqqqqqqq".to_string())
+        );
+    }
+
+    #[test]
+    fn bip_account_vectors() {
+        let p = parse(&format!("pkh({BIP44_XPUB}/0/*)"));
+        assert_eq!(address(&p, Chain::Receive, 0), "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+
+        let p = parse(&format!("wpkh({BIP84_XPUB}/0/*)"));
+        assert_eq!(address(&p, Chain::Receive, 0), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(address(&p, Chain::Receive, 1), "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g");
+        assert_eq!(address(&p, Chain::Change, 0), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+
+        let p = parse(&format!("tr({BIP86_XPUB}/0/*)"));
+        assert_eq!(address(&p, Chain::Receive, 0), "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+        assert_eq!(address(&p, Chain::Change, 0), "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7");
+    }
+
+    #[test]
+    fn slip132_keys() {
+        let p = parse(BIP84_ZPUB);
+        assert_eq!(p.kind, ScriptKind::Wpkh);
+        assert_eq!(p.converted_from.as_deref(), Some("zpub"));
+        assert!(p.descriptor.starts_with(&format!("wpkh({BIP84_XPUB}/0/*)#")));
+        assert_eq!(address(&p, Chain::Receive, 0), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+
+        // The prefix decides the script type of a bare key
+        let p = parse(BIP49_YPUB);
+        assert_eq!(p.kind, ScriptKind::ShWpkh);
+        assert_eq!(address(&p, Chain::Receive, 0), "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf");
+
+        // A bare xpub uses the chosen type
+        let p = ParsedDescriptor::parse(BIP44_XPUB, ScriptKind::Pkh).unwrap();
+        assert_eq!((p.kind, p.converted_from.clone()), (ScriptKind::Pkh, None));
+        assert_eq!(address(&p, Chain::Receive, 0), "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+    }
+
+    #[test]
+    fn branches() {
+        let p = parse(&format!("wpkh([73c5da0a/84h/0h/0h]{BIP84_XPUB}/<0;1>/*)"));
+        assert_eq!(p.receive, [ChildNumber::Normal { index: 0 }]);
+        assert_eq!(p.change, Some(vec![ChildNumber::Normal { index: 1 }]));
+        assert_eq!(p.origin.as_ref().unwrap().1.to_string(), "84'/0'/0'");
+        assert_eq!(address(&p, Chain::Change, 0), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+
+        // No change branch below anything but .../0/*
+        let p = parse(&format!("wpkh({BIP84_XPUB}/5/*)"));
+        assert!(p.change.is_none());
+        assert!(p.derive(Chain::Change, 0, Network::Bitcoin).is_err());
+
+        let p = parse(&format!("wpkh({BIP84_XPUB}/0/0)"));
+        assert!(!p.ranged);
+        assert_eq!(address(&p, Chain::Receive, 0), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert!(p.derive(Chain::Receive, 1, Network::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn rejects() {
+        for (input, err) in [
+            (format!("wpkh({BIP84_XPUB}/0h/*)"), "hardened"),
+            (format!("wpkh({BIP84_XPUB}/*/0)"), "last step"),
+            (format!("sh(multi(1,{BIP84_XPUB}))"), "not supported"),
+            (format!("tr({BIP86_XPUB},pk({BIP86_XPUB}))"), "script paths"),
+            ("xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi".to_string(), "private keys"),
+            ("wpkh(79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)".to_string(), "x-only"),
+            ("".to_string(), "enter a descriptor"),
+        ] {
+            let e = ParsedDescriptor::parse(&input, ScriptKind::Wpkh).unwrap_err();
+            assert!(e.contains(err), "{input}: {e}");
+        }
+    }
+
+    #[test]
+    fn finds_addresses() {
+        let p = parse(&format!("wpkh({BIP84_XPUB}/0/*)"));
+        let change = address(&p, Chain::Change, 7);
+        assert_eq!(p.find(&change, 20, Network::Bitcoin), Some((Chain::Change, 7)));
+        assert_eq!(p.find(&change.to_uppercase(), 20, Network::Bitcoin), Some((Chain::Change, 7)));
+        assert_eq!(p.find(&change, 7, Network::Bitcoin), None);
+        assert_eq!(
+            p.find("bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g", 20, Network::Bitcoin),
+            Some((Chain::Receive, 1))
+        );
+        // Same key, wrong network
+        assert_eq!(p.find("bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g", 20, Network::Testnet), None);
+
+        let single = parse("wpkh(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)");
+        let a = address(&single, Chain::Receive, 0);
+        assert_eq!(a, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(single.find(&a, 20, Network::Bitcoin), Some((Chain::Receive, 0)));
+    }
+}
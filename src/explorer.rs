@@ -0,0 +1,563 @@
+use std::sync::mpsc::{self, Receiver};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use bitcoin::{Network, NetworkKind};
+
+use crate::descriptor::{Chain, ChecksumState, KeySource, ParsedDescriptor, ScriptKind};
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::{centered_rect, copy_to_clipboard};
+use crate::theme::Theme;
+
+// ===== Descriptor / xpub explorer =====
+
+/// How far both chains are searched by the "belongs to" check.
+const VERIFY_GAP: u32 = 1000;
+const MAX_COUNT: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExplorerFocus {
+    Descriptor,
+    Verify,
+    List,
+}
+
+/// What the explorer asks the main loop to do after a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ExplorerAction {
+    None,
+    Close,
+    /// Show the address in the QR overlay.
+    ShowQr(String),
+    /// Save the address to the book: (address, descriptor, note).
+    Save(String, String, String),
+}
+
+/// Running "belongs to" check: the address, the descriptor it runs
+/// against and where the result arrives.
+type Check = (String, String, Receiver<Option<(Chain, u32)>>);
+
+pub(crate) struct DescriptorExplorer {
+    pub input: TextInput,
+    pub verify: TextInput,
+    pub focus: ExplorerFocus,
+    pub parsed: Option<ParsedDescriptor>,
+    pub error: Option<String>,
+    /// Script type used for bare xpub/tpub keys (Ctrl+T).
+    pub bare_kind: ScriptKind,
+    pub network: Network,
+    pub chain: Chain,
+    pub count: u32,
+    pub selected: u32,
+    pub status: String,
+    checking: Option<Check>,
+}
+
+impl DescriptorExplorer {
+    pub(crate) fn new() -> Self {
+        Self {
+            input: TextInput::new("", false),
+            verify: TextInput::new("", false),
+            focus: ExplorerFocus::Descriptor,
+            parsed: None,
+            error: None,
+            bare_kind: ScriptKind::Wpkh,
+            network: Network::Bitcoin,
+            chain: Chain::Receive,
+            count: 10,
+            selected: 0,
+            status: String::new(),
+            checking: None,
+        }
+    }
+
+    /// Re-parses the input. The network follows the key: xpubs are mainnet,
+    /// tpubs keep a test network chosen with Ctrl+N.
+    fn parse(&mut self) {
+        self.status.clear();
+        match ParsedDescriptor::parse(&self.input.value, self.bare_kind) {
+            Ok(p) => {
+                match p.network_kind() {
+                    Some(NetworkKind::Main) => self.network = Network::Bitcoin,
+                    Some(NetworkKind::Test) if self.network == Network::Bitcoin => {
+                        self.network = Network::Testnet;
+                    }
+                    _ => {}
+                }
+                if p.change.is_none() {
+                    self.chain = Chain::Receive;
+                }
+                self.selected = 0;
+                self.parsed = Some(p);
+                self.error = None;
+                self.focus = ExplorerFocus::List;
+            }
+            Err(e) => {
+                self.parsed = None;
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Networks selectable for the current key.
+    fn cycle_network(&mut self) {
+        let options: &[Network] = match self.parsed.as_ref().and_then(|p| p.network_kind()) {
+            Some(NetworkKind::Main) => &[Network::Bitcoin],
+            Some(NetworkKind::Test) => &[Network::Testnet, Network::Signet, Network::Regtest],
+            None => &[Network::Bitcoin, Network::Testnet, Network::Signet, Network::Regtest],
+        };
+        let i = options.iter().position(|n| *n == self.network).map(|i| i + 1).unwrap_or(0);
+        self.network = options[i % options.len()];
+    }
+
+    fn visible_count(&self) -> u32 {
+        match &self.parsed {
+            Some(p) if p.ranged => self.count,
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
+    fn selected_address(&self) -> Option<String> {
+        let p = self.parsed.as_ref()?;
+        p.derive(self.chain, self.selected, self.network).ok().map(|a| a.to_string())
+    }
+
+    /// Starts searching both chains for the typed address on a background
+    /// thread; deriving thousands of keys would stall the UI.
+    fn check_verify(&mut self) {
+        let Some(p) = &self.parsed else {
+            self.status = "Parse a descriptor first".to_string();
+            return;
+        };
+        if self.checking.is_some() {
+            self.status = "Check already running".to_string();
+            return;
+        }
+        let addr = self.verify.value.trim().to_string();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn({
+            let (p, addr, network) = (p.clone(), addr.clone(), self.network);
+            move || {
+                let _ = tx.send(p.find(&addr, VERIFY_GAP, network));
+            }
+        });
+        self.status = format!("Checking the first {VERIFY_GAP} receive/change addresses…");
+        self.checking = Some((addr, p.descriptor.clone(), rx));
+    }
+
+    /// Picks up a finished check. Called once per main loop iteration.
+    pub(crate) fn poll(&mut self) {
+        let Some((addr, descriptor, rx)) = &self.checking else {
+            return;
+        };
+        let Ok(found) = rx.try_recv() else {
+            return;
+        };
+        // Ignore the result if another descriptor was parsed meanwhile
+        if self.parsed.as_ref().is_some_and(|p| &p.descriptor == descriptor) {
+            self.status = match found {
+                Some((chain, i)) => {
+                    self.chain = chain;
+                    self.selected = i;
+                    self.count = self.count.max(i + 1);
+                    format!("✔ {addr} is {} #{i}", chain.label())
+                }
+                None => format!("✘ not found in the first {VERIFY_GAP} receive/change addresses"),
+            };
+        }
+        self.checking = None;
+    }
+
+    /// Pasted text goes to the focused input.
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        match self.focus {
+            ExplorerFocus::Descriptor => self.input.insert_str(text.trim()),
+            ExplorerFocus::Verify => self.verify.insert_str(text.trim()),
+            ExplorerFocus::List => {}
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> ExplorerAction {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let action = keymap.action(Scope::Explorer, key);
+        match (action, key.code) {
+            (Some(Action::ExplorerClose), _) | (_, KeyCode::Esc) => return ExplorerAction::Close,
+            (Some(Action::ExplorerScriptType), _) => {
+                self.bare_kind = self.bare_kind.next();
+                if self.parsed.is_some() {
+                    self.parse();
+                }
+            }
+            (Some(Action::ExplorerNetwork), _) => self.cycle_network(),
+            (_, KeyCode::Tab | KeyCode::BackTab) => {
+                self.focus = match self.focus {
+                    ExplorerFocus::Descriptor => ExplorerFocus::Verify,
+                    ExplorerFocus::Verify => ExplorerFocus::List,
+                    ExplorerFocus::List => ExplorerFocus::Descriptor,
+                };
+            }
+            _ => match self.focus {
+                ExplorerFocus::Descriptor => match key.code {
+                    KeyCode::Enter => self.parse(),
+                    code if !ctrl => {
+                        self.input.handle_key(code);
+                    }
+                    _ => {}
+                },
+                ExplorerFocus::Verify => match key.code {
+                    KeyCode::Enter => self.check_verify(),
+                    code if !ctrl => {
+                        self.verify.handle_key(code);
+                    }
+                    _ => {}
+                },
+                ExplorerFocus::List => return self.handle_list_key(key.code, action),
+            },
+        }
+        ExplorerAction::None
+    }
+
+    fn handle_list_key(&mut self, code: KeyCode, action: Option<Action>) -> ExplorerAction {
+        let visible = self.visible_count();
+        match (action, code) {
+            (Some(Action::ExplorerMore), _) => self.count = (self.count + 10).min(MAX_COUNT),
+            (Some(Action::ExplorerFewer), _) => {
+                self.count = self.count.saturating_sub(10).max(10);
+                self.selected = self.selected.min(self.count - 1);
+            }
+            (Some(Action::ExplorerCopy), _) => {
+                if let Some(a) = self.selected_address() {
+                    self.status = match copy_to_clipboard(&a) {
+                        Ok(()) => "Copied".to_string(),
+                        Err(e) => format!("Copy failed: {e}"),
+                    };
+                }
+            }
+            (Some(Action::ExplorerAddToBook), _) => {
+                if let (Some(a), Some(p)) = (self.selected_address(), &self.parsed) {
+                    let note = format!("{} #{}", self.chain.label(), self.selected);
+                    return ExplorerAction::Save(a, p.descriptor.clone(), note);
+                }
+            }
+            (_, KeyCode::Up) => self.selected = self.selected.saturating_sub(1),
+            (_, KeyCode::Down) if self.selected + 1 < visible => self.selected += 1,
+            (_, KeyCode::Left | KeyCode::Right) if self.parsed.as_ref().is_some_and(|p| p.change.is_some()) => {
+                self.chain = match self.chain {
+                    Chain::Receive => Chain::Change,
+                    Chain::Change => Chain::Receive,
+                };
+            }
+            (_, KeyCode::Enter) => {
+                if let Some(a) = self.selected_address() {
+                    return ExplorerAction::ShowQr(a);
+                }
+            }
+            _ => {}
+        }
+        ExplorerAction::None
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(85, 80, size)
+    }
+
+    pub(crate) fn draw(&self, f: &mut Frame, size: Rect, keymap: &Keymap, theme: &Theme) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(" Descriptor / xpub explorer (offline) ");
+        if !self.status.is_empty() {
+            outer = outer.title_bottom(Span::styled(
+                format!(" {} ", self.status),
+                theme.warn(),
+            ));
+        }
+        f.render_widget(outer, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(9),
+                Constraint::Length(3),
+                Constraint::Min(3),
+            ])
+            .split(area);
+
+        let focused = |on: bool| {
+            if on {
+                theme.focused()
+            } else {
+                theme.muted()
+            }
+        };
+
+        // Descriptor input
+        let input_title = match &self.error {
+            Some(_) => Span::styled(" Descriptor or xpub — INVALID (Enter = parse) ", theme.error()),
+            None => Span::raw(" Descriptor or xpub/ypub/zpub (Enter = parse) "),
+        };
+        f.render_widget(
+            Paragraph::new(self.input.value.clone()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(focused(self.focus == ExplorerFocus::Descriptor))
+                    .title(input_title),
+            ),
+            rows[0],
+        );
+
+        // Parsed structure
+        let info: Vec<Line> = match (&self.parsed, &self.error) {
+            (_, Some(e)) => vec![Line::from(Span::styled(e.clone(), theme.error()))],
+            (Some(p), None) => structure_lines(p, self.network, theme),
+            (None, None) => vec![
+                Line::from("Paste a descriptor, e.g. wpkh([d34db33f/84'/0'/0']xpub.../0/*)"),
+                Line::from("or an account xpub/ypub/zpub. Nothing is sent to the node."),
+                Line::from(format!(
+                    "Bare xpub/tpub keys are read as {}{}.",
+                    self.bare_kind.describe(),
+                    keymap.note(&[Action::ExplorerScriptType])
+                )),
+            ],
+        };
+        f.render_widget(
+            Paragraph::new(info)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title(" Structure ")),
+            rows[1],
+        );
+
+        // Verify input
+        f.render_widget(
+            Paragraph::new(self.verify.value.clone()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(focused(self.focus == ExplorerFocus::Verify))
+                    .title(format!(" Belongs to this descriptor? (Enter = check first {VERIFY_GAP}) ")),
+            ),
+            rows[2],
+        );
+
+        // Derived addresses
+        let items: Vec<ListItem> = match &self.parsed {
+            Some(p) => (0..self.visible_count())
+                .map(|i| {
+                    let text = match p.derive(self.chain, i, self.network) {
+                        Ok(a) => format!("#{i:<4} {a}"),
+                        Err(e) => format!("#{i:<4} {e}"),
+                    };
+                    ListItem::new(text)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut state = ListState::default();
+        if !items.is_empty() {
+            state.select(Some(self.selected as usize));
+        }
+        let list_keys: Vec<String> =
+            [Action::ExplorerMore, Action::ExplorerFewer, Action::ExplorerAddToBook, Action::ExplorerCopy]
+                .iter()
+                .filter_map(|&a| keymap.hint(a))
+                .collect();
+        let list = List::new(items)
+            .highlight_style(theme.selected())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(focused(self.focus == ExplorerFocus::List))
+                    .title(format!(
+                        " {} addresses ({}) • ←/→ receive/change • Enter=QR • {} ",
+                        self.chain.label(),
+                        self.network,
+                        list_keys.join(" • ")
+                    )),
+            );
+        f.render_stateful_widget(list, rows[3], &mut state);
+
+        // Cursor in the focused input
+        let (field, rect) = match self.focus {
+            ExplorerFocus::Descriptor => (&self.input, rows[0]),
+            ExplorerFocus::Verify => (&self.verify, rows[2]),
+            ExplorerFocus::List => return,
+        };
+        let cursor_x = (rect.x + 1).saturating_add(field.cursor as u16);
+        f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+    }
+}
+
+fn structure_lines(p: &ParsedDescriptor, network: Network, theme: &Theme) -> Vec<Line<'static>> {
+    let steps = |s: &[bitcoin::bip32::ChildNumber]| {
+        let mut out: String = s.iter().map(|c| format!("/{c}")).collect();
+        if p.ranged {
+            out.push_str("/*");
+        }
+        out
+    };
+    let checksum = match &p.checksum_state {
+        ChecksumState::Missing => Span::raw(format!("{} (computed)", p.checksum)),
+        ChecksumState::Valid => Span::styled(format!("{} ✔", p.checksum), theme.ok()),
+        ChecksumState::Mismatch(given) => Span::styled(
+            format!("#{given} is wrong, expected #{}", p.checksum),
+            theme.error(),
+        ),
+    };
+    let mut lines = vec![
+        Line::from(format!("Descriptor: {}", p.descriptor)),
+        Line::from(vec![Span::raw("Checksum:   "), checksum]),
+        Line::from(format!("Script:     {}", p.kind.describe())),
+    ];
+    if let Some((fp, path)) = &p.origin {
+        lines.push(Line::from(format!("Origin:     master {fp}, path m/{path}")));
+    }
+    match &p.key {
+        KeySource::Xpub(x) => {
+            lines.push(Line::from(format!(
+                "Key:        extended, depth {}, parent {}, child {}, fingerprint {}",
+                x.depth,
+                x.parent_fingerprint,
+                x.child_number,
+                x.fingerprint()
+            )));
+            if let Some(prefix) = &p.converted_from {
+                lines.push(Line::from(format!("            converted from {prefix} (SLIP-132)")));
+            }
+        }
+        KeySource::Single(pk) => lines.push(Line::from(format!("Key:        single {pk}"))),
+        KeySource::XOnly(pk) => lines.push(Line::from(format!("Key:        x-only {pk}"))),
+    }
+    let branches = match &p.change {
+        Some(c) => format!("receive {} • change {}", steps(&p.receive), steps(c)),
+        None if p.ranged => format!("receive {} (no change branch)", steps(&p.receive)),
+        None => "single address (not ranged)".to_string(),
+    };
+    lines.push(Line::from(format!("Branches:   {branches} • network {network}")));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ctrl, key};
+
+    // BIP84 account key of the "abandon ... about" test mnemonic
+    const XPUB: &str = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+
+    fn explorer(input: &str) -> DescriptorExplorer {
+        let mut e = DescriptorExplorer::new();
+        e.handle_paste(input);
+        e.handle_key(key(KeyCode::Enter), &Keymap::defaults());
+        e
+    }
+
+    /// The same key with testnet version bytes.
+    fn tpub(xpub: &str) -> String {
+        let mut data = bitcoin::base58::decode_check(xpub).unwrap();
+        data[..4].copy_from_slice(&[0x04, 0x35, 0x87, 0xcf]);
+        bitcoin::base58::encode_check(&data)
+    }
+
+    #[test]
+    fn parses_and_lists() {
+        let mut e = explorer(XPUB);
+        assert!(e.error.is_none());
+        assert_eq!((e.focus, e.network), (ExplorerFocus::List, Network::Bitcoin));
+        assert_eq!(e.selected_address().unwrap(), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+
+        let keymap = Keymap::defaults();
+        e.handle_key(key(KeyCode::Down), &keymap);
+        e.handle_key(key(KeyCode::Right), &keymap);
+        assert_eq!((e.chain, e.selected), (Chain::Change, 1));
+        assert_eq!(
+            e.handle_key(key(KeyCode::Enter), &keymap),
+            ExplorerAction::ShowQr(e.selected_address().unwrap())
+        );
+        let ExplorerAction::Save(_, descriptor, note) = e.handle_key(key(KeyCode::Char('a')), &keymap) else {
+            panic!("not saved");
+        };
+        assert!(descriptor.starts_with(&format!("wpkh({XPUB}/0/*)#")));
+        assert_eq!(note, "change #1");
+        assert_eq!(e.handle_key(key(KeyCode::Esc), &keymap), ExplorerAction::Close);
+    }
+
+    #[test]
+    fn count_is_bounded() {
+        let mut e = explorer(XPUB);
+        let keymap = Keymap::defaults();
+        for _ in 0..20 {
+            e.handle_key(key(KeyCode::Char('+')), &keymap);
+        }
+        assert_eq!(e.count, MAX_COUNT);
+        e.selected = 50;
+        for _ in 0..20 {
+            e.handle_key(key(KeyCode::Char('-')), &keymap);
+        }
+        assert_eq!((e.count, e.selected), (10, 9));
+    }
+
+    #[test]
+    fn network_follows_the_key() {
+        let keymap = Keymap::defaults();
+        let ctrl_n = ctrl('n');
+        let mut e = explorer(XPUB);
+        e.handle_key(ctrl_n, &keymap);
+        assert_eq!(e.network, Network::Bitcoin);
+
+        let mut e = explorer(&tpub(XPUB));
+        assert_eq!(e.network, Network::Testnet);
+        e.handle_key(ctrl_n, &keymap);
+        assert_eq!(e.network, Network::Signet);
+        assert!(e.selected_address().unwrap().starts_with("tb1q"));
+
+        let e = explorer("wpkh(xpub_broken/0/*)");
+        assert!(e.parsed.is_none() && e.error.is_some());
+        assert_eq!(e.focus, ExplorerFocus::Descriptor);
+    }
+
+    fn wait(e: &mut DescriptorExplorer) {
+        while e.checking.is_some() {
+            e.poll();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn checks_in_the_background() {
+        let mut e = explorer(XPUB);
+        e.verify.set("bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+        e.check_verify();
+        e.check_verify();
+        assert_eq!(e.status, "Check already running");
+        wait(&mut e);
+        assert_eq!(e.status, "✔ bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el is change #0");
+        assert_eq!((e.chain, e.selected), (Chain::Change, 0));
+
+        e.verify.set("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        e.check_verify();
+        wait(&mut e);
+        assert_eq!(e.status, format!("✘ not found in the first {VERIFY_GAP} receive/change addresses"));
+
+        // A result for a descriptor that was replaced meanwhile is dropped
+        e.check_verify();
+        e.focus = ExplorerFocus::Descriptor;
+        e.input.set(&tpub(XPUB));
+        e.handle_key(key(KeyCode::Enter), &Keymap::defaults());
+        wait(&mut e);
+        assert!(e.status.is_empty());
+    }
+}
@@ -0,0 +1,402 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use bitcoin::Amount;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::file::write_atomic;
+use crate::units::group_thousands;
+
+// ===== Fiat conversion =====
+
+/// The `fiat` section of config.json. Without it nothing is fetched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct FiatSettings {
+    /// Currency code shown next to values, e.g. "EUR".
+    pub currency: String,
+    /// `http(s)://` URL fetched with curl, or a local file path.
+    pub source: String,
+    /// Dot-separated path to the BTC price in the JSON, e.g. "bitcoin.eur".
+    /// Empty when the document is just the number.
+    pub path: String,
+    pub refresh_minutes: u64,
+    /// Rates older than this are flagged as stale.
+    pub stale_minutes: u64,
+}
+
+impl Default for FiatSettings {
+    fn default() -> Self {
+        Self {
+            currency: "EUR".to_string(),
+            source: String::new(),
+            path: String::new(),
+            refresh_minutes: 10,
+            stale_minutes: 60,
+        }
+    }
+}
+
+/// Last good rate, cached in the data dir so it survives restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FiatRate {
+    pub currency: String,
+    /// Price of 1 BTC.
+    pub rate: f64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Follows `path` through objects (by key) and arrays (by index) and reads
+/// the number there; numeric strings are accepted too.
+fn extract(json: &serde_json::Value, path: &str) -> Result<f64, String> {
+    let mut v = json;
+    for key in path.split('.').filter(|k| !k.is_empty()) {
+        v = match v {
+            serde_json::Value::Array(a) => key.parse::<usize>().ok().and_then(|i| a.get(i)),
+            _ => v.get(key),
+        }
+        .ok_or_else(|| format!("no \"{key}\" in the price JSON (path \"{path}\")"))?;
+    }
+    let rate = match v {
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        other => other.as_f64(),
+    }
+    .ok_or_else(|| format!("\"{path}\" is not a number"))?;
+    if rate.is_finite() && rate > 0.0 {
+        Ok(rate)
+    } else {
+        Err(format!("invalid rate {rate}"))
+    }
+}
+
+fn curl_error(e: std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => "curl not found; install curl or set \"source\" to a local file".to_string(),
+        _ => format!("curl: {e}"),
+    }
+}
+
+/// Reads a price document. URLs go through the `curl` binary, so there is
+/// no HTTP or TLS code in the app itself.
+fn read_source(source: &str) -> Result<Vec<u8>, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let out = Command::new("curl")
+            .args(["-fsSL", "--max-time", "15", source])
+            .output()
+            .map_err(curl_error)?;
+        if !out.status.success() {
+            return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+        }
+        Ok(out.stdout)
+    } else {
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        std::fs::read(path).map_err(|e| format!("{path}: {e}"))
+    }
+}
+
+/// Reads a price document from a source; `read_source` outside tests.
+type Reader = fn(&str) -> Result<Vec<u8>, String>;
+
+fn fetch_rate(settings: &FiatSettings, read: Reader) -> Result<f64, String> {
+    if settings.source.trim().is_empty() {
+        return Err("no \"source\" set in the fiat settings".to_string());
+    }
+    let data = read(settings.source.trim())?;
+    let json: serde_json::Value = serde_json::from_slice(&data).map_err(|e| format!("price JSON: {e}"))?;
+    extract(&json, &settings.path)
+}
+
+/// Two decimals with thousands separators.
+fn money(value: f64) -> String {
+    let cents = (value.abs() * 100.0).round() as u64;
+    let sign = if value < 0.0 && cents > 0 { "-" } else { "" };
+    format!("{sign}{}.{:02}", group_thousands(cents / 100), cents % 100)
+}
+
+impl FiatRate {
+    /// `≈ 1,234.56 EUR` for an amount in satoshis.
+    pub(crate) fn format(&self, sats: i64) -> String {
+        format!("≈ {} {}", money(sats as f64 * self.rate / 100_000_000.0), self.currency)
+    }
+}
+
+/// Adds the fiat value to an amount already formatted in the display
+/// unit, e.g. `0.5 BTC (≈ 30,000.00 EUR)`. Unchanged without a rate.
+pub(crate) fn with_fiat(shown: String, sats: i64, fiat: Option<&FiatRate>) -> String {
+    match fiat {
+        Some(rate) => format!("{shown} ({})", rate.format(sats)),
+        None => shown,
+    }
+}
+
+/// Keeps a fiat rate fresh in the background and formats amounts with it.
+pub(crate) struct FiatFeed {
+    pub settings: FiatSettings,
+    cache_path: PathBuf,
+    pub rate: Option<FiatRate>,
+    pub error: Option<String>,
+    pending: Option<Receiver<Result<f64, String>>>,
+    last_attempt: Option<Instant>,
+    read: Reader,
+}
+
+impl FiatFeed {
+    /// Starts from the cached rate if it is for the configured currency.
+    pub(crate) fn new(settings: FiatSettings, cache_path: &Path) -> Self {
+        let rate = std::fs::read(cache_path)
+            .ok()
+            .and_then(|d| serde_json::from_slice::<FiatRate>(&d).ok())
+            .filter(|r| r.currency == settings.currency);
+        Self {
+            settings,
+            cache_path: cache_path.to_path_buf(),
+            rate,
+            error: None,
+            pending: None,
+            last_attempt: None,
+            read: read_source,
+        }
+    }
+
+    /// Call from the main loop: collects a finished fetch and starts the
+    /// next one when due. Fetches run on their own thread.
+    pub(crate) fn poll(&mut self) {
+        if let Some(rx) = &self.pending
+            && let Ok(result) = rx.try_recv()
+        {
+            self.pending = None;
+            match result {
+                Ok(rate) => {
+                    let r = FiatRate {
+                        currency: self.settings.currency.clone(),
+                        rate,
+                        fetched_at: Utc::now(),
+                    };
+                    if let Ok(data) = serde_json::to_vec(&r) {
+                        let _ = write_atomic(&self.cache_path, &data);
+                    }
+                    self.rate = Some(r);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        let every = Duration::from_secs(self.settings.refresh_minutes.max(1) * 60);
+        if self.pending.is_none() && self.last_attempt.is_none_or(|t| t.elapsed() >= every) {
+            self.refresh();
+        }
+    }
+
+    pub(crate) fn refresh(&mut self) {
+        if self.pending.is_some() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let (settings, read) = (self.settings.clone(), self.read);
+        std::thread::spawn(move || {
+            let _ = tx.send(fetch_rate(&settings, read));
+        });
+        self.pending = Some(rx);
+        self.last_attempt = Some(Instant::now());
+    }
+
+    fn is_stale(&self, rate: &FiatRate) -> bool {
+        (Utc::now() - rate.fetched_at).num_minutes() >= self.settings.stale_minutes as i64
+    }
+
+    /// `≈ 1,234.56 EUR`, or None until a rate is known.
+    pub(crate) fn format(&self, amount: Amount) -> Option<String> {
+        Some(self.rate.as_ref()?.format(amount.to_sat() as i64))
+    }
+
+    /// Rate age for the panel, with a warning when stale or failing.
+    pub(crate) fn status(&self) -> String {
+        let currency = &self.settings.currency;
+        match &self.rate {
+            None => match &self.error {
+                Some(e) => format!("{currency}: {e}"),
+                None => format!("{currency}: fetching rate…"),
+            },
+            Some(r) => {
+                let age = Utc::now() - r.fetched_at;
+                let ago = match age.num_minutes() {
+                    m if m < 1 => "just now".to_string(),
+                    m if m < 120 => format!("{m} min ago"),
+                    _ => format!("{} h ago", age.num_hours()),
+                };
+                let mut s = format!("1 BTC = {} {currency} ({ago})", money(r.rate));
+                if self.is_stale(r) {
+                    s.push_str(" ⚠ stale");
+                }
+                if self.error.is_some() {
+                    s.push_str(" ⚠ update failed");
+                }
+                s
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    /// Serves `body` with `status` to the next `count` requests on 127.0.0.1.
+    fn serve(status: &'static str, body: &'static str, count: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        format!("http://{addr}/price")
+    }
+
+    fn settings(source: String, path: &str) -> FiatSettings {
+        FiatSettings { source, path: path.to_string(), ..FiatSettings::default() }
+    }
+
+    #[test]
+    fn extracts_rates() {
+        let json: serde_json::Value =
+            serde_json::from_str(r#"{"bitcoin": {"eur": 61234.5}, "data": [{"price": "60000.25"}], "zero": 0}"#).unwrap();
+        assert_eq!(extract(&json, "bitcoin.eur"), Ok(61234.5));
+        assert_eq!(extract(&json, "data.0.price"), Ok(60000.25));
+        assert_eq!(extract(&serde_json::json!(42000), ""), Ok(42000.0));
+        assert_eq!(
+            extract(&json, "bitcoin.usd"),
+            Err("no \"usd\" in the price JSON (path \"bitcoin.usd\")".to_string())
+        );
+        assert!(extract(&json, "data.1.price").is_err());
+        assert_eq!(extract(&json, "bitcoin"), Err("\"bitcoin\" is not a number".to_string()));
+        assert_eq!(extract(&json, "zero"), Err("invalid rate 0".to_string()));
+    }
+
+    #[test]
+    fn formats_money() {
+        assert_eq!(money(0.0), "0.00");
+        assert_eq!(money(1234567.891), "1,234,567.89");
+        assert_eq!(money(0.005), "0.01");
+        assert_eq!(money(-1234.5), "-1,234.50");
+        assert_eq!(money(-0.001), "0.00");
+    }
+
+    /// Stands in for `read_source`, so only the ignored test needs curl.
+    fn canned(source: &str) -> Result<Vec<u8>, String> {
+        match source {
+            "mem:eur" => Ok(br#"{"bitcoin": {"eur": 61234.5}}"#.to_vec()),
+            "mem:cache" => Ok(br#"{"bitcoin": {"eur": 50000}}"#.to_vec()),
+            "mem:html" => Ok(b"<html>rate limited</html>".to_vec()),
+            _ => Err("The requested URL returned error: 404".to_string()),
+        }
+    }
+
+    #[test]
+    fn fetches_with_a_reader() {
+        assert_eq!(fetch_rate(&settings("mem:eur".into(), "bitcoin.eur"), canned), Ok(61234.5));
+        let err = fetch_rate(&settings("mem:html".into(), "bitcoin.eur"), canned).unwrap_err();
+        assert!(err.starts_with("price JSON:"), "{err}");
+        assert!(fetch_rate(&settings("mem:404".into(), "bitcoin.eur"), canned).unwrap_err().contains("404"));
+        assert!(fetch_rate(&settings(" ".to_string(), ""), canned).unwrap_err().contains("no \"source\""));
+    }
+
+    #[test]
+    #[ignore = "needs the curl binary"]
+    fn fetches_over_http() {
+        let url = serve("200 OK", r#"{"bitcoin": {"eur": 61234.5}}"#, 1);
+        assert_eq!(fetch_rate(&settings(url, "bitcoin.eur"), read_source), Ok(61234.5));
+        let url = serve("404 Not Found", "{}", 1);
+        assert!(fetch_rate(&settings(url, "bitcoin.eur"), read_source).is_err());
+    }
+
+    #[test]
+    fn formats_amounts_with_the_rate() {
+        let rate = FiatRate { currency: "EUR".to_string(), rate: 60000.0, fetched_at: Utc::now() };
+        assert_eq!(rate.format(50_000_000), "≈ 30,000.00 EUR");
+        assert_eq!(rate.format(-1_000), "≈ -0.60 EUR");
+        assert_eq!(with_fiat("0.5 BTC".to_string(), 50_000_000, Some(&rate)), "0.5 BTC (≈ 30,000.00 EUR)");
+        assert_eq!(with_fiat("0.5 BTC".to_string(), 50_000_000, None), "0.5 BTC");
+    }
+
+    #[test]
+    fn explains_missing_curl() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(curl_error(missing), "curl not found; install curl or set \"source\" to a local file");
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(curl_error(denied).starts_with("curl: "));
+    }
+
+    #[test]
+    fn reads_local_files() {
+        let dir = temp_dir("fiat-file");
+        let path = dir.join("price.json");
+        std::fs::write(&path, "[12.5]").unwrap();
+        assert_eq!(fetch_rate(&settings(path.display().to_string(), "0"), read_source), Ok(12.5));
+        assert_eq!(fetch_rate(&settings(format!("file://{}", path.display()), "0"), read_source), Ok(12.5));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn poll_caches_the_rate() {
+        let dir = temp_dir("fiat-cache");
+        let cache = dir.join("fiat-rate.json");
+        let mut feed = FiatFeed::new(settings("mem:cache".into(), "bitcoin.eur"), &cache);
+        feed.read = canned;
+        assert_eq!(feed.status(), "EUR: fetching rate…");
+
+        let started = Instant::now();
+        while feed.rate.is_none() && started.elapsed() < Duration::from_secs(10) {
+            feed.poll();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(feed.rate.as_ref().map(|r| r.rate), Some(50000.0));
+        assert_eq!(feed.format(Amount::from_sat(150_000_000)).as_deref(), Some("≈ 75,000.00 EUR"));
+        assert_eq!(feed.status(), "1 BTC = 50,000.00 EUR (just now)");
+
+        let cached: FiatRate = serde_json::from_slice(&std::fs::read(&cache).unwrap()).unwrap();
+        assert_eq!((cached.currency.as_str(), cached.rate), ("EUR", 50000.0));
+
+        // The cache is used on the next start, but not for another currency
+        let again = FiatFeed::new(settings(String::new(), ""), &cache);
+        assert_eq!(again.rate, Some(cached));
+        let usd = FiatFeed::new(FiatSettings { currency: "USD".to_string(), ..FiatSettings::default() }, &cache);
+        assert_eq!(usd.rate, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flags_stale_and_failing_rates() {
+        let dir = temp_dir("fiat-stale");
+        let mut feed = FiatFeed::new(FiatSettings::default(), &dir.join("none.json"));
+        feed.rate = Some(FiatRate {
+            currency: "EUR".to_string(),
+            rate: 40000.0,
+            fetched_at: Utc::now() - chrono::Duration::minutes(90),
+        });
+        assert_eq!(feed.status(), "1 BTC = 40,000.00 EUR (90 min ago) ⚠ stale");
+        feed.error = Some("timeout".to_string());
+        assert_eq!(feed.status(), "1 BTC = 40,000.00 EUR (90 min ago) ⚠ stale ⚠ update failed");
+        feed.settings.stale_minutes = 120;
+        feed.error = None;
+        assert_eq!(feed.status(), "1 BTC = 40,000.00 EUR (90 min ago)");
+        feed.rate = None;
+        feed.error = Some("curl not found".to_string());
+        assert_eq!(feed.status(), "EUR: curl not found");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,21 +1,69 @@
 use serde::{Deserialize, Serialize};
-use serde_json;
-use std::fs::File;
-use std::io::{self, BufReader};
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
 
+use bitcoin::Network;
 use chrono::{DateTime, Utc};
 
+use crate::address::AddressType;
+use crate::crypto::{BookCipher, is_encrypted};
+
 // ===== Address book types & constants =====
-pub const ADDRESS_BOOK_PATH: &str = "addresses.json";
+
+/// Command list used when commands.json is missing or unreadable.
+pub const DEFAULT_COMMANDS: [&str; 11] = [
+    "getblockchaininfo",
+    "getnetworkinfo",
+    "getblockcount",
+    "getmempoolinfo",
+    "getpeerinfo",
+    "getwalletinfo",
+    "getbestblockhash",
+    "getdifficulty",
+    "getchaintips",
+    "getrawmempool",
+    "getrawmempool true",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddressEntry {
     pub created_at: DateTime<Utc>,
     pub address: String,
+    // Fields below are absent in older address books and default on load.
+    /// Script type requested from the node.
+    #[serde(default)]
+    pub address_type: Option<AddressType>,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub network: Option<Network>,
+    /// Wallet that handed out the address, if it came from the node.
+    #[serde(default)]
+    pub wallet: Option<String>,
+    #[serde(default)]
+    pub used: bool,
+    /// Imported into the watch-only wallet.
+    #[serde(default)]
+    pub watch_only: bool,
+    /// Checksummed descriptor the entry was imported with or derived from.
+    /// For descriptor entries (xpubs) `address` holds the same string.
+    #[serde(default)]
+    pub descriptor: Option<String>,
+}
+
+impl AddressEntry {
+    /// True for entries that watch a descriptor rather than one address.
+    pub(crate) fn is_descriptor(&self) -> bool {
+        self.address.contains('(')
+    }
 }
 
 pub(crate) fn load_commands_from_json(
-    path: &str,
+    path: &Path,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -23,17 +71,367 @@ pub(crate) fn load_commands_from_json(
     Ok(commands)
 }
 
-pub(crate) fn load_address_book(path: &str) -> Vec<AddressEntry> {
-    match File::open(path) {
-        Ok(f) => match serde_json::from_reader::<_, Vec<AddressEntry>>(f) {
-            Ok(list) => list,
-            Err(_) => Vec::new(),
-        },
-        Err(_) => Vec::new(),
+/// Loads the command list, falling back to `DEFAULT_COMMANDS`. A missing
+/// file is created with the defaults so it can be edited; a broken one is
+/// left alone and reported through the returned warning.
+pub(crate) fn load_commands(path: &Path) -> (Vec<String>, Option<String>) {
+    let defaults = || DEFAULT_COMMANDS.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    match load_commands_from_json(path) {
+        Ok(list) if !list.is_empty() => (list, None),
+        Ok(_) => (defaults(), Some(format!("{} is empty, using built-in commands", path.display()))),
+        Err(_) if !path.exists() => {
+            let _ = serde_json::to_string_pretty(&DEFAULT_COMMANDS)
+                .map(|data| std::fs::write(path, data));
+            (defaults(), None)
+        }
+        Err(e) => (
+            defaults(),
+            Some(format!("{}: {e}, using built-in commands", path.display())),
+        ),
+    }
+}
+
+/// Contents of the address book file: either the entries, or an encrypted
+/// blob waiting for the passphrase.
+pub(crate) enum BookFile {
+    Plain(Vec<AddressEntry>),
+    Locked(Vec<u8>),
+}
+
+/// Loads the book. A missing file is an empty book; a file that cannot be
+/// read or parsed is an error, so callers never save over it by accident.
+pub(crate) fn load_address_book(path: &Path) -> Result<BookFile, String> {
+    let data = match std::fs::read(path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BookFile::Plain(Vec::new())),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    if is_encrypted(&data) {
+        return Ok(BookFile::Locked(data));
+    }
+    serde_json::from_slice::<Vec<AddressEntry>>(&data)
+        .map(BookFile::Plain)
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Decrypts a `BookFile::Locked` blob, returning the session key with it.
+pub(crate) fn unlock_address_book(
+    data: &[u8],
+    passphrase: &str,
+) -> Result<(BookCipher, Vec<AddressEntry>), String> {
+    let (cipher, plain) = BookCipher::unlock(data, passphrase)?;
+    let entries = serde_json::from_slice(&plain).map_err(|e| e.to_string())?;
+    Ok((cipher, entries))
+}
+
+/// Saves the book after merging duplicate addresses in place, encrypted
+/// if a cipher is given. Returns how many duplicates were merged away.
+pub(crate) fn save_address_book(
+    path: &Path,
+    entries: &mut Vec<AddressEntry>,
+    cipher: Option<&BookCipher>,
+) -> Result<usize, String> {
+    let merged = dedupe_address_book(entries);
+    let mut data = serde_json::to_vec_pretty(entries).map_err(|e| e.to_string())?;
+    if let Some(c) = cipher {
+        data = c.seal(&data)?;
+    }
+    rotate_backups(path).map_err(|e| format!("backup of {}: {e}", path.display()))?;
+    write_atomic(path, &data).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(merged)
+}
+
+/// Deletes backups that are still plaintext, after the book was encrypted.
+/// Returns how many were removed.
+pub(crate) fn remove_plain_backups(path: &Path) -> Result<usize, String> {
+    let mut removed = 0;
+    for n in 1..=BACKUP_COUNT {
+        let backup = with_suffix(path, &format!(".{n}"));
+        if let Ok(data) = std::fs::read(&backup)
+            && !is_encrypted(&data)
+        {
+            std::fs::remove_file(&backup).map_err(|e| format!("{}: {e}", backup.display()))?;
+            removed += 1;
+        }
     }
+    Ok(removed)
 }
 
-pub(crate) fn save_address_book(path: &str, entries: &Vec<AddressEntry>) -> Result<(), String> {
-    let data = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
-    std::fs::write(path, data).map_err(|e| e.to_string())
+// ===== Crash-safe persistence =====
+
+/// Number of previous versions kept next to the book as `<file>.1` (newest)
+/// up to `<file>.N`.
+pub const BACKUP_COUNT: usize = 3;
+
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Shifts `<file>.1..N-1` up by one and copies the current file to `<file>.1`.
+fn rotate_backups(path: &Path) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    for n in (1..BACKUP_COUNT).rev() {
+        let from = with_suffix(path, &format!(".{n}"));
+        if from.exists() {
+            std::fs::rename(&from, with_suffix(path, &format!(".{}", n + 1)))?;
+        }
+    }
+    std::fs::copy(path, with_suffix(path, ".1"))?;
+    Ok(())
+}
+
+/// Writes `data` to a temp file in the same directory, fsyncs it and
+/// renames it over `path`, so readers only ever see the old or the new file.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = with_suffix(path, ".tmp");
+    {
+        let mut f = File::create(&tmp)?;
+        f.write_all(data)?;
+        f.sync_all()?;
+    }
+    std::fs::rename(&tmp, path)?;
+
+    // Persist the rename itself; directories cannot be opened on Windows.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Exclusive lock on `<file>.lock`, held for as long as the value lives.
+pub(crate) struct BookLock {
+    _file: File,
+}
+
+/// Takes the address book lock so a second bitatui instance cannot write
+/// the same file. Fails immediately if another process holds it.
+pub(crate) fn lock_address_book(path: &Path) -> Result<BookLock, String> {
+    let lock_path = with_suffix(path, ".lock");
+    let file = File::create(&lock_path).map_err(|e| format!("{}: {e}", lock_path.display()))?;
+    match file.try_lock() {
+        Ok(()) => Ok(BookLock { _file: file }),
+        Err(TryLockError::WouldBlock) => Err(format!(
+            "{} is in use by another bitatui instance",
+            path.display()
+        )),
+        Err(TryLockError::Error(e)) => Err(format!("{}: {e}", lock_path.display())),
+    }
+}
+
+/// Bech32 addresses are case-insensitive, base58 ones are not.
+fn address_key(addr: &str) -> String {
+    let a = addr.trim();
+    let lower = a.to_ascii_lowercase();
+    if lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1") {
+        lower
+    } else {
+        a.to_string()
+    }
+}
+
+/// Collapses entries with the same address into the oldest one, keeping
+/// any label, note or metadata that only the duplicates carried.
+pub(crate) fn dedupe_address_book(entries: &mut Vec<AddressEntry>) -> usize {
+    let before = entries.len();
+    let mut kept: Vec<AddressEntry> = Vec::with_capacity(before);
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for e in entries.drain(..) {
+        match index.get(&address_key(&e.address)) {
+            Some(&i) => {
+                let k = &mut kept[i];
+                if e.created_at < k.created_at {
+                    k.created_at = e.created_at;
+                }
+                if k.label.is_empty() {
+                    k.label = e.label;
+                }
+                if k.note.is_empty() {
+                    k.note = e.note;
+                } else if !e.note.is_empty() && e.note != k.note {
+                    k.note = format!("{} / {}", k.note, e.note);
+                }
+                k.address_type = k.address_type.or(e.address_type);
+                k.network = k.network.or(e.network);
+                if k.wallet.is_none() {
+                    k.wallet = e.wallet;
+                }
+                k.used |= e.used;
+                k.watch_only |= e.watch_only;
+                if k.descriptor.is_none() {
+                    k.descriptor = e.descriptor;
+                }
+            }
+            None => {
+                index.insert(address_key(&e.address), kept.len());
+                kept.push(e);
+            }
+        }
+    }
+
+    *entries = kept;
+    before - entries.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    fn entry(created_at: &str, address: &str, label: &str) -> AddressEntry {
+        serde_json::from_value(serde_json::json!({
+            "created_at": created_at,
+            "address": address,
+            "label": label,
+        }))
+        .unwrap()
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn writes_atomically() {
+        let dir = temp_dir("file-atomic");
+        let path = dir.join("book.json");
+        write_atomic(&path, b"one").unwrap();
+        write_atomic(&path, b"two").unwrap();
+        assert_eq!(read(&path), "two");
+        assert!(!with_suffix(&path, ".tmp").exists());
+        assert!(write_atomic(&dir.join("missing/book.json"), b"x").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotates_backups() {
+        let dir = temp_dir("file-rotate");
+        let path = dir.join("book.json");
+        // Nothing to back up yet
+        rotate_backups(&path).unwrap();
+        assert!(!with_suffix(&path, ".1").exists());
+
+        for n in 1..=5 {
+            std::fs::write(&path, format!("v{n}")).unwrap();
+            rotate_backups(&path).unwrap();
+        }
+        assert_eq!(read(&path), "v5");
+        assert_eq!(read(&with_suffix(&path, ".1")), "v5");
+        assert_eq!(read(&with_suffix(&path, ".2")), "v4");
+        assert_eq!(read(&with_suffix(&path, ".3")), "v3");
+        assert!(!with_suffix(&path, &format!(".{}", BACKUP_COUNT + 1)).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let dir = temp_dir("file-book");
+        let path = dir.join("addresses.json");
+        assert!(matches!(load_address_book(&path), Ok(BookFile::Plain(b)) if b.is_empty()));
+
+        let mut book = vec![
+            entry("2024-01-01T00:00:00Z", "bc1qexample", "rent"),
+            entry("2024-01-02T00:00:00Z", "BC1QEXAMPLE", ""),
+        ];
+        assert_eq!(save_address_book(&path, &mut book, None).unwrap(), 1);
+        save_address_book(&path, &mut book, None).unwrap();
+        let Ok(BookFile::Plain(loaded)) = load_address_book(&path) else {
+            panic!("not a plain book");
+        };
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].label, "rent");
+        assert!(with_suffix(&path, ".1").exists());
+        assert_eq!(remove_plain_backups(&path).unwrap(), 1);
+
+        // A broken file is an error, never an empty book
+        std::fs::write(&path, "[{").unwrap();
+        assert!(matches!(load_address_book(&path), Err(e) if e.contains("addresses.json")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn loads_old_books() {
+        let dir = temp_dir("file-old");
+        let path = dir.join("addresses.json");
+        std::fs::write(
+            &path,
+            r#"[{"created_at":"2023-05-01T12:00:00Z","address":"bc1qold"},
+               {"created_at":"2023-06-01T12:00:00Z","address":"bc1qlabelled","address_type":"bech32","label":"rent"}]"#,
+        )
+        .unwrap();
+        let Ok(BookFile::Plain(loaded)) = load_address_book(&path) else {
+            panic!("not a plain book");
+        };
+        assert_eq!(loaded.len(), 2);
+        let old = &loaded[0];
+        assert_eq!(old.address, "bc1qold");
+        assert_eq!(old.address_type, None);
+        assert_eq!((old.label.as_str(), old.note.as_str()), ("", ""));
+        assert_eq!((old.network, old.wallet.as_deref()), (None, None));
+        assert!(!old.used && !old.watch_only && old.descriptor.is_none());
+        assert_eq!(loaded[1].label, "rent");
+        assert_eq!(loaded[1].address_type, Some(AddressType::Bech32));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = temp_dir("file-lock");
+        let path = dir.join("addresses.json");
+        let lock = lock_address_book(&path).unwrap();
+        assert!(matches!(lock_address_book(&path), Err(e) if e.contains("in use by another bitatui instance")));
+        drop(lock);
+        assert!(lock_address_book(&path).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bech32_keys_ignore_case() {
+        assert_eq!(address_key(" BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4 "), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(address_key("TB1QABC"), "tb1qabc");
+        assert_eq!(address_key("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"), "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2");
+    }
+
+    #[test]
+    fn dedupe_merges_into_the_oldest() {
+        let mut a = entry("2024-03-01T00:00:00Z", "bc1qexample", "");
+        a.note = "first".to_string();
+        a.used = true;
+        let mut b = entry("2024-01-01T00:00:00Z", "BC1QEXAMPLE", "rent");
+        b.note = "second".to_string();
+        b.wallet = Some("main".to_string());
+        b.watch_only = true;
+        let c = entry("2024-02-01T00:00:00Z", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", "cold");
+        let d = entry("2024-04-01T00:00:00Z", "bc1qexample", "other");
+        let mut book = vec![a, c, b, d];
+
+        assert_eq!(dedupe_address_book(&mut book), 2);
+        assert_eq!(book.len(), 2);
+        let merged = &book[0];
+        // Kept in place of the first seen, with the oldest date
+        assert_eq!(merged.address, "bc1qexample");
+        assert_eq!(merged.created_at.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        // The first non-empty label wins, differing notes are joined
+        assert_eq!(merged.label, "rent");
+        assert_eq!(merged.note, "first / second");
+        assert_eq!(merged.wallet.as_deref(), Some("main"));
+        assert!(merged.used && merged.watch_only);
+        assert_eq!(book[1].label, "cold");
+
+        assert_eq!(dedupe_address_book(&mut book), 0);
+    }
+
+    #[test]
+    fn base58_duplicates_need_the_same_case() {
+        let mut book = vec![
+            entry("2024-01-01T00:00:00Z", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", ""),
+            entry("2024-01-02T00:00:00Z", "1bvbmseystwetqtfn5au4m4gfg7xjanvn2", ""),
+        ];
+        assert_eq!(dedupe_address_book(&mut book), 0);
+    }
 }
@@ -0,0 +1,75 @@
+// ===== Fuzzy matching =====
+
+/// Scores `text` against `pattern` as a case-insensitive subsequence match.
+/// Returns None when not every pattern char appears in order. Higher is
+/// better: consecutive runs and matches at word starts earn a bonus, gaps
+/// cost a little.
+pub(crate) fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.trim().to_lowercase().chars().collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let mut score = 0i64;
+    let mut pi = 0usize;
+    let mut last_match: Option<usize> = None;
+    for (ti, &c) in text.iter().enumerate() {
+        if pi == pattern.len() {
+            break;
+        }
+        if c != pattern[pi] {
+            continue;
+        }
+        score += 1;
+        match last_match {
+            Some(prev) if prev + 1 == ti => score += 5,
+            Some(prev) => score -= (ti - prev - 1).min(3) as i64,
+            None => score -= ti.min(5) as i64,
+        }
+        if ti == 0 || !text[ti - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(ti);
+        pi += 1;
+    }
+
+    (pi == pattern.len()).then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_scores() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("  ", "anything"), Some(0));
+        // 1 per char, +3 at a word start, +5 per consecutive char
+        assert_eq!(fuzzy_score("abc", "abc"), Some(16));
+        // A one-char gap costs 1
+        assert_eq!(fuzzy_score("ac", "abc"), Some(4));
+        // A late first match costs up to 5
+        assert_eq!(fuzzy_score("c", "abc"), Some(-1));
+        assert_eq!(fuzzy_score("z", "abcdefgz"), Some(-4));
+    }
+
+    #[test]
+    fn needs_every_char_in_order() {
+        assert_eq!(fuzzy_score("cba", "abc"), None);
+        assert_eq!(fuzzy_score("abcd", "abc"), None);
+        assert_eq!(fuzzy_score("x", ""), None);
+        assert!(fuzzy_score("GBC", "getblockcount").is_some());
+        assert!(fuzzy_score("ü", "Über").is_some());
+    }
+
+    #[test]
+    fn prefers_runs_and_word_starts() {
+        let run = fuzzy_score("get", "getblock").unwrap();
+        let scattered = fuzzy_score("get", "g-e-t").unwrap();
+        assert!(run > scattered);
+        let word_start = fuzzy_score("bal", "get balance").unwrap();
+        let inside = fuzzy_score("bal", "getxbalance").unwrap();
+        assert!(word_start > inside);
+    }
+}
@@ -0,0 +1,382 @@
+use bitcoin::{Amount, SignedAmount};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Color,
+    symbols,
+    text::Span,
+    widgets::{Axis, Block, Borders, Chart, Clear, Dataset, GraphType, Paragraph, Tabs},
+};
+
+use crate::cli::{rpc_error, run_bitcoin_cli};
+use crate::fiat::{FiatRate, with_fiat};
+use crate::node::{Balances, fetch_balances, json_signed_amount};
+use crate::centered_rect;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::privacy::Privacy;
+use crate::units::Unit;
+use crate::theme::Theme;
+
+// ===== Balance history chart =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryRange {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl HistoryRange {
+    const ALL: [HistoryRange; 4] = [HistoryRange::Day, HistoryRange::Week, HistoryRange::Month, HistoryRange::All];
+
+    fn label(self) -> &'static str {
+        match self {
+            HistoryRange::Day => "Day",
+            HistoryRange::Week => "Week",
+            HistoryRange::Month => "Month",
+            HistoryRange::All => "All",
+        }
+    }
+
+    fn span(self) -> Option<Duration> {
+        match self {
+            HistoryRange::Day => Some(Duration::days(1)),
+            HistoryRange::Week => Some(Duration::weeks(1)),
+            HistoryRange::Month => Some(Duration::days(30)),
+            HistoryRange::All => None,
+        }
+    }
+
+    fn time_format(self) -> &'static str {
+        match self {
+            HistoryRange::Day => "%H:%M",
+            HistoryRange::Week | HistoryRange::Month => "%b %d",
+            HistoryRange::All => "%Y-%m-%d",
+        }
+    }
+}
+
+/// Which `getbalances` figure a transaction counts towards today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bucket {
+    Trusted,
+    Pending,
+    Immature,
+}
+
+impl Bucket {
+    const ALL: [Bucket; 3] = [Bucket::Trusted, Bucket::Pending, Bucket::Immature];
+
+    fn label(self) -> &'static str {
+        match self {
+            Bucket::Trusted => "Trusted",
+            Bucket::Pending => "Pending",
+            Bucket::Immature => "Immature",
+        }
+    }
+
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Bucket::Trusted => theme.ok,
+            Bucket::Pending => theme.warn,
+            Bucket::Immature => theme.info,
+        }
+    }
+
+    fn current(self, b: &Balances) -> Amount {
+        match self {
+            Bucket::Trusted => b.trusted,
+            Bucket::Pending => b.pending,
+            Bucket::Immature => b.immature,
+        }
+    }
+}
+
+/// One wallet transaction entry: when, which balance, and by how much
+/// (amount plus fee, so sends include what they paid).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TxEvent {
+    pub time: DateTime<Utc>,
+    pub bucket: Bucket,
+    pub delta: SignedAmount,
+}
+
+/// Reads every wallet transaction with `listsinceblock`, oldest first.
+/// Orphaned and conflicted transactions are left out.
+pub(crate) fn fetch_events() -> Result<Vec<TxEvent>, String> {
+    let out = run_bitcoin_cli("listsinceblock").map_err(|e| e.to_string())?;
+    if let Some(e) = rpc_error(&out) {
+        return Err(e);
+    }
+    let json: serde_json::Value = serde_json::from_str(&out).map_err(|e| e.to_string())?;
+    parse_events(&json)
+}
+
+/// Events of a `listsinceblock` result. A malformed amount fails the whole
+/// list rather than skewing the chart.
+fn parse_events(json: &serde_json::Value) -> Result<Vec<TxEvent>, String> {
+    let mut events = Vec::new();
+    for tx in json["transactions"].as_array().map(|a| a.as_slice()).unwrap_or_default() {
+        let Some(category) = tx["category"].as_str() else { continue };
+        let confirmations = tx["confirmations"].as_i64().unwrap_or(0);
+        if category == "orphan" || confirmations < 0 {
+            continue;
+        }
+        let Some(time) = tx["time"].as_i64().and_then(|t| Utc.timestamp_opt(t, 0).single()) else {
+            continue;
+        };
+        let bucket = match category {
+            "immature" => Bucket::Immature,
+            _ if confirmations == 0 && tx["trusted"].as_bool() == Some(false) => Bucket::Pending,
+            _ => Bucket::Trusted,
+        };
+        // Only sends carry a fee
+        let fee = match &tx["fee"] {
+            serde_json::Value::Null => SignedAmount::ZERO,
+            fee => json_signed_amount(fee)?,
+        };
+        events.push(TxEvent { time, bucket, delta: json_signed_amount(&tx["amount"])? + fee });
+    }
+    events.sort_by_key(|e| e.time);
+    Ok(events)
+}
+
+pub(crate) struct BalanceHistory {
+    pub range: HistoryRange,
+    pub events: Vec<TxEvent>,
+    pub balances: Option<Balances>,
+    pub error: Option<String>,
+    /// Fiat rate for the current balances; main keeps it in step with the feed.
+    pub fiat: Option<FiatRate>,
+}
+
+impl BalanceHistory {
+    pub(crate) fn new() -> Self {
+        Self {
+            range: HistoryRange::Month,
+            events: Vec::new(),
+            balances: None,
+            error: None,
+            fiat: None,
+        }
+    }
+
+    pub(crate) fn load(&mut self) {
+        self.error = None;
+        match fetch_events() {
+            Ok(e) => self.events = e,
+            Err(e) => self.error = Some(format!("listsinceblock failed: {e}")),
+        }
+        match fetch_balances() {
+            Ok(b) => self.balances = Some(b),
+            Err(e) => self.error = Some(format!("getbalances failed: {e}")),
+        }
+    }
+
+    /// Main view actions that keep working on the chart.
+    pub(crate) const SHARED_ACTIONS: [Action; 3] = [Action::TogglePrivacy, Action::CycleUnit, Action::CycleTheme];
+
+    /// Handles a key. Returns false when the chart should close.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> bool {
+        match (keymap.action(Scope::History, key), key.code) {
+            (Some(Action::HistoryClose), _) | (_, KeyCode::Esc) => return false,
+            (Some(Action::HistoryDay), _) => self.range = HistoryRange::Day,
+            (Some(Action::HistoryWeek), _) => self.range = HistoryRange::Week,
+            (Some(Action::HistoryMonth), _) => self.range = HistoryRange::Month,
+            (Some(Action::HistoryAll), _) => self.range = HistoryRange::All,
+            (Some(Action::HistoryReload), _) => self.load(),
+            _ if key.modifiers.contains(KeyModifiers::CONTROL) => {}
+            (_, KeyCode::Tab | KeyCode::Right) => {
+                let i = HistoryRange::ALL.iter().position(|r| *r == self.range).unwrap_or(0);
+                self.range = HistoryRange::ALL[(i + 1) % HistoryRange::ALL.len()];
+            }
+            (_, KeyCode::BackTab | KeyCode::Left) => {
+                let i = HistoryRange::ALL.iter().position(|r| *r == self.range).unwrap_or(0);
+                self.range = HistoryRange::ALL[(i + HistoryRange::ALL.len() - 1) % HistoryRange::ALL.len()];
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Step series per bucket between `start` and `now`, with x in seconds
+    /// since `start` and y in `unit`.
+    fn series(&self, start: DateTime<Utc>, now: DateTime<Utc>, unit: Unit) -> Vec<(Bucket, Vec<(f64, f64)>)> {
+        let x = |t: DateTime<Utc>| (t - start).num_seconds().max(0) as f64;
+        Bucket::ALL
+            .iter()
+            .map(|&bucket| {
+                let mut running = SignedAmount::ZERO;
+                let mut points = Vec::new();
+                for e in self.events.iter().filter(|e| e.bucket == bucket) {
+                    if e.time < start {
+                        running += e.delta;
+                        continue;
+                    }
+                    if points.is_empty() {
+                        points.push((0.0, unit.value(running.to_sat())));
+                    }
+                    points.push((x(e.time), unit.value(running.to_sat())));
+                    running += e.delta;
+                    points.push((x(e.time), unit.value(running.to_sat())));
+                }
+                if points.is_empty() {
+                    points.push((0.0, unit.value(running.to_sat())));
+                }
+                points.push((x(now), unit.value(running.to_sat())));
+                (bucket, points)
+            })
+            .collect()
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(85, 80, size)
+    }
+
+    pub(crate) fn draw(&self, f: &mut Frame, size: Rect, keymap: &Keymap, privacy: Privacy, unit: Unit, theme: &Theme) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(" Balance history ");
+        if let Some(e) = &self.error {
+            outer = outer.title_bottom(Span::styled(format!(" {e} "), theme.warn()));
+        }
+        f.render_widget(outer, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(1), Constraint::Min(5), Constraint::Length(1)])
+            .split(area);
+
+        let selected = HistoryRange::ALL.iter().position(|r| *r == self.range).unwrap_or(0);
+        f.render_widget(
+            Tabs::new(HistoryRange::ALL.iter().map(|r| r.label()).collect::<Vec<_>>())
+                .select(selected)
+                .highlight_style(theme.focused()),
+            rows[0],
+        );
+
+        let now = Utc::now();
+        let start = match self.range.span() {
+            Some(span) => now - span,
+            None => self
+                .events
+                .first()
+                .map(|e| e.time)
+                .filter(|t| *t < now - Duration::hours(1))
+                .unwrap_or(now - Duration::days(1)),
+        };
+        let series = self.series(start, now, unit);
+
+        let (mut lo, mut hi) = (0.0f64, 0.0f64);
+        for (_, points) in &series {
+            for (_, y) in points {
+                lo = lo.min(*y);
+                hi = hi.max(*y);
+            }
+        }
+        if hi - lo < unit.value(1) {
+            hi = lo + unit.value(100_000);
+        }
+        let pad = (hi - lo) * 0.05;
+        let (lo, hi) = (if lo < 0.0 { lo - pad } else { 0.0 }, hi + pad);
+
+        let amount_label = |v: f64| {
+            let sats = SignedAmount::from_sat((v / unit.value(1)).round() as i64);
+            Span::raw(privacy.amount(&unit.format_signed(sats)))
+        };
+        let datasets: Vec<Dataset> = series
+            .iter()
+            .map(|(bucket, points)| {
+                let current = self
+                    .balances
+                    .map(|b| {
+                        let amount = bucket.current(&b);
+                        privacy.amount(&with_fiat(unit.format(amount), amount.to_sat() as i64, self.fiat.as_ref()))
+                    })
+                    .unwrap_or_else(|| "?".to_string());
+                Dataset::default()
+                    .name(format!("{} {current}", bucket.label()))
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(theme.fg(bucket.color(theme)))
+                    .data(points)
+            })
+            .collect();
+
+        let width = (now - start).num_seconds().max(1) as f64;
+        let fmt = self.range.time_format();
+        let mid = start + (now - start) / 2;
+        let chart = Chart::new(datasets)
+            .x_axis(
+                Axis::default()
+                    .bounds([0.0, width])
+                    .labels(vec![
+                        Span::raw(start.with_timezone(&chrono::Local).format(fmt).to_string()),
+                        Span::raw(mid.with_timezone(&chrono::Local).format(fmt).to_string()),
+                        Span::raw(now.with_timezone(&chrono::Local).format(fmt).to_string()),
+                    ]),
+            )
+            .y_axis(
+                Axis::default()
+                    .title(unit.label())
+                    .bounds([lo, hi])
+                    .labels(vec![amount_label(lo), amount_label((lo + hi) / 2.0), amount_label(hi)]),
+            )
+            .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)));
+        f.render_widget(chart, rows[1]);
+
+        let in_range = self.events.iter().filter(|e| e.time >= start).count();
+        f.render_widget(
+            Paragraph::new(format!(
+                "{in_range} transaction(s) in range, {} total • {} • ←/→=range • Esc=close",
+                self.events.len(),
+                keymap.hints(Scope::History).join(" • ")
+            ))
+            .style(theme.muted()),
+            rows[2],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_from_listsinceblock() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{"transactions": [
+                {"category": "send", "amount": -0.1, "fee": -0.00001, "confirmations": 3, "time": 200},
+                {"category": "receive", "amount": 0.30000001, "confirmations": 0, "trusted": false, "time": 100},
+                {"category": "immature", "amount": 6.25, "confirmations": 5, "time": 300},
+                {"category": "orphan", "amount": 6.25, "confirmations": 0, "time": 50},
+                {"category": "receive", "amount": 1, "confirmations": -2, "time": 60}
+            ]}"#,
+        )
+        .unwrap();
+        let events = parse_events(&json).unwrap();
+        let deltas: Vec<(i64, Bucket)> = events.iter().map(|e| (e.delta.to_sat(), e.bucket)).collect();
+        assert_eq!(
+            deltas,
+            [(30_000_001, Bucket::Pending), (-10_001_000, Bucket::Trusted), (625_000_000, Bucket::Immature)]
+        );
+    }
+
+    #[test]
+    fn bad_amount_fails_the_list() {
+        let json: serde_json::Value =
+            serde_json::from_str(r#"{"transactions": [{"category": "receive", "amount": "x", "time": 1}]}"#).unwrap();
+        assert!(parse_events(&json).is_err());
+    }
+}
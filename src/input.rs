@@ -0,0 +1,244 @@
+use crossterm::event::KeyCode;
+use zeroize::{Zeroize, Zeroizing};
+
+// ===== Single-line text input =====
+
+/// Editable single-line text field. The cursor is a char index, so labels
+/// and notes containing non-ASCII text can be edited safely. Not `Clone`,
+/// so a passphrase can't be copied out of a masked field unnoticed.
+#[derive(Debug, Default)]
+pub(crate) struct TextInput {
+    pub value: String,
+    pub cursor: usize,
+    pub allow_spaces: bool,
+    /// Passphrase fields render as bullets.
+    pub masked: bool,
+}
+
+impl TextInput {
+    pub(crate) fn new(value: &str, allow_spaces: bool) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.chars().count(),
+            allow_spaces,
+            masked: false,
+        }
+    }
+
+    /// Empty masked field for passphrases. Its text is wiped from memory
+    /// when replaced, cleared or dropped.
+    pub(crate) fn secret() -> Self {
+        let mut input = Self::new("", true);
+        input.masked = true;
+        // Typing within this never reallocates and leaves no stray copies
+        input.value.reserve(256);
+        input
+    }
+
+    /// Text to draw: the value, or one bullet per char when masked.
+    pub(crate) fn shown(&self) -> String {
+        if self.masked {
+            "•".repeat(self.value.chars().count())
+        } else {
+            self.value.clone()
+        }
+    }
+
+    /// Replaces the text in place, keeping the buffer.
+    pub(crate) fn set(&mut self, value: &str) {
+        if self.masked {
+            self.value.zeroize();
+        } else {
+            self.value.clear();
+        }
+        self.reserve(value.len());
+        self.value.push_str(value);
+        self.cursor = value.chars().count();
+    }
+
+    /// Makes room for `extra` more bytes. A masked field moves to a bigger
+    /// buffer itself so the old one can be wiped rather than freed as is.
+    fn reserve(&mut self, extra: usize) {
+        if !self.masked || self.value.capacity() - self.value.len() >= extra {
+            return;
+        }
+        let mut grown = String::with_capacity((self.value.len() + extra).max(self.value.capacity() * 2));
+        grown.push_str(&self.value);
+        self.value.zeroize();
+        self.value = grown;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.set("");
+    }
+
+    /// Inserts pasted text at the cursor. Line breaks and other control
+    /// characters are dropped, as are spaces unless the field allows them.
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        let allow_spaces = self.allow_spaces;
+        for c in text.chars().filter(|c| !c.is_control() && (allow_spaces || *c != ' ')) {
+            let at = self.byte_index(self.cursor);
+            self.reserve(c.len_utf8());
+            self.value.insert(at, c);
+            self.cursor += 1;
+        }
+    }
+
+    fn byte_index(&self, char_idx: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(char_idx)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+
+    /// Applies an editing key. Returns true if the key was consumed.
+    pub(crate) fn handle_key(&mut self, code: KeyCode) -> bool {
+        let len = self.value.chars().count();
+        match code {
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    let at = self.byte_index(self.cursor - 1);
+                    self.value.remove(at);
+                    self.cursor -= 1;
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < len {
+                    let at = self.byte_index(self.cursor);
+                    self.value.remove(at);
+                }
+            }
+            KeyCode::Char(c) if !c.is_control() && (self.allow_spaces || c != ' ') => {
+                let at = self.byte_index(self.cursor.min(len));
+                self.reserve(c.len_utf8());
+                self.value.insert(at, c);
+                self.cursor = (self.cursor + 1).min(len + 1);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Drop for TextInput {
+    fn drop(&mut self) {
+        if self.masked {
+            self.value.zeroize();
+        }
+    }
+}
+
+/// Reads a passphrase from the terminal outside the TUI, echoing `*` for
+/// each character. Esc or Ctrl+C aborts.
+pub(crate) fn prompt_passphrase(prompt: &str) -> Result<Zeroizing<String>, String> {
+    use crossterm::event::{self, Event, KeyEventKind, KeyModifiers};
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+    use std::io::Write;
+
+    let mut out = std::io::stdout();
+    let _ = write!(out, "{prompt}");
+    let _ = out.flush();
+
+    enable_raw_mode().map_err(|e| e.to_string())?;
+    // Room for a long passphrase, so typing never reallocates
+    let mut pass = Zeroizing::new(String::with_capacity(256));
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(k)) if k.kind != KeyEventKind::Release => k,
+            Ok(_) => continue,
+            Err(e) => break Err(e.to_string()),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Esc => break Err("aborted".to_string()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err("aborted".to_string());
+            }
+            KeyCode::Backspace if pass.pop().is_some() => {
+                let _ = write!(out, "\u{8} \u{8}");
+            }
+            KeyCode::Char(c) => {
+                pass.push(c);
+                let _ = write!(out, "*");
+            }
+            _ => {}
+        }
+        let _ = out.flush();
+    };
+    let _ = disable_raw_mode();
+    let _ = write!(out, "\r\n");
+    result.map(|()| pass)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(input: &mut TextInput, keys: &[KeyCode]) -> String {
+        for &k in keys {
+            input.handle_key(k);
+        }
+        input.value.clone()
+    }
+
+    #[test]
+    fn edits_by_char() {
+        let mut input = TextInput::new("café", true);
+        assert_eq!(input.cursor, 4);
+        assert_eq!(typed(&mut input, &[KeyCode::Backspace, KeyCode::Char('é')]), "café");
+        assert_eq!(typed(&mut input, &[KeyCode::Left, KeyCode::Left, KeyCode::Char('ñ')]), "cañfé");
+        assert_eq!(typed(&mut input, &[KeyCode::Home, KeyCode::Delete]), "añfé");
+        assert_eq!(typed(&mut input, &[KeyCode::End, KeyCode::Right, KeyCode::Char('!')]), "añfé!");
+        assert_eq!(typed(&mut input, &[KeyCode::Home, KeyCode::Left, KeyCode::Backspace]), "añfé!");
+        assert_eq!(input.cursor, 0);
+        assert!(!input.handle_key(KeyCode::Tab));
+    }
+
+    #[test]
+    fn spaces_and_paste() {
+        let mut input = TextInput::new("", false);
+        assert!(!input.handle_key(KeyCode::Char(' ')));
+        input.insert_str("bc1q abc\r\n\tdef");
+        assert_eq!(input.value, "bc1qabcdef");
+        assert_eq!(input.cursor, 10);
+
+        let mut input = TextInput::new("ab", true);
+        input.cursor = 1;
+        input.insert_str("x y");
+        assert_eq!((input.value.as_str(), input.cursor), ("ax yb", 4));
+    }
+
+    #[test]
+    fn secrets_are_masked() {
+        let mut input = TextInput::secret();
+        input.insert_str("pässword");
+        assert_eq!(input.shown(), "••••••••");
+        input.clear();
+        assert_eq!((input.value.as_str(), input.cursor), ("", 0));
+        input.set("abc");
+        assert_eq!((input.shown().as_str(), input.cursor), ("•••", 3));
+        assert_eq!(TextInput::new("abc", true).shown(), "abc");
+    }
+
+    #[test]
+    fn secrets_keep_their_buffer() {
+        let mut input = TextInput::secret();
+        let buffer = (input.value.as_ptr(), input.value.capacity());
+        input.set("hunter2");
+        input.clear();
+        typed(&mut input, &[KeyCode::Char('x'); 200]);
+        input.handle_key(KeyCode::Backspace);
+        input.insert_str("pässword");
+        assert_eq!((input.value.as_ptr(), input.value.capacity()), buffer);
+
+        // Outgrowing it moves to a new buffer, still masked
+        input.insert_str(&"y".repeat(100));
+        assert!(input.value.capacity() > buffer.1);
+        assert_eq!(input.value.chars().count(), 307);
+    }
+}
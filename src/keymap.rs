@@ -0,0 +1,676 @@
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// ===== Actions & key bindings (keys.json) =====
+
+/// Where an action's keys are read: the main view or one of the dialogs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scope {
+    Main,
+    AddressBook,
+    Explorer,
+    Psbt,
+    Message,
+    Passphrase,
+    Wallets,
+    History,
+    Palette,
+}
+
+/// Everything that can be bound to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    RunCommand,
+    Refresh,
+    ScrollDown,
+    ScrollUp,
+    TogglePrivacy,
+    CycleUnit,
+    CycleTheme,
+    AddressBook,
+    Explorer,
+    Psbt,
+    Message,
+    Passphrase,
+    Wallets,
+    History,
+    Palette,
+    Quit,
+    BookNew,
+    BookChange,
+    BookGetNew,
+    BookType,
+    BookEditLabel,
+    BookEditNote,
+    BookUsed,
+    BookSearch,
+    BookSort,
+    BookScan,
+    BookWatch,
+    BookQrLevel,
+    BookQrSize,
+    BookExport,
+    BookDelete,
+    BookCopy,
+    BookClose,
+    ExplorerScriptType,
+    ExplorerNetwork,
+    ExplorerMore,
+    ExplorerFewer,
+    ExplorerAddToBook,
+    ExplorerCopy,
+    ExplorerClose,
+    PsbtCopy,
+    PsbtSave,
+    PsbtShowImported,
+    PsbtReset,
+    PsbtClose,
+    MessageSign,
+    MessageVerify,
+    MessageCopy,
+    MessageClose,
+    PassphraseMode,
+    PassphraseLock,
+    PassphraseClose,
+    WalletsTab,
+    WalletsRefresh,
+    WalletsBackup,
+    WalletsCreate,
+    WalletsRestore,
+    WalletsClose,
+    HistoryDay,
+    HistoryWeek,
+    HistoryMonth,
+    HistoryAll,
+    HistoryReload,
+    HistoryClose,
+    PaletteClose,
+}
+
+/// One row per action, in declaration order: the action, its keys.json
+/// name, the command palette label, the help bar text and the default keys.
+type ActionRow = (Action, &'static str, &'static str, &'static str, &'static [&'static str]);
+
+const ACTIONS: [ActionRow; 65] = [
+    (Action::RunCommand, "run_command", "Run selected command", "run", &["Enter"]),
+    (Action::Refresh, "refresh", "Refresh node, wallet and output", "refresh", &["r"]),
+    (Action::ScrollDown, "scroll_down", "Scroll output down", "scroll down", &["j", "PgDn"]),
+    (Action::ScrollUp, "scroll_up", "Scroll output up", "scroll up", &["k", "PgUp"]),
+    (Action::TogglePrivacy, "toggle_privacy", "Toggle privacy mode", "privacy mode", &["h"]),
+    (Action::CycleUnit, "cycle_unit", "Change display unit", "unit", &["b"]),
+    (Action::CycleTheme, "cycle_theme", "Switch color theme", "theme", &["t"]),
+    (Action::AddressBook, "address_book", "Address book & QR", "QR overlay", &["w"]),
+    (Action::Explorer, "explorer", "Descriptor explorer", "descriptor explorer", &["d"]),
+    (Action::Psbt, "psbt", "PSBT QR", "PSBT QR", &["p"]),
+    (Action::Message, "message", "Sign / verify message", "sign/verify message", &["m"]),
+    (Action::Passphrase, "passphrase", "Unlock / lock wallet", "unlock/lock wallet", &["u"]),
+    (Action::Wallets, "wallets", "Wallets", "wallets", &["W"]),
+    (Action::History, "history", "Balance history", "balance history", &["g"]),
+    (Action::Palette, "palette", "Command palette", "command palette", &["Ctrl+P"]),
+    (Action::Quit, "quit", "Quit", "quit", &["q"]),
+    (Action::BookNew, "book_new", "New receive address (saved)", "new(save)", &["Ctrl+N"]),
+    (Action::BookChange, "book_change", "New change address (saved)", "change(save)", &["Ctrl+R"]),
+    (Action::BookGetNew, "book_get_new", "Get a new address", "getnew", &["Ctrl+G"]),
+    (Action::BookType, "book_type", "Cycle address type", "type", &["Ctrl+T"]),
+    (Action::BookEditLabel, "book_edit_label", "Edit label", "edit label", &["Ctrl+L"]),
+    (Action::BookEditNote, "book_edit_note", "Edit note", "edit note", &["Ctrl+O"]),
+    (Action::BookUsed, "book_used", "Toggle used", "used", &["Ctrl+U"]),
+    (Action::BookSearch, "book_search", "Search", "search", &["Ctrl+F"]),
+    (Action::BookSort, "book_sort", "Cycle sort order", "sort", &["Ctrl+S"]),
+    (Action::BookScan, "book_scan", "Scan balances", "scan balances", &["Ctrl+B"]),
+    (Action::BookWatch, "book_watch", "Watch address or descriptor", "watch-only", &["Ctrl+W"]),
+    (Action::BookQrLevel, "book_qr_level", "QR error correction level", "QR EC level", &["Ctrl+Q"]),
+    (Action::BookQrSize, "book_qr_size", "QR module size", "QR size", &["Ctrl+K"]),
+    (Action::BookExport, "book_export", "Export QR", "export QR", &["Ctrl+E"]),
+    (Action::BookDelete, "book_delete", "Delete entry", "delete", &["Ctrl+D"]),
+    (Action::BookCopy, "book_copy", "Copy address", "copy", &["Ctrl+C"]),
+    (Action::BookClose, "book_close", "Close address book", "close", &["Ctrl+X"]),
+    (Action::ExplorerScriptType, "explorer_script_type", "Explorer: script type for bare xpub", "script type for bare xpub", &["Ctrl+T"]),
+    (Action::ExplorerNetwork, "explorer_network", "Explorer: cycle network", "network", &["Ctrl+N"]),
+    (Action::ExplorerMore, "explorer_more", "Explorer: show more addresses", "more", &["+"]),
+    (Action::ExplorerFewer, "explorer_fewer", "Explorer: show fewer addresses", "fewer", &["-"]),
+    (Action::ExplorerAddToBook, "explorer_add_to_book", "Explorer: add address to book", "add to book", &["a"]),
+    (Action::ExplorerCopy, "explorer_copy", "Explorer: copy address", "copy", &["c"]),
+    (Action::ExplorerClose, "explorer_close", "Close descriptor explorer", "close", &["Ctrl+X"]),
+    (Action::PsbtCopy, "psbt_copy", "PSBT: copy base64", "copy base64", &["Ctrl+C"]),
+    (Action::PsbtSave, "psbt_save", "PSBT: save imported", "save imported", &["Ctrl+S"]),
+    (Action::PsbtShowImported, "psbt_show_imported", "PSBT: show imported as QR", "show imported as QR", &["Ctrl+L"]),
+    (Action::PsbtReset, "psbt_reset", "PSBT: reset import", "reset import", &["Ctrl+R"]),
+    (Action::PsbtClose, "psbt_close", "Close PSBT QR", "close", &["Ctrl+X"]),
+    (Action::MessageSign, "message_sign", "Message: sign", "sign", &["Ctrl+S"]),
+    (Action::MessageVerify, "message_verify", "Message: verify", "verify", &["Ctrl+V"]),
+    (Action::MessageCopy, "message_copy", "Message: copy signature", "copy signature", &["Ctrl+C"]),
+    (Action::MessageClose, "message_close", "Close sign / verify message", "close", &["Ctrl+X"]),
+    (Action::PassphraseMode, "passphrase_mode", "Passphrase: unlock / change / encrypt", "unlock/change/encrypt", &["Ctrl+T"]),
+    (Action::PassphraseLock, "passphrase_lock", "Passphrase: lock now", "lock now", &["Ctrl+L"]),
+    (Action::PassphraseClose, "passphrase_close", "Close unlock / lock wallet", "close", &["Ctrl+X"]),
+    (Action::WalletsTab, "wallets_tab", "Wallets: next tab", "next tab", &["Ctrl+T"]),
+    (Action::WalletsRefresh, "wallets_refresh", "Wallets: refresh list", "refresh", &["r"]),
+    (Action::WalletsBackup, "wallets_backup", "Wallets: back up selected", "backup", &["b"]),
+    (Action::WalletsCreate, "wallets_create", "Wallets: create", "create", &["c"]),
+    (Action::WalletsRestore, "wallets_restore", "Wallets: restore", "restore", &["o"]),
+    (Action::WalletsClose, "wallets_close", "Close wallets", "close", &["Ctrl+X"]),
+    (Action::HistoryDay, "history_day", "History: last day", "day", &["d"]),
+    (Action::HistoryWeek, "history_week", "History: last week", "week", &["w"]),
+    (Action::HistoryMonth, "history_month", "History: last month", "month", &["m"]),
+    (Action::HistoryAll, "history_all", "History: all time", "all", &["a"]),
+    (Action::HistoryReload, "history_reload", "History: reload", "reload", &["r"]),
+    (Action::HistoryClose, "history_close", "Close balance history", "close", &["q", "Ctrl+X"]),
+    (Action::PaletteClose, "palette_close", "Close command palette", "close", &["Ctrl+X"]),
+];
+
+impl Action {
+    /// Every action, in table order.
+    pub(crate) fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|row| row.0)
+    }
+
+    /// The action's row; `table_follows_the_enum` checks the order.
+    fn row(self) -> &'static ActionRow {
+        &ACTIONS[self as usize]
+    }
+
+    /// Name used in keys.json.
+    pub(crate) fn name(self) -> &'static str {
+        self.row().1
+    }
+
+    /// Description for the command palette.
+    pub(crate) fn label(self) -> &'static str {
+        self.row().2
+    }
+
+    /// Short text for the help bar.
+    fn help(self) -> &'static str {
+        self.row().3
+    }
+
+    pub(crate) fn scope(self) -> Scope {
+        const PREFIXES: [(&str, Scope); 8] = [
+            ("book_", Scope::AddressBook),
+            ("explorer_", Scope::Explorer),
+            ("psbt_", Scope::Psbt),
+            ("message_", Scope::Message),
+            ("passphrase_", Scope::Passphrase),
+            ("wallets_", Scope::Wallets),
+            ("history_", Scope::History),
+            ("palette_", Scope::Palette),
+        ];
+        PREFIXES
+            .iter()
+            .find(|(p, _)| self.name().starts_with(p))
+            .map(|(_, s)| *s)
+            .unwrap_or(Scope::Main)
+    }
+
+    /// Actions that only work on a list, never while a text field has
+    /// focus, so plain letters are fine for them.
+    fn on_list(self) -> bool {
+        matches!(
+            self,
+            Action::ExplorerMore
+                | Action::ExplorerFewer
+                | Action::ExplorerAddToBook
+                | Action::ExplorerCopy
+                | Action::WalletsRefresh
+                | Action::WalletsBackup
+                | Action::WalletsCreate
+                | Action::WalletsRestore
+        )
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        self.row().4
+    }
+}
+
+/// A key with or without Ctrl. Shift is part of the char (`W`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KeyBind {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 14] = [
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Space", KeyCode::Char(' ')),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PgUp", KeyCode::PageUp),
+    ("PgDn", KeyCode::PageDown),
+];
+
+impl KeyBind {
+    /// Parses `x`, `W`, `Ctrl+P`, `Enter`, `PgDn`, `F5` and the like.
+    /// Names are case-insensitive; single chars are not.
+    pub(crate) fn parse(s: &str) -> Result<KeyBind, String> {
+        let (ctrl, rest) = match s.split_once('+') {
+            Some((m, k)) if m.eq_ignore_ascii_case("ctrl") && !k.is_empty() => (true, k),
+            _ => (false, s),
+        };
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if ctrl => KeyCode::Char(c.to_ascii_lowercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMED_KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(rest)) {
+                Some((_, code)) => *code,
+                None => match rest.strip_prefix(['F', 'f']).and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key \"{s}\"")),
+                },
+            },
+        };
+        Ok(KeyBind { code, ctrl })
+    }
+
+    pub(crate) fn label(self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) if self.ctrl => c.to_ascii_uppercase().to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            code => NAMED_KEYS
+                .iter()
+                .find(|(_, c)| *c == code)
+                .map(|(n, _)| n.to_string())
+                .unwrap_or_else(|| format!("{code:?}")),
+        };
+        if self.ctrl { format!("Ctrl+{key}") } else { key }
+    }
+
+    fn matches(self, key: KeyEvent) -> bool {
+        key.code == self.code && key.modifiers.contains(KeyModifiers::CONTROL) == self.ctrl
+    }
+
+    /// Keys a screen keeps for itself: list selection in the main view,
+    /// range stepping in the history, and focus, selection and text editing
+    /// in the dialogs. Letters stay free for list-only actions.
+    fn reserved_for(self, action: Action) -> bool {
+        if self.ctrl {
+            return false;
+        }
+        let navigation = matches!(
+            self.code,
+            KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::Home
+                | KeyCode::End
+                | KeyCode::PageUp
+                | KeyCode::PageDown
+                | KeyCode::Backspace
+                | KeyCode::Delete
+                | KeyCode::Tab
+                | KeyCode::BackTab
+                | KeyCode::Enter
+                | KeyCode::Esc
+        );
+        match action.scope() {
+            Scope::Main => matches!(self.code, KeyCode::Up | KeyCode::Down),
+            Scope::History => navigation,
+            _ if action.on_list() => navigation,
+            _ => navigation || matches!(self.code, KeyCode::Char(_)),
+        }
+    }
+}
+
+/// Keys the screens handle themselves and that keys.json can't change,
+/// with their help bar text. Listed before the bound actions.
+const FIXED_KEYS: [(Scope, &str, &str); 32] = [
+    (Scope::Main, "↑/↓", "select command (scroll when Output is focused)"),
+    (Scope::AddressBook, "Tab", "label/amount"),
+    (Scope::AddressBook, "↑/↓", "select saved"),
+    (Scope::AddressBook, "←/→ Home End Backspace Delete", "edit"),
+    (Scope::Explorer, "Enter", "parse/check/QR"),
+    (Scope::Explorer, "Tab", "next field"),
+    (Scope::Explorer, "↑/↓", "select"),
+    (Scope::Explorer, "←/→", "receive/change"),
+    (Scope::Explorer, "Esc", "close"),
+    (Scope::Psbt, "Tab", "show/import"),
+    (Scope::Psbt, "Enter", "load PSBT / add UR part"),
+    (Scope::Psbt, "↑/↓", "frame rate"),
+    (Scope::Psbt, "PgUp/PgDn", "fragment size"),
+    (Scope::Psbt, "Esc", "close"),
+    (Scope::Message, "Tab", "next field"),
+    (Scope::Message, "↑/↓", "address from book"),
+    (Scope::Message, "Enter", "sign (verify in signature field)"),
+    (Scope::Message, "Esc", "close"),
+    (Scope::Passphrase, "Enter", "next field / confirm"),
+    (Scope::Passphrase, "Tab", "next field"),
+    (Scope::Passphrase, "↑/↓", "unlock time"),
+    (Scope::Passphrase, "Esc", "close"),
+    (Scope::Wallets, "↑/↓", "select"),
+    (Scope::Wallets, "Enter", "load/unload, create, back up or restore"),
+    (Scope::Wallets, "Tab", "next field"),
+    (Scope::Wallets, "Space", "toggle option"),
+    (Scope::Wallets, "Esc", "close"),
+    (Scope::History, "←/→", "range"),
+    (Scope::History, "Esc", "close"),
+    (Scope::Palette, "↑/↓", "select"),
+    (Scope::Palette, "Enter", "run"),
+    (Scope::Palette, "Esc", "close"),
+];
+
+/// The active bindings, one list of keys per action in `ACTIONS` order.
+#[derive(Debug, Clone)]
+pub(crate) struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBind>)>,
+}
+
+impl Keymap {
+    pub(crate) fn defaults() -> Keymap {
+        Keymap {
+            bindings: Action::all()
+                .map(|a| {
+                    let keys = a.default_keys().iter().filter_map(|k| KeyBind::parse(k).ok()).collect();
+                    (a, keys)
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn action(&self, scope: Scope, key: KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(a, keys)| a.scope() == scope && keys.iter().any(|k| k.matches(key)))
+            .map(|(a, _)| *a)
+    }
+
+    /// The action's keys for display, e.g. `j/PgDn`.
+    pub(crate) fn keys(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| keys.iter().map(|k| k.label()).collect::<Vec<_>>().join("/"))
+            .unwrap_or_default()
+    }
+
+    /// `keys=help` for one action, e.g. `Ctrl+S=sign`; None when unbound.
+    pub(crate) fn hint(&self, action: Action) -> Option<String> {
+        let keys = self.keys(action);
+        (!keys.is_empty()).then(|| format!("{keys}={}", action.help()))
+    }
+
+    /// ` (hint • hint)` to put after a title; empty when none is bound.
+    pub(crate) fn note(&self, actions: &[Action]) -> String {
+        let hints: Vec<String> = actions.iter().filter_map(|&a| self.hint(a)).collect();
+        if hints.is_empty() { String::new() } else { format!(" ({})", hints.join(" • ")) }
+    }
+
+    /// Hints for every bound action of a scope.
+    pub(crate) fn hints(&self, scope: Scope) -> Vec<String> {
+        self.bindings.iter().filter(|(a, _)| a.scope() == scope).filter_map(|(a, _)| self.hint(*a)).collect()
+    }
+
+    /// Help bar text for a scope: its fixed keys, then the bound actions.
+    pub(crate) fn help(&self, scope: Scope) -> String {
+        FIXED_KEYS
+            .iter()
+            .filter(|(s, _, _)| *s == scope)
+            .map(|(_, keys, help)| format!("{keys}={help}"))
+            .chain(self.hints(scope))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+
+    /// Replaces the keys of the actions named in `json`, e.g.
+    /// `{"scroll_down": ["j", "Down"], "quit": "Ctrl+Q"}`. Returns warnings
+    /// for unknown names and keys; those entries are skipped.
+    fn apply(&mut self, json: &serde_json::Value) -> Vec<String> {
+        let Some(map) = json.as_object() else {
+            return vec!["expected an object of action names to keys".to_string()];
+        };
+        let mut warnings = Vec::new();
+        for (name, value) in map {
+            let Some(slot) = self.bindings.iter_mut().find(|(a, _)| a.name() == name) else {
+                warnings.push(format!("unknown action \"{name}\""));
+                continue;
+            };
+            let specs: Vec<&str> = match value {
+                serde_json::Value::String(s) => vec![s.as_str()],
+                serde_json::Value::Array(a) => a.iter().filter_map(|v| v.as_str()).collect(),
+                _ => {
+                    warnings.push(format!("\"{name}\": expected a key or a list of keys"));
+                    continue;
+                }
+            };
+            let mut keys = Vec::new();
+            for &spec in &specs {
+                match KeyBind::parse(spec) {
+                    Ok(k) if k.reserved_for(slot.0) => {
+                        warnings.push(format!("\"{name}\": {} can't be rebound there", k.label()));
+                    }
+                    Ok(k) => keys.push(k),
+                    Err(e) => warnings.push(format!("\"{name}\": {e}")),
+                }
+            }
+            // An empty list unbinds; a list with only bad keys keeps the defaults
+            if !keys.is_empty() || specs.is_empty() {
+                slot.1 = keys;
+            }
+        }
+        warnings
+    }
+
+    /// Finds keys bound to two actions in the same scope. The action listed
+    /// first keeps the key; it is taken off the other one.
+    fn resolve_conflicts(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for i in 0..self.bindings.len() {
+            for j in i + 1..self.bindings.len() {
+                let (first, second) = (self.bindings[i].0, self.bindings[j].0);
+                if first.scope() != second.scope() {
+                    continue;
+                }
+                let shared: Vec<KeyBind> =
+                    self.bindings[j].1.iter().filter(|k| self.bindings[i].1.contains(k)).copied().collect();
+                for key in shared {
+                    warnings.push(format!(
+                        "{} is bound to both {} and {}; keeping it for {}",
+                        key.label(),
+                        first.name(),
+                        second.name(),
+                        first.name()
+                    ));
+                    self.bindings[j].1.retain(|k| *k != key);
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// Loads keys.json over the defaults. A missing file means the defaults;
+/// problems in it are returned as warnings for the startup notices.
+pub(crate) fn load_keymap(path: &Path) -> (Keymap, Vec<String>) {
+    let mut keymap = Keymap::defaults();
+    let data = match std::fs::read(path) {
+        Ok(d) => d,
+        Err(_) => return (keymap, Vec::new()),
+    };
+    let warnings = match serde_json::from_slice::<serde_json::Value>(&data) {
+        Ok(json) => {
+            let mut w = keymap.apply(&json);
+            w.extend(keymap.resolve_conflicts());
+            w
+        }
+        Err(e) => vec![format!("{e}, using default keys")],
+    };
+    let warnings = warnings.into_iter().map(|w| format!("{}: {w}", path.display())).collect();
+    (keymap, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ctrl, key, temp_dir};
+
+    fn applied(json: &str) -> (Keymap, Vec<String>) {
+        let mut keymap = Keymap::defaults();
+        let mut warnings = keymap.apply(&serde_json::from_str(json).unwrap());
+        warnings.extend(keymap.resolve_conflicts());
+        (keymap, warnings)
+    }
+
+    #[test]
+    fn parses_keys() {
+        let parse = |s| KeyBind::parse(s).map(|k| (k.code, k.ctrl));
+        assert_eq!(parse("j"), Ok((KeyCode::Char('j'), false)));
+        assert_eq!(parse("W"), Ok((KeyCode::Char('W'), false)));
+        assert_eq!(parse("+"), Ok((KeyCode::Char('+'), false)));
+        assert_eq!(parse("Ctrl+P"), Ok((KeyCode::Char('p'), true)));
+        assert_eq!(parse("ctrl+p"), Ok((KeyCode::Char('p'), true)));
+        assert_eq!(parse("Ctrl+Space"), Ok((KeyCode::Char(' '), true)));
+        assert_eq!(parse("enter"), Ok((KeyCode::Enter, false)));
+        assert_eq!(parse("PgDn"), Ok((KeyCode::PageDown, false)));
+        assert_eq!(parse("F5"), Ok((KeyCode::F(5), false)));
+        assert_eq!(parse("f12"), Ok((KeyCode::F(12), false)));
+        assert!(parse("F13").is_err());
+        assert!(parse("F0").is_err());
+        assert!(parse("Ctrl+").is_err());
+        assert!(parse("Hyper+x").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn labels_round_trip() {
+        for spec in ["j", "W", "Ctrl+P", "Ctrl+Space", "Enter", "PgUp", "F5", "+"] {
+            assert_eq!(KeyBind::parse(spec).unwrap().label(), spec);
+        }
+        assert_eq!(KeyBind::parse("ctrl+x").unwrap().label(), "Ctrl+X");
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let mut keymap = Keymap::defaults();
+        for action in Action::all() {
+            assert!(!action.default_keys().is_empty(), "{}", action.name());
+            for spec in action.default_keys() {
+                let key = KeyBind::parse(spec).unwrap();
+                assert!(!key.reserved_for(action), "{} {spec}", action.name());
+            }
+        }
+        assert_eq!(keymap.resolve_conflicts(), Vec::<String>::new());
+        let names: std::collections::HashSet<_> = Action::all().map(|a| a.name()).collect();
+        assert_eq!(names.len(), ACTIONS.len());
+    }
+
+    #[test]
+    fn table_follows_the_enum() {
+        for (i, row) in ACTIONS.iter().enumerate() {
+            assert_eq!(row.0 as usize, i, "{} is out of order", row.1);
+            assert_eq!(row.0.row().0, row.0);
+        }
+        assert_eq!(Action::PaletteClose as usize, ACTIONS.len() - 1);
+    }
+
+    #[test]
+    fn scopes_come_from_names() {
+        assert_eq!(Action::Explorer.scope(), Scope::Main);
+        assert_eq!(Action::History.scope(), Scope::Main);
+        assert_eq!(Action::BookCopy.scope(), Scope::AddressBook);
+        assert_eq!(Action::ExplorerCopy.scope(), Scope::Explorer);
+        assert_eq!(Action::PsbtReset.scope(), Scope::Psbt);
+        assert_eq!(Action::WalletsTab.scope(), Scope::Wallets);
+        assert_eq!(Action::HistoryDay.scope(), Scope::History);
+        assert_eq!(Action::PaletteClose.scope(), Scope::Palette);
+    }
+
+    #[test]
+    fn looks_up_by_scope() {
+        let keymap = Keymap::defaults();
+        assert_eq!(keymap.action(Scope::Main, key(KeyCode::Char('q'))), Some(Action::Quit));
+        assert_eq!(keymap.action(Scope::History, key(KeyCode::Char('q'))), Some(Action::HistoryClose));
+        assert_eq!(keymap.action(Scope::AddressBook, ctrl('t')), Some(Action::BookType));
+        assert_eq!(keymap.action(Scope::Explorer, ctrl('t')), Some(Action::ExplorerScriptType));
+        assert_eq!(keymap.action(Scope::Wallets, ctrl('t')), Some(Action::WalletsTab));
+        assert_eq!(keymap.action(Scope::Passphrase, ctrl('t')), Some(Action::PassphraseMode));
+        // Ctrl is part of the binding
+        assert_eq!(keymap.action(Scope::Main, ctrl('q')), None);
+        assert_eq!(keymap.action(Scope::AddressBook, key(KeyCode::Char('t'))), None);
+    }
+
+    #[test]
+    fn applies_overrides() {
+        let (keymap, warnings) = applied(r#"{"scroll_down": ["J", "Ctrl+N"], "psbt_save": "F2", "quit": []}"#);
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(keymap.keys(Action::ScrollDown), "J/Ctrl+N");
+        assert_eq!(keymap.action(Scope::Psbt, key(KeyCode::F(2))), Some(Action::PsbtSave));
+        assert_eq!(keymap.action(Scope::Psbt, ctrl('s')), None);
+        assert_eq!(keymap.keys(Action::Quit), "");
+        assert!(!keymap.help(Scope::Main).contains("quit"));
+    }
+
+    #[test]
+    fn reports_bad_entries() {
+        let (keymap, warnings) = applied(
+            r#"{"nope": "x", "refresh": "Hyper+r", "message_sign": "s", "book_copy": ["c", "Ctrl+Y"], "explorer_copy": "Enter", "history_day": "Left", "palette": 5}"#,
+        );
+        assert_eq!(
+            warnings,
+            [
+                "\"book_copy\": c can't be rebound there",
+                "\"explorer_copy\": Enter can't be rebound there",
+                "\"history_day\": Left can't be rebound there",
+                "\"message_sign\": s can't be rebound there",
+                "unknown action \"nope\"",
+                "\"palette\": expected a key or a list of keys",
+                "\"refresh\": unknown key \"Hyper+r\"",
+            ]
+        );
+        // Only bad keys: the defaults stay; a good key among bad ones is used
+        assert_eq!(keymap.keys(Action::Refresh), "r");
+        assert_eq!(keymap.keys(Action::MessageSign), "Ctrl+S");
+        assert_eq!(keymap.keys(Action::BookCopy), "Ctrl+Y");
+    }
+
+    #[test]
+    fn resolves_conflicts_within_a_scope() {
+        let (keymap, warnings) = applied(r#"{"quit": ["q", "r"], "book_search": "Ctrl+P"}"#);
+        assert_eq!(warnings, ["r is bound to both refresh and quit; keeping it for refresh"]);
+        assert_eq!(keymap.keys(Action::Quit), "q");
+        // The same key in another scope is no conflict
+        assert_eq!(keymap.action(Scope::Main, ctrl('p')), Some(Action::Palette));
+        assert_eq!(keymap.action(Scope::AddressBook, ctrl('p')), Some(Action::BookSearch));
+    }
+
+    #[test]
+    fn builds_help_text() {
+        let (keymap, _) = applied(r#"{"book_qr_size": [], "scroll_down": ["j", "PgDn"]}"#);
+        assert_eq!(keymap.hint(Action::ScrollDown).as_deref(), Some("j/PgDn=scroll down"));
+        assert_eq!(keymap.hint(Action::BookQrSize), None);
+        assert_eq!(keymap.note(&[Action::BookQrLevel, Action::BookQrSize]), " (Ctrl+Q=QR EC level)");
+        assert_eq!(keymap.note(&[Action::BookQrSize]), "");
+        assert_eq!(
+            keymap.hints(Scope::Psbt).join("  "),
+            "Ctrl+C=copy base64  Ctrl+S=save imported  Ctrl+L=show imported as QR  Ctrl+R=reset import  Ctrl+X=close"
+        );
+        assert_eq!(keymap.help(Scope::History), "←/→=range  Esc=close  d=day  w=week  m=month  a=all  r=reload  q/Ctrl+X=close");
+        assert_eq!(keymap.help(Scope::Palette), "↑/↓=select  Enter=run  Esc=close  Ctrl+X=close");
+    }
+
+    #[test]
+    fn missing_or_broken_file() {
+        let dir = temp_dir("keymap-file");
+        let path = dir.join("keys.json");
+        let (keymap, warnings) = load_keymap(&path);
+        assert!(warnings.is_empty());
+        assert_eq!(keymap.keys(Action::Quit), "q");
+        std::fs::write(&path, "{not json").unwrap();
+        let (keymap, warnings) = load_keymap(&path);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].ends_with("using default keys"), "{warnings:?}");
+        assert_eq!(keymap.keys(Action::Quit), "q");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,563 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use bitcoin::Network;
+use chrono::{DateTime, Utc};
+
+use crate::address::{AddrValidity, AddressType, check_address};
+use crate::cli::{run_bitcoin_cli, run_bitcoin_cli_args};
+use crate::descriptor::descriptor_checksum;
+use crate::file::{AddressEntry, write_atomic};
+use crate::node::fetch_wallet_name;
+
+// ===== BIP-329 wallet labels =====
+
+/// One line of a BIP-329 JSON Lines export. Unknown fields are dropped;
+/// records of types we do not map are kept verbatim in the sidecar file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelRecord {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "ref")]
+    pub reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+const SUPPORTED_TYPES: [&str; 4] = ["addr", "tx", "output", "xpub"];
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub addresses: usize,
+    pub other: usize,
+    pub skipped: usize,
+    pub synced: usize,
+}
+
+pub(crate) fn read_label_records(path: &Path) -> Result<Vec<LabelRecord>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut out = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let rec: LabelRecord =
+            serde_json::from_str(&line).map_err(|e| format!("{}:{}: {e}", path.display(), n + 1))?;
+        out.push(rec);
+    }
+    Ok(out)
+}
+
+/// Non-address labels kept between imports and exports. A missing file
+/// simply means nothing was imported yet.
+pub(crate) fn load_sidecar(path: &Path) -> Result<Vec<LabelRecord>, String> {
+    if path.exists() {
+        read_label_records(path)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+pub(crate) fn write_label_records(path: &Path, records: &[LabelRecord]) -> Result<(), String> {
+    let mut out = String::new();
+    for rec in records {
+        out.push_str(&serde_json::to_string(rec).map_err(|e| e.to_string())?);
+        out.push('\n');
+    }
+    write_atomic(path, out.as_bytes()).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Labels the node's current wallet knows about, keyed by address.
+fn fetch_node_labels() -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    let Ok(list) = run_bitcoin_cli("listlabels") else {
+        return out;
+    };
+    let labels: Vec<String> = serde_json::from_str(&list).unwrap_or_default();
+    for label in labels.into_iter().filter(|l| !l.is_empty()) {
+        let Ok(res) = run_bitcoin_cli_args("getaddressesbylabel", &[&label]) else {
+            continue;
+        };
+        if let Ok(serde_json::Value::Object(map)) = serde_json::from_str(&res) {
+            for addr in map.keys() {
+                out.insert(addr.clone(), label.clone());
+            }
+        }
+    }
+    out
+}
+
+/// Builds the BIP-329 export: address book labels (which win over the
+/// node's), node wallet labels, then the preserved non-address records.
+pub(crate) fn export_bip329(
+    book: &[AddressEntry],
+    sidecar: &[LabelRecord],
+    path: &Path,
+) -> Result<usize, String> {
+    let mut records: Vec<LabelRecord> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for e in book.iter().filter(|e| !e.label.is_empty() && !e.is_descriptor()) {
+        seen.insert(e.address.clone());
+        records.push(LabelRecord {
+            kind: "addr".to_string(),
+            reference: e.address.clone(),
+            label: Some(e.label.clone()),
+            origin: None,
+            spendable: None,
+        });
+    }
+
+    let mut node_labels: Vec<(String, String)> = fetch_node_labels().into_iter().collect();
+    node_labels.sort();
+    for (addr, label) in node_labels {
+        if seen.contains(&addr) {
+            continue;
+        }
+        records.push(LabelRecord {
+            kind: "addr".to_string(),
+            reference: addr,
+            label: Some(label),
+            origin: None,
+            spendable: None,
+        });
+    }
+
+    records.extend(sidecar.iter().filter(|r| r.kind != "addr").cloned());
+    write_label_records(path, &records)?;
+    Ok(records.len())
+}
+
+/// Applies a BIP-329 file: addr labels go into the address book (new
+/// entries are created for unknown addresses) and to the node with
+/// `setlabel`; tx, output and xpub labels are merged into `sidecar`.
+/// New entries get `node_chain` when the address is valid there, which
+/// tells signet from testnet.
+pub(crate) fn import_bip329(
+    path: &Path,
+    book: &mut Vec<AddressEntry>,
+    sidecar: &mut Vec<LabelRecord>,
+    node_chain: Option<Network>,
+) -> Result<ImportSummary, String> {
+    let records = read_label_records(path)?;
+    let node_wallet = fetch_wallet_name();
+    let mut summary = ImportSummary::default();
+
+    for rec in records {
+        if !SUPPORTED_TYPES.contains(&rec.kind.as_str()) {
+            summary.skipped += 1;
+            continue;
+        }
+        if rec.kind != "addr" {
+            match sidecar
+                .iter_mut()
+                .find(|r| r.kind == rec.kind && r.reference == rec.reference)
+            {
+                Some(existing) => *existing = rec,
+                None => sidecar.push(rec),
+            }
+            summary.other += 1;
+            continue;
+        }
+
+        let label = rec.label.unwrap_or_default();
+        let network = match check_address(&rec.reference) {
+            AddrValidity::Valid(info) => info.pick(node_chain),
+            _ => {
+                summary.skipped += 1;
+                continue;
+            }
+        };
+        match book.iter_mut().find(|e| e.address == rec.reference) {
+            Some(e) => {
+                if !label.is_empty() {
+                    e.label = label.clone();
+                }
+            }
+            None => book.push(AddressEntry {
+                created_at: Utc::now(),
+                address: rec.reference.clone(),
+                address_type: AddressType::of_address(&rec.reference),
+                label: label.clone(),
+                note: String::new(),
+                network: Some(network),
+                wallet: None,
+                used: false,
+                watch_only: false,
+                descriptor: None,
+            }),
+        }
+        summary.addresses += 1;
+
+        if node_wallet.is_some()
+            && !label.is_empty()
+            && let Ok(out) = run_bitcoin_cli_args("setlabel", &[&rec.reference, &label])
+            && !out.starts_with("Error:")
+        {
+            summary.synced += 1;
+        }
+    }
+    Ok(summary)
+}
+
+// ===== CSV =====
+
+const CSV_HEADER: [&str; 10] = [
+    "created_at",
+    "address",
+    "label",
+    "note",
+    "address_type",
+    "network",
+    "wallet",
+    "used",
+    "watch_only",
+    "descriptor",
+];
+
+/// Rows read from a CSV and rows left out for lack of a valid address
+/// or checksummed descriptor.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CsvSummary {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// A descriptor entry as the book stores it: `desc#checksum`.
+fn is_checksummed_descriptor(s: &str) -> bool {
+    s.contains('(')
+        && s.rsplit_once('#')
+            .is_some_and(|(desc, sum)| descriptor_checksum(desc).is_ok_and(|c| c == sum))
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Splits CSV text into records, honouring quoted fields with embedded
+/// commas, quotes and newlines (RFC 4180).
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+pub(crate) fn export_csv(book: &[AddressEntry], path: &Path) -> Result<usize, String> {
+    let mut out = CSV_HEADER.join(",");
+    out.push('\n');
+    for e in book {
+        let fields = [
+            e.created_at.to_rfc3339(),
+            e.address.clone(),
+            e.label.clone(),
+            e.note.clone(),
+            e.address_type.map(|t| t.rpc_name().to_string()).unwrap_or_default(),
+            e.network.map(|n| n.to_string()).unwrap_or_default(),
+            e.wallet.clone().unwrap_or_default(),
+            e.used.to_string(),
+            e.watch_only.to_string(),
+            e.descriptor.clone().unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    std::fs::write(path, out).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(book.len())
+}
+
+/// Reads a CSV written by `export_csv` and merges it into the book.
+pub(crate) fn import_csv(
+    path: &Path,
+    book: &mut Vec<AddressEntry>,
+    node_chain: Option<Network>,
+) -> Result<CsvSummary, String> {
+    let shown = path.display();
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{shown}: {e}"))?;
+    merge_csv(&text, book, node_chain).map_err(|e| format!("{shown}: {e}"))
+}
+
+/// Merges CSV text into the book. Columns are found by their header name
+/// (any order, any case, unknown ones ignored); only `address` is
+/// required. Imported values overwrite existing ones when non-empty.
+fn merge_csv(text: &str, book: &mut Vec<AddressEntry>, node_chain: Option<Network>) -> Result<CsvSummary, String> {
+    // Spreadsheet exports often start with a byte order mark
+    let mut rows = parse_csv(text.trim_start_matches('\u{feff}')).into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .ok_or("empty file")?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    if !header.iter().any(|h| h == "address") {
+        return Err("no 'address' column".to_string());
+    }
+
+    let mut summary = CsvSummary::default();
+    for row in rows {
+        // Header name -> value; the first of two same-named columns wins
+        let mut fields: HashMap<&str, &str> = HashMap::new();
+        for (name, value) in header.iter().zip(&row) {
+            fields.entry(name).or_insert(value.trim());
+        }
+        let get = |name: &str| fields.get(name).copied().unwrap_or("");
+
+        let address = get("address");
+        if address.is_empty() && row.iter().all(|v| v.trim().is_empty()) {
+            continue;
+        }
+        // The exported network column settles testnet vs signet
+        let exported: Option<Network> = get("network").parse().ok();
+        let network = match check_address(address) {
+            AddrValidity::Valid(info) => Some(info.pick(exported.or(node_chain))),
+            _ if is_checksummed_descriptor(address) => exported.or(node_chain),
+            _ => {
+                summary.skipped += 1;
+                continue;
+            }
+        };
+
+        let created_at = DateTime::parse_from_rfc3339(get("created_at"))
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+        let address_type = serde_json::from_value(serde_json::Value::String(get("address_type").to_string()))
+            .ok()
+            .or_else(|| AddressType::of_address(address));
+        let wallet = Some(get("wallet").to_string()).filter(|w| !w.is_empty());
+        let used = get("used").eq_ignore_ascii_case("true");
+        let watch_only = get("watch_only").eq_ignore_ascii_case("true");
+        let descriptor = Some(get("descriptor").to_string()).filter(|d| is_checksummed_descriptor(d));
+        let (label, note) = (get("label"), get("note"));
+
+        match book.iter_mut().find(|e| e.address == address) {
+            Some(e) => {
+                if !label.is_empty() {
+                    e.label = label.to_string();
+                }
+                if !note.is_empty() {
+                    e.note = note.to_string();
+                }
+                e.address_type = e.address_type.or(address_type);
+                e.wallet = e.wallet.clone().or(wallet);
+                e.used |= used;
+                e.watch_only |= watch_only;
+                e.descriptor = e.descriptor.clone().or(descriptor);
+            }
+            None => book.push(AddressEntry {
+                created_at,
+                address: address.to_string(),
+                address_type,
+                label: label.to_string(),
+                note: note.to_string(),
+                network,
+                wallet,
+                used,
+                watch_only,
+                descriptor,
+            }),
+        }
+        summary.imported += 1;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    const RECEIVE: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+    const LEGACY: &str = "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA";
+    const TESTNET: &str = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+
+    fn entry(address: &str, label: &str) -> AddressEntry {
+        serde_json::from_value(serde_json::json!({
+            "created_at": "2024-01-01T00:00:00Z",
+            "address": address,
+            "label": label,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_rfc4180() {
+        let text = "a,b,c\r\n\"x, y\",\"say \"\"hi\"\"\",\"two\nlines\"\n,,\nlast";
+        assert_eq!(
+            parse_csv(text),
+            [
+                vec!["a", "b", "c"],
+                vec!["x, y", "say \"hi\"", "two\nlines"],
+                vec!["", "", ""],
+                vec!["last"],
+            ]
+        );
+        assert!(parse_csv("").is_empty());
+    }
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let dir = temp_dir("labels-roundtrip");
+        let path = dir.join("book.csv");
+        let mut rent = entry(RECEIVE, "rent, \"march\"");
+        rent.note = "first\nsecond".to_string();
+        rent.used = true;
+        rent.network = Some(Network::Bitcoin);
+        let desc = "wpkh([d34db33f/84h/0h/0h]xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)";
+        let desc = format!("{desc}#{}", descriptor_checksum(desc).unwrap());
+        let mut watched = entry(&desc, "cold");
+        (watched.watch_only, watched.descriptor) = (true, Some(desc.clone()));
+        let mut derived = entry(LEGACY, "");
+        derived.descriptor = Some(desc.clone());
+        let book = vec![rent, derived, watched];
+        assert_eq!(export_csv(&book, &path).unwrap(), 3);
+
+        let mut imported = Vec::new();
+        assert_eq!(import_csv(&path, &mut imported, None).unwrap(), CsvSummary { imported: 3, skipped: 0 });
+        assert_eq!(imported[0].label, "rent, \"march\"");
+        assert_eq!(imported[0].note, "first\nsecond");
+        assert_eq!(imported[0].created_at, book[0].created_at);
+        assert!(imported[0].used && !imported[1].used);
+        assert_eq!(imported[0].address_type, AddressType::of_address(RECEIVE));
+        assert_eq!(imported[1].address, LEGACY);
+        // Descriptor entries and where addresses came from survive
+        assert_eq!(imported[1].descriptor.as_deref(), Some(desc.as_str()));
+        assert_eq!(imported[2].address, desc);
+        assert!(imported[2].watch_only && imported[2].is_descriptor());
+        assert_eq!(imported[2].network, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn csv_columns_by_name() {
+        // Reordered, differently cased, with an unknown column and a BOM
+        let text = format!(
+            "\u{feff}Label,Extra,NETWORK, address ,note\nrent,x,,{RECEIVE},\ncoffee,y,signet,{TESTNET},beans\nbad,z,,notanaddress,\n"
+        );
+        let mut book = vec![entry(RECEIVE, "old")];
+        assert_eq!(merge_csv(&text, &mut book, None).unwrap(), CsvSummary { imported: 2, skipped: 1 });
+        assert_eq!(book.len(), 2);
+        assert_eq!(book[0].label, "rent");
+        assert_eq!((book[1].label.as_str(), book[1].note.as_str()), ("coffee", "beans"));
+        assert_eq!(book[1].network, Some(Network::Signet));
+
+        // Empty values keep what the book has
+        assert_eq!(merge_csv(&format!("address,label\n{RECEIVE},\n"), &mut book, None).unwrap().imported, 1);
+        assert_eq!(book[0].label, "rent");
+
+        // Of two same-named columns the first counts
+        merge_csv(&format!("label,address,label\nfirst,{RECEIVE},second\n"), &mut book, None).unwrap();
+        assert_eq!(book[0].label, "first");
+        // Short rows just lack the trailing fields
+        merge_csv(&format!("address,label,note\n{LEGACY}\n"), &mut book, None).unwrap();
+        assert_eq!(book[2].label, "");
+    }
+
+    #[test]
+    fn csv_skips_bad_rows_and_uses_the_node_chain() {
+        let mut book = Vec::new();
+        let text = format!("address\n{TESTNET}\nwpkh(xpub)#qqqqqqqq\nraw(deadbeef)#89f8spxm\n\n");
+        let summary = merge_csv(&text, &mut book, Some(Network::Signet)).unwrap();
+        // The blank line is not counted, the bad checksum is
+        assert_eq!(summary, CsvSummary { imported: 2, skipped: 1 });
+        assert_eq!(book[0].network, Some(Network::Signet));
+        assert_eq!((book[1].address.as_str(), book[1].network), ("raw(deadbeef)#89f8spxm", Some(Network::Signet)));
+    }
+
+    #[test]
+    fn csv_needs_an_address_column() {
+        assert_eq!(merge_csv("", &mut Vec::new(), None).unwrap_err(), "empty file");
+        assert_eq!(merge_csv("label,addr\nx,y\n", &mut Vec::new(), None).unwrap_err(), "no 'address' column");
+    }
+
+    #[test]
+    fn bip329_records() {
+        // Example lines from BIP-329
+        let dir = temp_dir("labels-bip329");
+        let path = dir.join("labels.jsonl");
+        let lines = [
+            r#"{ "type": "tx", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd", "label": "Transaction", "origin": "wpkh([d34db33f/84'/0'/0'])" }"#,
+            r#"{ "type": "addr", "ref": "bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c", "label": "Address" }"#,
+            r#"{ "type": "output", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0", "label": "Output", "spendable": false }"#,
+            r#"{ "type": "pubkey", "ref": "0283409659355b6d1cc3c32decd5d561abaac86c37a353b52895a5e6c196d6f448", "label": "Public Key" }"#,
+        ];
+        std::fs::write(&path, format!("{}\n\n", lines.join("\n"))).unwrap();
+        let records = read_label_records(&path).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].origin.as_deref(), Some("wpkh([d34db33f/84'/0'/0'])"));
+        assert_eq!((records[1].kind.as_str(), records[1].label.as_deref()), ("addr", Some("Address")));
+        assert_eq!(records[2].spendable, Some(false));
+        assert!(!SUPPORTED_TYPES.contains(&records[3].kind.as_str()));
+
+        // Written back without the absent optional fields
+        write_label_records(&path, &records[1..2]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"type\":\"addr\",\"ref\":\"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c\",\"label\":\"Address\"}\n"
+        );
+
+        std::fs::write(&path, "{\"type\": \"addr\"}\n").unwrap();
+        assert!(read_label_records(&path).unwrap_err().contains("labels.jsonl:1:"));
+        assert!(load_sidecar(&dir.join("missing.jsonl")).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bip329_import_uses_the_node_chain() {
+        let dir = temp_dir("labels-chain");
+        let path = dir.join("labels.jsonl");
+        std::fs::write(&path, format!("{{\"type\": \"addr\", \"ref\": \"{TESTNET}\", \"label\": \"faucet\"}}\n")).unwrap();
+        let (mut book, mut sidecar) = (Vec::new(), Vec::new());
+        import_bip329(&path, &mut book, &mut sidecar, Some(Network::Signet)).unwrap();
+        assert_eq!((book[0].label.as_str(), book[0].network), ("faucet", Some(Network::Signet)));
+        // A mainnet node can't use the address, so it keeps its own network
+        let mut book = Vec::new();
+        import_bip329(&path, &mut book, &mut sidecar, Some(Network::Bitcoin)).unwrap();
+        assert_eq!(book[0].network, Some(Network::Testnet));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,11 @@
 use std::time::{Duration, Instant};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode,
+        KeyEvent, MouseButton, MouseEvent, MouseEventKind,
+    },
+    cursor::Show,
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -9,91 +13,230 @@ use crossterm::{
 use ratatui::{
     Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
 };
 
-use qrcode::QrCode;
-use qrcode::render::unicode;
-
-use bitcoin::{Address, Network};
-use core::str::FromStr;
-
 use arboard::Clipboard;
-use std::io::{self};
-
-use chrono::{Utc};
+use std::io;
 
+mod address;
+mod args;
 mod cli;
-mod node;
+mod config;
+mod crypto;
+mod descriptor;
+mod explorer;
+mod fiat;
 mod file;
+mod fuzzy;
+mod history;
+mod input;
+mod keymap;
+mod labels;
+mod message;
+mod node;
+mod overlay;
+mod palette;
+mod paths;
+mod privacy;
+mod psbt;
+mod qr;
+#[cfg(test)]
+mod test_util;
+mod theme;
+mod ur;
+mod units;
+mod walletlock;
+mod wallets;
+mod watch;
+
+use crate::cli::{run_bitcoin_cli, run_bitcoin_cli_args};
+use crate::config::{load_settings, save_settings};
+use crate::node::{LockState, WalletInfo, fetch_chain, fetch_lock_state, fetch_node_info, fetch_wallet_info};
+use crate::crypto::BookCipher;
+use crate::file::{
+    BookFile, load_address_book, load_commands, lock_address_book, remove_plain_backups,
+    save_address_book, unlock_address_book,
+};
+use crate::input::prompt_passphrase;
 
-use crate::cli::run_bitcoin_cli;
-use crate::node::{fetch_node_info, fetch_wallet_info};
-use crate::file::{load_commands_from_json, load_address_book, save_address_book};
+use crate::explorer::{DescriptorExplorer, ExplorerAction, ExplorerFocus};
+use crate::fiat::{FiatFeed, with_fiat};
+use crate::overlay::AddressOverlay;
+use crate::args::{Task, USAGE, parse_args};
+
+use crate::history::BalanceHistory;
+use crate::message::MessageTool;
+use crate::keymap::{Action, Scope, load_keymap};
+use crate::palette::{CommandPalette, PaletteAction, PaletteTarget};
+use crate::paths::Paths;
+use crate::privacy::Privacy;
+use crate::theme::Themes;
+use crate::walletlock::{PassMode, PassphraseDialog};
+use crate::wallets::{WalletManager, WalletTab};
+use crate::psbt::PsbtScreen;
+use crate::watch::{WatchStatus, fetch_watch_status};
 
 use file::AddressEntry;
-use file::ADDRESS_BOOK_PATH;
+
+/// Which modal screen, if any, is drawn over the main view and receives keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Main,
+    AddressBook,
+    Explorer,
+    Psbt,
+    Message,
+    Passphrase,
+    Wallets,
+    History,
+    Palette,
+}
+
+/// Main view pane that receives ↑/↓, picked by clicking it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Commands,
+    Output,
+}
+
+/// Output lines moved per scroll wheel step.
+const SCROLL_LINES: usize = 3;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let paths = Paths::resolve(args.config_dir, args.data_dir);
+    let migrated = match paths.prepare() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(task) = args.task {
+        match run_task(task, &paths) {
+            Ok(msg) => println!("{msg}"),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     enable_raw_mode()?;
+    let _restore = TerminalGuard;
 
     const VERSION_LABEL: &str = concat!(" bitatui ", env!("CARGO_PKG_VERSION"));
 
-    let mut hide_amounts = false;
-
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Load command list (left pane)
-    let commands = load_commands_from_json("commands.json")?;
+    let (commands, commands_warning) = load_commands(&paths.commands());
+    let (mut settings, settings_warning) = load_settings(&paths.settings());
+    let mut privacy = Privacy::new(settings.privacy);
+    let (keymap, keymap_warnings) = load_keymap(&paths.keymap());
+    let (mut themes, theme_warnings) = Themes::load(&settings.theme, &settings.themes);
 
     // Main UI state
     let mut selected = 0usize;
+    let mut focus = Pane::Commands;
+    // Where the panes were last drawn, for mouse clicks
+    let mut frame_size = Rect::default();
+    let mut commands_area = Rect::default();
+    let mut output_area = Rect::default();
     let mut last_input = Instant::now();
-    let mut output = String::new();
-    let mut output_lines: Vec<String> = Vec::new();
     let mut scroll_offset = 0usize;
 
-    let _refresh_interval = Duration::from_secs(5);
+    let refresh_interval = Duration::from_secs(5);
     let mut _last_refresh = Instant::now();
 
-    // Node/Wallet info
-    let mut node_info = String::new();
-    let mut wallet_info = String::new();
-
     // Overlay state
-    let mut show_qr_overlay = false;
-    let mut address = String::from("bc1qfpacvgpjms0eu6mszhwgjjs03yldesmmcgzad0");
-    let mut addr_cursor: usize = address.len();
-
-    // Address book state (persistent)
-    let mut addr_book: Vec<AddressEntry> = load_address_book(ADDRESS_BOOK_PATH);
-    let mut addr_selected: usize = if addr_book.is_empty() {
-        0
-    } else {
-        addr_book.len() - 1
-    };
+    let mut screen = Screen::Main;
+    let mut explorer = DescriptorExplorer::new();
+    let mut psbt_screen = PsbtScreen::new(paths.data_dir.clone());
+    let mut message_tool = MessageTool::new();
+    let mut passphrase = PassphraseDialog::new();
+    let mut history = BalanceHistory::new();
+    let mut wallets = WalletManager::new(&paths.data_dir.join("backups"));
+    let mut palette = CommandPalette::new();
+
+    // Address book state (persistent). If the file is broken or another
+    // instance holds the lock, the book stays read-only for this session.
+    let book_lock = lock_address_book(&paths.address_book());
+    // An encrypted book stays locked until the passphrase is entered in
+    // the overlay.
+    let (mut addr_book, locked, book_error): (Vec<AddressEntry>, Option<Vec<u8>>, Option<String>) =
+        match load_address_book(&paths.address_book()) {
+            Ok(BookFile::Plain(book)) => (book, None, None),
+            Ok(BookFile::Locked(data)) => (Vec::new(), Some(data), None),
+            Err(e) => (Vec::new(), None, Some(format!("Address book not loaded: {e}"))),
+        };
+    let read_only = book_error.or_else(|| book_lock.as_ref().err().cloned());
+    let mut overlay = AddressOverlay::new(&addr_book, paths.address_book());
+    overlay.read_only = read_only.clone();
+    overlay.locked = locked;
+    overlay.unit = settings.unit;
 
     // Initial fetches
-    output = run_bitcoin_cli(&commands[selected])?;
-    output_lines = output.lines().map(|l| l.to_string()).collect();
+    let mut output = command_output(run_bitcoin_cli(&commands[selected]));
+    // The command behind `output`; the selection can move without running
+    let mut output_command = commands[selected].clone();
+    let mut output_lines: Vec<String> = output.lines().map(|l| l.to_string()).collect();
+    // Startup notices go above the first command's output
+    let notices: Vec<String> = migrated
+        .iter()
+        .map(|m| format!("Migrated {m}"))
+        .chain(commands_warning)
+        .chain(settings_warning)
+        .chain(keymap_warnings)
+        .chain(theme_warnings)
+        .chain(read_only.map(|r| format!("{r} (address book is read-only)")))
+        .collect();
+    if !notices.is_empty() {
+        output_lines.splice(0..0, notices.into_iter().chain([String::new()]));
+    }
+
+    // Node/Wallet info
+    let mut node_info = fetch_node_info().unwrap_or_else(|_| "Failed to fetch node info".to_string());
+    let mut wallet_info: Option<WalletInfo> = fetch_wallet_info().ok();
+    let mut lock_state: Option<LockState> = fetch_lock_state();
+    // Optional fiat rate, fetched in the background
+    let mut fiat: Option<FiatFeed> = settings.fiat.clone().map(|f| FiatFeed::new(f, &paths.fiat_cache()));
 
-    node_info = fetch_node_info().unwrap_or_else(|_| "Failed to fetch node info".to_string());
-    wallet_info = fetch_wallet_info().unwrap_or_else(|_| "Failed to fetch wallet info".to_string());
+    // Watch-only wallet panel, shown once anything is watched. Refreshed on
+    // 'r' and every few seconds while an import or rescan is running.
+    let mut watch_status: Option<Result<WatchStatus, String>> = None;
+    let mut last_watch_refresh = Instant::now();
+    if addr_book.iter().any(|e| e.watch_only) {
+        watch_status = Some(fetch_watch_status());
+    }
 
     loop {
             terminal.draw(|f| {
             let size = f.size();
+            let theme = themes.active();
+            let rate = fiat.as_ref().and_then(|feed| feed.rate.as_ref());
+            frame_size = size;
 
             // === NEW: global background ===
-            let bg_block = Block::default()
-                .style(Style::default());
-                // .style(Style::default().bg(Color::Rgb(58, 100, 148)));
+            let bg_block = Block::default().style(theme.base());
 
             f.render_widget(bg_block, size);
 
@@ -109,10 +252,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
                 .split(root[0]);
 
-            // Left: Node Info (7), Wallet Info (7), Commands (rest)
+            // Left: Node Info (7), Wallet Info (9, 11 with fiat), Watch-only (7, if any), Commands (rest)
+            let watched = addr_book.iter().filter(|e| e.watch_only).count();
             let left_chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Length(7), Constraint::Length(7), Constraint::Min(0)])
+                .constraints([
+                    Constraint::Length(7),
+                    Constraint::Length(if fiat.is_some() { 11 } else { 9 }),
+                    Constraint::Length(if watched > 0 { 7 } else { 0 }),
+                    Constraint::Min(0),
+                ])
                 .split(main_chunks[0]);
 
             // Node Info panel
@@ -126,12 +275,60 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .wrap(Wrap { trim: true });
             f.render_widget(node_info_paragraph, left_chunks[0]);
 
-            // Wallet Info panel
-            let wallet_info_paragraph = Paragraph::new(mask_digits_if(&wallet_info, hide_amounts))
+            // Wallet Info panel, with the lock state counting down
+            let now = chrono::Utc::now().timestamp();
+            let mut wallet_text = match &wallet_info {
+                Some(w) => w.lines(settings.unit, privacy),
+                None => "Failed to fetch wallet info".to_string(),
+            };
+            if let Some(lock) = lock_state {
+                wallet_text.push('\n');
+                wallet_text.push_str(&lock.describe(now));
+            }
+            if let Some(feed) = &fiat {
+                let value = wallet_info
+                    .as_ref()
+                    .and_then(|w| feed.format(w.balances.trusted + w.balances.pending));
+                if let Some(v) = value {
+                    wallet_text.push_str(&format!("\n{}", privacy.amount(&v)));
+                }
+                wallet_text.push('\n');
+                wallet_text.push_str(&feed.status());
+            }
+            let wallet_info_paragraph = Paragraph::new(wallet_text)
                 .block(Block::default().title("Wallet Info").borders(Borders::ALL))
                 .wrap(Wrap { trim: true });
             f.render_widget(wallet_info_paragraph, left_chunks[1]);
 
+            // Watch-only panel
+            if watched > 0 {
+                let text = match &watch_status {
+                    Some(Ok(w)) => {
+                        let scan = match w.scanning {
+                            Some(p) => format!("Rescanning: {:.0}%", p * 100.0),
+                            None if overlay.watch_importing() => "Importing…".to_string(),
+                            None => "Rescan: done".to_string(),
+                        };
+                        let btc = |a: bitcoin::Amount| {
+                            privacy.amount(&with_fiat(settings.unit.format(a), a.to_sat() as i64, rate))
+                        };
+                        format!(
+                            "Watching: {watched}   {scan}\nConfirmed: {}\nPending: {}\nImmature: {}\nTransactions: {}",
+                            btc(w.trusted),
+                            btc(w.pending),
+                            btc(w.immature),
+                            w.tx_count
+                        )
+                    }
+                    Some(Err(e)) => format!("Watching: {watched}\n{e}"),
+                    None => format!("Watching: {watched}\n(press r to refresh)"),
+                };
+                let watch_paragraph = Paragraph::new(text)
+                    .block(Block::default().title("Watch-only").borders(Borders::ALL))
+                    .wrap(Wrap { trim: true });
+                f.render_widget(watch_paragraph, left_chunks[2]);
+            }
+
             // Commands list
             let items: Vec<ListItem> = commands
                 .iter()
@@ -139,55 +336,118 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .map(|(i, cmd)| {
                     let mut item = ListItem::new(cmd.to_string());
                     if i == selected {
-                        item = item.style(
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
-                        );
+                        item = item.style(theme.selected());
                     }
                     item
                 })
                 .collect();
 
-            let list = List::new(items).block(Block::default().title("Commands").borders(Borders::ALL));
-            f.render_widget(list, left_chunks[2]);
+            let pane_style = |pane: Pane| if focus == pane { theme.focused() } else { Style::default() };
+            let list = List::new(items).block(
+                Block::default()
+                    .title("Commands")
+                    .borders(Borders::ALL)
+                    .border_style(pane_style(Pane::Commands)),
+            );
+            f.render_widget(list, left_chunks[3]);
+            commands_area = left_chunks[3];
 
             // Right: Output panel
             let height = main_chunks[1].height as usize;
-            let visible_height = if height > 2 { height - 2 } else { 0 };
-            let visible_lines = if output_lines.len() > visible_height + scroll_offset {
-                &output_lines[scroll_offset..scroll_offset + visible_height]
-            } else if scroll_offset < output_lines.len() {
-                &output_lines[scroll_offset..]
+            let visible_height = height.saturating_sub(2);
+            let shown_lines =
+                privacy.output(&output_command, &settings.unit.output(&output_command, &output_lines, rate));
+            let visible_lines = if shown_lines.len() > visible_height + scroll_offset {
+                &shown_lines[scroll_offset..scroll_offset + visible_height]
+            } else if scroll_offset < shown_lines.len() {
+                &shown_lines[scroll_offset..]
             } else {
                 &[] as &[String]
             };
 
             let paragraph = Paragraph::new(visible_lines.join("\n"))
-                .block(Block::default().title("Output").borders(Borders::ALL))
+                .block(
+                    Block::default()
+                        .title("Output")
+                        .borders(Borders::ALL)
+                        .border_style(pane_style(Pane::Output)),
+                )
                 .wrap(Wrap { trim: false });
             f.render_widget(paragraph, main_chunks[1]);
+            output_area = main_chunks[1];
 
             // ===== Bottom Help bar =====
-            let orange = Color::Rgb(255, 165, 0);
-            let help_lines: Vec<Line> = if show_qr_overlay {
-                vec![
+            let help_lines: Vec<Line> = match screen {
+                Screen::AddressBook => vec![
                     Line::from(Span::styled(
                         "Overlay keys:",
-                        Style::default().fg(orange).add_modifier(Modifier::BOLD),
+                        theme.heading(),
                     )),
-                    Line::from(
-                        "Ctrl+N=new(save)  Ctrl+G=getnew  Ctrl+C=copy  ↑/↓=select saved  ←/→ Home End Backspace Delete=edit  Ctrl+X=close",
-                    ),
-                ]
-            } else {
-                vec![
+                    Line::from(keymap.help(Scope::AddressBook)),
+                ],
+                Screen::Explorer => vec![
+                    Line::from(Span::styled(
+                        "Explorer keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(keymap.help(Scope::Explorer)),
+                ],
+                Screen::Psbt => vec![
+                    Line::from(Span::styled(
+                        "PSBT keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(keymap.help(Scope::Psbt)),
+                ],
+                Screen::Message => vec![
+                    Line::from(Span::styled(
+                        "Message keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(keymap.help(Scope::Message)),
+                ],
+                Screen::Passphrase => vec![
+                    Line::from(Span::styled(
+                        "Passphrase keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(keymap.help(Scope::Passphrase)),
+                ],
+                Screen::Wallets => vec![
+                    Line::from(Span::styled(
+                        "Wallet keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(keymap.help(Scope::Wallets)),
+                ],
+                Screen::History => vec![
+                    Line::from(Span::styled(
+                        "History keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(format!(
+                        "{}  {}",
+                        keymap.help(Scope::History),
+                        BalanceHistory::SHARED_ACTIONS.iter().filter_map(|&a| keymap.hint(a)).collect::<Vec<_>>().join("  ")
+                    )),
+                ],
+                Screen::Palette => vec![
+                    Line::from(Span::styled(
+                        "Palette keys:",
+                        theme.heading(),
+                    )),
+                    Line::from(format!(
+                        "type to search actions, commands, RPC help and addresses  {}",
+                        keymap.help(Scope::Palette)
+                    )),
+                ],
+                Screen::Main => vec![
                     Line::from(Span::styled(
                         "Main keys:",
-                        Style::default().fg(orange).add_modifier(Modifier::BOLD),
+                        theme.heading(),
                     )),
-                   Line::from("↑/↓=select command  Enter=run  r=refresh  j/k=scroll output  h=hide/show amounts w=QR overlay  q=quit"),
-                ]
+                    Line::from(keymap.help(Scope::Main)),
+                ],
             };
 
             let help = Paragraph::new(help_lines)
@@ -196,134 +456,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     Block::default()
                         .title("Help")
                         .borders(Borders::ALL)
-                        .border_style(Style::default().fg(orange)),
+                        .border_style(theme.fg(theme.help)),
                 );
             f.render_widget(help, root[1]);
 
             // ===== Overlay on top (if active) =====
-            if show_qr_overlay {
-                let orange = Color::Rgb(245, 200, 66);
-                let area = centered_rect(80, 75, size);
-                f.render_widget(Clear, area);
-
-                // Outer box
-                let outer = Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(orange))
-                    .title(" Address Book & QR (edit left • list right) ");
-                f.render_widget(outer, area);
-
-                // Split overlay horizontally: left (editor + QR), right (list)
-                let cols = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .margin(1)
-                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
-                    .split(area);
-
-                // Left column: input, QR
-                let left = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Length(3), Constraint::Min(8)])
-                    .split(cols[0]);
-
-                // Validation
-                let validity = check_address(&address);
-                let (input_title, input_title_style, qr_title, qr_title_style, qr_dim): (String, Style, String, Style, bool) =
-                    match validity {
-                        AddrValidity::Empty => (
-                            " BTC Address ".to_string(),
-                            Style::default().fg(Color::Yellow),
-                            " Bitcoin QR Code — (enter an address) ".to_string(),
-                            Style::default().fg(Color::Yellow),
-                            true,
-                        ),
-                        AddrValidity::Invalid => (
-                            " BTC Address — INVALID ".to_string(),
-                            Style::default().fg(Color::Red),
-                            " Bitcoin QR Code — INVALID ".to_string(),
-                            Style::default().fg(Color::Red),
-                            true,
-                        ),
-                        AddrValidity::ValidAny(net) => {
-                            let label = match net {
-                                Network::Bitcoin => "VALID (mainnet)",
-                                Network::Testnet => "VALID (testnet)",
-                                Network::Testnet4 => "VALID (testnet4)",
-                                Network::Signet => "VALID (signet)",
-                                Network::Regtest => "VALID (regtest)",
-                            };
-                            (
-                                " BTC Address — VALID ".to_string(),
-                                Style::default().fg(Color::Green),
-                                format!(" Bitcoin QR Code — {label} "),
-                                Style::default().fg(Color::Green),
-                                false,
-                            )
-                        }
-                    };
-
-                // Input box
-                let input_block = Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(orange))
-                    .title(Span::styled(input_title.clone(), input_title_style));
-                let input = Paragraph::new(address.clone()).block(input_block);
-                f.render_widget(input, left[0]);
-
-                // Cursor inside input
-                let cursor_x = (left[0].x + 1).saturating_add(addr_cursor as u16);
-                let cursor_y = left[0].y + 1;
-                f.set_cursor(
-                    cursor_x.min(left[0].x + left[0].width.saturating_sub(2)),
-                    cursor_y,
-                );
-
-                // QR box
-                let qr_block = Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(orange))
-                    .title(Span::styled(qr_title.clone(), qr_title_style));
-                let qr_text = if qr_dim { String::new() } else { generate_qr_unicode(&address) };
-                let mut qr_par = Paragraph::new(qr_text).block(qr_block);
-                if qr_dim {
-                    qr_par = qr_par.style(Style::default().fg(Color::DarkGray));
+            match screen {
+                Screen::AddressBook => overlay.draw(f, size, &addr_book, &keymap, privacy, theme),
+                Screen::Explorer => explorer.draw(f, size, &keymap, theme),
+                Screen::Psbt => psbt_screen.draw(f, size, &keymap, privacy, theme),
+                Screen::Message => message_tool.draw(f, size, &addr_book, &keymap, privacy, theme),
+                Screen::Wallets => wallets.draw(f, size, &keymap, theme),
+                Screen::History => history.draw(f, size, &keymap, privacy, settings.unit, theme),
+                Screen::Palette => palette.draw(f, size, privacy, theme),
+                Screen::Passphrase => {
+                    let line = lock_state.map(|l| l.describe(now)).unwrap_or_else(|| "no wallet".to_string());
+                    passphrase.draw(f, size, &line, &keymap, theme)
                 }
-                f.render_widget(qr_par, left[1]);
-
-                // Right column: address list
-                let list_items: Vec<ListItem> = addr_book
-                    .iter()
-                    .enumerate()
-                    .map(|(i, e)| {
-                        let date_str = e.created_at.format("%Y-%m-%d %H:%M").to_string();
-                        let shown = if e.address.len() > 22 {
-                            format!(
-                                "{}  {}…{}",
-                                date_str,
-                                &e.address[..12],
-                                &e.address[e.address.len() - 8..]
-                            )
-                        } else {
-                            format!("{}  {}", date_str, e.address)
-                        };
-                        let mut item = ListItem::new(shown);
-                        if i == addr_selected {
-                            item = item.style(
-                                Style::default()
-                                    .fg(Color::Yellow)
-                                    .add_modifier(Modifier::BOLD),
-                            );
-                        }
-                        item
-                    })
-                    .collect();
-
-                let list_block = Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(orange))
-                    .title(" Addresses (↑/↓ select) ");
-                let list = List::new(list_items).block(list_block);
-                f.render_widget(list, cols[1]);
+                Screen::Main => {}
             }
             // === Version label (top-right, single line, no box) ===
             {
@@ -341,9 +491,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 let version_text = Paragraph::new(Line::from(Span::styled(
                     VERSION_LABEL,
-                    Style::default()
-                        .fg(Color::Rgb(180, 180, 180)) // tweak if it blends with your bg
-                        .add_modifier(Modifier::BOLD),
+                    theme.fg(theme.version).add_modifier(Modifier::BOLD),
                 )))
                 .alignment(Alignment::Right);
 
@@ -352,182 +500,478 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         })?;
 
-        // ===== Input handling =====
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if last_input.elapsed() >= Duration::from_millis(120) {
-                    if show_qr_overlay {
-                        // Keys active while overlay is open
-                        match (key.modifiers, key.code) {
-                            // ---- Ctrl combos ----
-                            (m, KeyCode::Char('n')) if m.contains(KeyModifiers::CONTROL) => {
-                                match run_bitcoin_cli("getnewaddress") {
-                                    Ok(s) => {
-                                        let new_addr = s.trim().to_string();
-                                        if matches!(
-                                            check_address(&new_addr),
-                                            AddrValidity::ValidAny(_)
-                                        ) {
-                                            let entry = AddressEntry {
-                                                created_at: Utc::now(),
-                                                address: new_addr.clone(),
-                                            };
-                                            addr_book.push(entry);
-                                            let _ =
-                                                save_address_book(ADDRESS_BOOK_PATH, &addr_book);
-                                            addr_selected = addr_book.len() - 1;
-                                            address = new_addr;
-                                            addr_cursor = address.len();
-                                        }
-                                    }
-                                    Err(_) => {}
-                                }
-                            }
-                            (m, KeyCode::Char('g')) if m.contains(KeyModifiers::CONTROL) => {
-                                if let Ok(s) = run_bitcoin_cli("getnewaddress") {
-                                    address = s.trim().to_string();
-                                    addr_cursor = address.len();
-                                }
-                            }
-                            (m, KeyCode::Char('c')) if m.contains(KeyModifiers::CONTROL) => {
-                                let _ = copy_to_clipboard(&address);
-                            }
-                            (m, KeyCode::Char('x')) if m.contains(KeyModifiers::CONTROL) => {
-                                show_qr_overlay = false; // close overlay
-                            }
+        if let Some(feed) = &mut fiat {
+            feed.poll();
+            // Screens that show balances keep their own copy of the rate
+            if overlay.fiat != feed.rate {
+                overlay.fiat = feed.rate.clone();
+                history.fiat = feed.rate.clone();
+                psbt_screen.fiat = feed.rate.clone();
+            }
+        }
 
-                            // ---- Navigation in list ----
-                            (_, KeyCode::Up) => {
-                                if !addr_book.is_empty() && addr_selected > 0 {
-                                    addr_selected -= 1;
-                                    address = addr_book[addr_selected].address.clone();
-                                    addr_cursor = address.len();
-                                }
-                            }
-                            (_, KeyCode::Down) => {
-                                if !addr_book.is_empty() && addr_selected + 1 < addr_book.len() {
-                                    addr_selected += 1;
-                                    address = addr_book[addr_selected].address.clone();
-                                    addr_cursor = address.len();
-                                }
-                            }
+        explorer.poll();
+        wallets.poll();
+        if wallets.changed {
+            wallets.changed = false;
+            lock_state = fetch_lock_state();
+            if let Ok(w_info) = fetch_wallet_info() {
+                wallet_info = Some(w_info);
+            }
+        }
 
-                            // ---- Editing the input ----
-                            (_, KeyCode::Left) => {
-                                if addr_cursor > 0 {
-                                    addr_cursor -= 1;
-                                }
-                            }
-                            (_, KeyCode::Right) => {
-                                if addr_cursor < address.len() {
-                                    addr_cursor += 1;
-                                }
-                            }
-                            (_, KeyCode::Home) => {
-                                addr_cursor = 0;
-                            }
-                            (_, KeyCode::End) => {
-                                addr_cursor = address.len();
-                            }
-                            (_, KeyCode::Backspace) => {
-                                if addr_cursor > 0 && !address.is_empty() {
-                                    address.remove(addr_cursor - 1);
-                                    addr_cursor -= 1;
-                                }
-                            }
-                            (_, KeyCode::Delete) => {
-                                if addr_cursor < address.len() && !address.is_empty() {
-                                    address.remove(addr_cursor);
-                                }
-                            }
-                            (_, KeyCode::Char(c)) => {
-                                if !c.is_control() && c != ' ' {
-                                    address.insert(addr_cursor.min(address.len()), c);
-                                    addr_cursor = (addr_cursor + 1).min(address.len());
-                                }
+        let importing = overlay.watch_importing();
+        overlay.poll(&mut addr_book);
+
+        // Follow a running import/rescan, and pick up its final balances
+        let scanning = matches!(&watch_status, Some(Ok(w)) if w.scanning.is_some());
+        if (importing && !overlay.watch_importing())
+            || ((importing || scanning) && last_watch_refresh.elapsed() >= refresh_interval)
+        {
+            watch_status = Some(fetch_watch_status());
+            last_watch_refresh = Instant::now();
+        }
+
+        // ===== Input handling =====
+        let event = if event::poll(Duration::from_millis(100))? {
+            Some(event::read()?)
+        } else {
+            None
+        };
+        // Pasted text arrives in one piece, so it bypasses the key debounce
+        if let Some(Event::Paste(text)) = &event {
+            match screen {
+                Screen::AddressBook => overlay.handle_paste(text),
+                Screen::Explorer => explorer.handle_paste(text),
+                Screen::Psbt => psbt_screen.handle_paste(text),
+                Screen::Message => message_tool.handle_paste(text),
+                Screen::Passphrase => passphrase.handle_paste(text),
+                Screen::Wallets => wallets.handle_paste(text),
+                Screen::Palette => palette.handle_paste(text),
+                Screen::History => {}
+                Screen::Main => {}
+            }
+        }
+        // Clicks pick commands and focus panes, the wheel scrolls. A click
+        // outside a dialog closes it.
+        let mut clicked: Option<usize> = None;
+        if let Some(Event::Mouse(mouse)) = event {
+            let click = mouse.kind == MouseEventKind::Down(MouseButton::Left);
+            let wheel = matches!(mouse.kind, MouseEventKind::ScrollUp | MouseEventKind::ScrollDown);
+            match dialog_area(screen, frame_size) {
+                Some(area) if click && !hit(area, mouse) => {
+                    if screen == Screen::Passphrase {
+                        passphrase.clear();
+                    }
+                    screen = Screen::Main;
+                }
+                Some(_) => {
+                    // The wheel moves list selections like the arrow keys
+                    let arrow = KeyEvent::from(if mouse.kind == MouseEventKind::ScrollDown {
+                        KeyCode::Down
+                    } else {
+                        KeyCode::Up
+                    });
+                    match screen {
+                        Screen::AddressBook => overlay.handle_mouse(mouse, frame_size, &addr_book),
+                        Screen::Palette if wheel => {
+                            palette.handle_key(arrow, &keymap);
+                        }
+                        Screen::Wallets if wheel && wallets.tab == WalletTab::List => {
+                            wallets.handle_key(arrow, &keymap);
+                        }
+                        Screen::Explorer if wheel && explorer.focus == ExplorerFocus::List => {
+                            explorer.handle_key(arrow, &keymap);
+                        }
+                        _ => {}
+                    }
+                }
+                None if hit(output_area, mouse) => match mouse.kind {
+                    MouseEventKind::ScrollDown => {
+                        scroll_offset = (scroll_offset + SCROLL_LINES).min(output_lines.len().saturating_sub(1));
+                    }
+                    MouseEventKind::ScrollUp => scroll_offset = scroll_offset.saturating_sub(SCROLL_LINES),
+                    _ if click => focus = Pane::Output,
+                    _ => {}
+                },
+                None if hit(commands_area, mouse) => {
+                    let rows = commands_area.inner(&Margin { vertical: 1, horizontal: 1 });
+                    match mouse.kind {
+                        // The wheel only moves the selection; Enter or a click runs it
+                        MouseEventKind::ScrollDown => selected = (selected + 1).min(commands.len() - 1),
+                        MouseEventKind::ScrollUp => selected = selected.saturating_sub(1),
+                        _ if click => {
+                            focus = Pane::Commands;
+                            let row = mouse.row.saturating_sub(rows.y) as usize;
+                            if hit(rows, mouse) && row < commands.len() {
+                                clicked = Some(row);
                             }
-                            _ => {}
                         }
-                        last_input = Instant::now();
-                        continue; // don't process main keys while modal is open
+                        _ => {}
                     }
-
-                    // Main view keys (overlay closed)
-                    match key.code {
-                        KeyCode::Char('h') => {
-                            hide_amounts = !hide_amounts;
+                }
+                None => {}
+            }
+        }
+        if let Some(i) = clicked {
+            selected = i;
+            output = command_output(run_bitcoin_cli(&commands[selected]));
+            output_command = commands[selected].clone();
+            output_lines = output.lines().map(|l| l.to_string()).collect();
+            scroll_offset = 0;
+            _last_refresh = Instant::now();
+        }
+        if let Some(Event::Key(key)) = event
+            && last_input.elapsed() >= Duration::from_millis(120)
+        {
+            let mut chosen: Option<PaletteTarget> = None;
+            match screen {
+                Screen::AddressBook => {
+                    // Keys active while overlay is open
+                    if !overlay.handle_key(key, &mut addr_book, &keymap) {
+                        screen = Screen::Main;
+                    }
+                    last_input = Instant::now();
+                    continue; // don't process main keys while modal is open
+                }
+                Screen::Explorer => {
+                    match explorer.handle_key(key, &keymap) {
+                        ExplorerAction::None => {}
+                        ExplorerAction::Close => screen = Screen::Main,
+                        ExplorerAction::ShowQr(addr) => {
+                            overlay.open_with(&addr_book, &addr);
+                            screen = Screen::AddressBook;
                         }
-                        KeyCode::Char('q') => break,
-                        KeyCode::Char('w') => {
-                            show_qr_overlay = true;
-                            if !addr_book.is_empty() {
-                                address = addr_book[addr_selected].address.clone();
-                            }
-                            addr_cursor = address.len();
+                        ExplorerAction::Save(address, descriptor, note) => {
+                            let entry = AddressEntry {
+                                created_at: chrono::Utc::now(),
+                                address_type: address::AddressType::of_address(&address),
+                                network: match address::check_address(&address) {
+                                    address::AddrValidity::Valid(info) => Some(info.pick(overlay.node_chain)),
+                                    _ => None,
+                                },
+                                address,
+                                label: String::new(),
+                                note,
+                                wallet: None,
+                                used: false,
+                                watch_only: false,
+                                descriptor: Some(descriptor),
+                            };
+                            explorer.status = match overlay.add_entry(&mut addr_book, entry) {
+                                Ok(()) => "Added to address book".to_string(),
+                                Err(e) => e,
+                            };
                         }
-                        KeyCode::Char('r') => {
-                            output = run_bitcoin_cli(&commands[selected])?;
-                            output_lines = output.lines().map(|l| l.to_string()).collect();
-                            if let Ok(info) = fetch_node_info() {
-                                node_info = info;
-                            }
-                            if let Ok(w_info) = fetch_wallet_info() {
-                                wallet_info = w_info;
-                            }
-                            scroll_offset = 0;
+                    }
+                    last_input = Instant::now();
+                    continue;
+                }
+                Screen::Psbt => {
+                    if !psbt_screen.handle_key(key, &keymap) {
+                        screen = Screen::Main;
+                    }
+                    last_input = Instant::now();
+                    continue;
+                }
+                Screen::Message => {
+                    if !message_tool.handle_key(key, &addr_book, &keymap) {
+                        screen = Screen::Main;
+                    }
+                    last_input = Instant::now();
+                    continue;
+                }
+                Screen::Passphrase => {
+                    if !passphrase.handle_key(key, &keymap) {
+                        screen = Screen::Main;
+                    }
+                    if passphrase.changed {
+                        passphrase.changed = false;
+                        lock_state = fetch_lock_state();
+                        if let Ok(w_info) = fetch_wallet_info() {
+                            wallet_info = Some(w_info);
                         }
-                        KeyCode::Down => {
-                            if selected < commands.len() - 1 {
-                                selected += 1;
-                                output = run_bitcoin_cli(&commands[selected])?;
-                                output_lines = output.lines().map(|l| l.to_string()).collect();
-                                scroll_offset = 0;
-                                _last_refresh = Instant::now();
+                    }
+                    last_input = Instant::now();
+                    continue;
+                }
+                Screen::Wallets => {
+                    if !wallets.handle_key(key, &keymap) {
+                        screen = Screen::Main;
+                    }
+                    last_input = Instant::now();
+                    continue;
+                }
+                Screen::History => {
+                    // Privacy, unit and theme keys of the main view work here too
+                    let shared = match keymap.action(Scope::History, key) {
+                        Some(_) => None,
+                        None => keymap.action(Scope::Main, key).filter(|a| BalanceHistory::SHARED_ACTIONS.contains(a)),
+                    };
+                    match shared {
+                        Some(action) => chosen = Some(PaletteTarget::Action(action)),
+                        None => {
+                            if !history.handle_key(key, &keymap) {
+                                screen = Screen::Main;
                             }
+                            last_input = Instant::now();
+                            continue;
                         }
-                        KeyCode::Up => {
-                            if selected > 0 {
-                                selected -= 1;
-                                output = run_bitcoin_cli(&commands[selected])?;
-                                output_lines = output.lines().map(|l| l.to_string()).collect();
-                                scroll_offset = 0;
-                                _last_refresh = Instant::now();
-                            }
+                    }
+                }
+                Screen::Palette => match palette.handle_key(key, &keymap) {
+                    PaletteAction::None => {
+                        last_input = Instant::now();
+                        continue;
+                    }
+                    PaletteAction::Close => {
+                        screen = Screen::Main;
+                        last_input = Instant::now();
+                        continue;
+                    }
+                    PaletteAction::Run(target) => {
+                        screen = Screen::Main;
+                        chosen = Some(target);
+                    }
+                },
+                Screen::Main => {}
+            }
+
+            // Main view keys (overlay closed), or the palette's choice
+            let target = chosen.or_else(|| keymap.action(Scope::Main, key).map(PaletteTarget::Action));
+            match target {
+                Some(PaletteTarget::Command(i)) => {
+                    selected = i;
+                    output = command_output(run_bitcoin_cli(&commands[selected]));
+                    output_command = commands[selected].clone();
+                    output_lines = output.lines().map(|l| l.to_string()).collect();
+                    scroll_offset = 0;
+                    _last_refresh = Instant::now();
+                }
+                Some(PaletteTarget::Rpc(method)) => {
+                    output = command_output(run_bitcoin_cli_args("help", &[&method]));
+                    output_command = format!("help {method}");
+                    output_lines = output.lines().map(|l| l.to_string()).collect();
+                    scroll_offset = 0;
+                }
+                Some(PaletteTarget::Address(address)) => {
+                    overlay.open_with(&addr_book, &address);
+                    overlay.refresh_usage(&mut addr_book);
+                    screen = Screen::AddressBook;
+                }
+                Some(PaletteTarget::Action(action)) => match action {
+                    Action::TogglePrivacy => {
+                        privacy.on = !privacy.on;
+                    }
+                    Action::CycleTheme => {
+                        settings.theme = themes.next().to_string();
+                        if let Err(e) = save_settings(&paths.settings(), &settings) {
+                            output_lines.insert(0, format!("Settings not saved: {e}"));
                         }
-                        KeyCode::PageDown | KeyCode::Char('j') => {
-                            if scroll_offset + 1 < output_lines.len() {
-                                scroll_offset += 1;
-                            }
+                    }
+                    Action::CycleUnit => {
+                        settings.unit = settings.unit.next();
+                        overlay.unit = settings.unit;
+                        if let Err(e) = save_settings(&paths.settings(), &settings) {
+                            output_lines.insert(0, format!("Settings not saved: {e}"));
                         }
-                        KeyCode::PageUp | KeyCode::Char('k') => {
-                            if scroll_offset > 0 {
-                                scroll_offset -= 1;
-                            }
+                    }
+                    Action::Quit => break,
+                    Action::Explorer => screen = Screen::Explorer,
+                    Action::Psbt => {
+                        psbt_screen.unit = settings.unit;
+                        screen = Screen::Psbt;
+                    }
+                    Action::Passphrase => {
+                        passphrase.open(match lock_state {
+                            Some(LockState::Unencrypted) => PassMode::Encrypt,
+                            _ => PassMode::Unlock,
+                        });
+                        screen = Screen::Passphrase;
+                    }
+                    Action::History => {
+                        history.load();
+                        screen = Screen::History;
+                    }
+                    Action::Wallets => {
+                        wallets.open();
+                        screen = Screen::Wallets;
+                    }
+                    Action::Message => {
+                        message_tool.open(&addr_book);
+                        screen = Screen::Message;
+                    }
+                    Action::AddressBook => {
+                        screen = Screen::AddressBook;
+                        overlay.open(&addr_book);
+                        overlay.refresh_usage(&mut addr_book);
+                    }
+                    Action::Palette => {
+                        palette.open(&commands, &addr_book, &keymap);
+                        screen = Screen::Palette;
+                    }
+                    Action::Refresh => {
+                        output = command_output(run_bitcoin_cli(&commands[selected]));
+                        output_command = commands[selected].clone();
+                        output_lines = output.lines().map(|l| l.to_string()).collect();
+                        if let Ok(info) = fetch_node_info() {
+                            node_info = info;
                         }
-                        KeyCode::Enter => {
-                            output = run_bitcoin_cli(&commands[selected])?;
-                            output_lines = output.lines().map(|l| l.to_string()).collect();
-                            scroll_offset = 0;
-                            _last_refresh = Instant::now();
+                        if let Ok(w_info) = fetch_wallet_info() {
+                            wallet_info = Some(w_info);
                         }
-                        _ => {}
+                        lock_state = fetch_lock_state();
+                        if addr_book.iter().any(|e| e.watch_only) {
+                            watch_status = Some(fetch_watch_status());
+                            last_watch_refresh = Instant::now();
+                        }
+                        if let Some(feed) = &mut fiat {
+                            feed.refresh();
+                        }
+                        scroll_offset = 0;
                     }
-                    last_input = Instant::now();
-                }
+                    Action::ScrollDown if scroll_offset + 1 < output_lines.len() => {
+                        scroll_offset += 1;
+                    }
+                    Action::ScrollUp => {
+                        scroll_offset = scroll_offset.saturating_sub(1);
+                    }
+                    Action::RunCommand => {
+                        output = command_output(run_bitcoin_cli(&commands[selected]));
+                        output_command = commands[selected].clone();
+                        output_lines = output.lines().map(|l| l.to_string()).collect();
+                        scroll_offset = 0;
+                        _last_refresh = Instant::now();
+                    }
+                    // Address book actions are handled by the overlay
+                    _ => {}
+                },
+                None => match key.code {
+                    KeyCode::Down if focus == Pane::Output => {
+                        scroll_offset = (scroll_offset + 1).min(output_lines.len().saturating_sub(1));
+                    }
+                    KeyCode::Up if focus == Pane::Output => scroll_offset = scroll_offset.saturating_sub(1),
+                    KeyCode::Down if selected < commands.len() - 1 => {
+                        selected += 1;
+                        output = command_output(run_bitcoin_cli(&commands[selected]));
+                        output_command = commands[selected].clone();
+                        output_lines = output.lines().map(|l| l.to_string()).collect();
+                        scroll_offset = 0;
+                        _last_refresh = Instant::now();
+                    }
+                    KeyCode::Up if selected > 0 => {
+                        selected -= 1;
+                        output = command_output(run_bitcoin_cli(&commands[selected]));
+                        output_command = commands[selected].clone();
+                        output_lines = output.lines().map(|l| l.to_string()).collect();
+                        scroll_offset = 0;
+                        _last_refresh = Instant::now();
+                    }
+                    _ => {}
+                },
             }
+            last_input = Instant::now();
         }
     }
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-
+    drop(book_lock);
     Ok(())
 }
 
+/// Puts the terminal back on every way out of the TUI, including errors
+/// and panics.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste, LeaveAlternateScreen, Show);
+    }
+}
+
+/// Text for the Output pane. A bitcoin-cli that can't be started is
+/// reported there rather than ending the program.
+fn command_output(result: Result<String, Box<dyn std::error::Error>>) -> String {
+    result.unwrap_or_else(|e| format!("Error: {e}"))
+}
+
+// ===== One-shot command line tasks =====
+
+fn run_task(task: Task, paths: &Paths) -> Result<String, String> {
+    let book_path = paths.address_book();
+    let _lock = lock_address_book(&book_path)?;
+    let (mut book, cipher) = match load_address_book(&book_path)? {
+        BookFile::Plain(book) => (book, None),
+        BookFile::Locked(data) => {
+            let pass = prompt_passphrase("Address book passphrase: ")?;
+            let (cipher, book) = unlock_address_book(&data, &pass)?;
+            (book, Some(cipher))
+        }
+    };
+    let cipher = cipher.as_ref();
+    match task {
+        Task::EncryptAddressBook => {
+            if cipher.is_some() {
+                return Err(format!("{} is already encrypted", book_path.display()));
+            }
+            let pass = prompt_passphrase("New passphrase: ")?;
+            if pass.is_empty() {
+                return Err("empty passphrase".to_string());
+            }
+            if prompt_passphrase("Repeat passphrase: ")? != pass {
+                return Err("passphrases do not match".to_string());
+            }
+            let new_cipher = BookCipher::create(&pass)?;
+            save_address_book(&book_path, &mut book, Some(&new_cipher))?;
+            let removed = remove_plain_backups(&book_path)?;
+            let mut msg = format!(
+                "Encrypted {} ({} entries), removed {removed} plaintext backup(s).",
+                book_path.display(),
+                book.len()
+            );
+            if let Some(legacy) = paths.legacy_address_book() {
+                msg.push_str(&format!(
+                    "\nA plaintext copy from an older version is still at {}; delete it.",
+                    legacy.display()
+                ));
+            }
+            Ok(msg)
+        }
+        Task::ExportLabels(path) => {
+            let sidecar = labels::load_sidecar(&paths.labels())?;
+            let n = labels::export_bip329(&book, &sidecar, &path)?;
+            Ok(format!("Exported {n} label(s) to {}", path.display()))
+        }
+        Task::ImportLabels(path) => {
+            let mut sidecar = labels::load_sidecar(&paths.labels())?;
+            let s = labels::import_bip329(&path, &mut book, &mut sidecar, fetch_chain())?;
+            save_address_book(&book_path, &mut book, cipher)?;
+            labels::write_label_records(&paths.labels(), &sidecar)?;
+            Ok(format!(
+                "Imported {} address label(s) ({} synced to node), {} tx/output/xpub label(s), skipped {}",
+                s.addresses, s.synced, s.other, s.skipped
+            ))
+        }
+        Task::ExportCsv(path) => {
+            let n = labels::export_csv(&book, &path)?;
+            Ok(format!("Exported {n} address(es) to {}", path.display()))
+        }
+        Task::ImportCsv(path) => {
+            let s = labels::import_csv(&path, &mut book, fetch_chain())?;
+            let merged = save_address_book(&book_path, &mut book, cipher)?;
+            Ok(format!(
+                "Imported {} row(s) from {}, merged {merged} duplicate(s), skipped {} without a valid address",
+                s.imported,
+                path.display(),
+                s.skipped
+            ))
+        }
+    }
+}
+
 // ===== Helpers for overlay & QR =====
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -547,60 +991,74 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn generate_qr_unicode(data: &str) -> String {
-    // Render a tiny valid QR even for empty input to avoid panic
-    let safe = if data.is_empty() { " " } else { data };
-    let code = QrCode::new(safe).unwrap();
-    code.render::<unicode::Dense1x2>().quiet_zone(false).build()
-}
-
-// ===== Address validation =====
-
-#[derive(Clone, Copy, Debug)]
-enum AddrValidity {
-    Empty,
-    Invalid,
-    ValidAny(Network),
+/// Where the open dialog is drawn, None on the main view.
+fn dialog_area(screen: Screen, size: Rect) -> Option<Rect> {
+    match screen {
+        Screen::Main => None,
+        Screen::AddressBook => Some(AddressOverlay::area(size)),
+        Screen::Explorer => Some(DescriptorExplorer::area(size)),
+        Screen::Psbt => Some(PsbtScreen::area(size)),
+        Screen::Message => Some(MessageTool::area(size)),
+        Screen::Passphrase => Some(PassphraseDialog::area(size)),
+        Screen::Wallets => Some(WalletManager::area(size)),
+        Screen::History => Some(BalanceHistory::area(size)),
+        Screen::Palette => Some(CommandPalette::area(size)),
+    }
 }
 
-fn check_address(addr: &str) -> AddrValidity {
-    let s = addr.trim();
-    if s.is_empty() {
-        return AddrValidity::Empty;
-    }
-    match Address::from_str(s) {
-        Ok(a) => {
-            for net in [
-                Network::Bitcoin,
-                Network::Testnet,
-                Network::Testnet4,
-                Network::Signet,
-                Network::Regtest,
-            ] {
-                if a.clone().require_network(net).is_ok() {
-                    return AddrValidity::ValidAny(net);
-                }
-            }
-            AddrValidity::Invalid
-        }
-        Err(_) => AddrValidity::Invalid,
-    }
+/// Whether a mouse event happened inside `area`.
+pub(crate) fn hit(area: Rect, mouse: MouseEvent) -> bool {
+    (area.x..area.x + area.width).contains(&mouse.column) && (area.y..area.y + area.height).contains(&mouse.row)
 }
 
 // ===== Clipboard =====
-fn copy_to_clipboard(text: &str) -> Result<(), String> {
+pub(crate) fn copy_to_clipboard(text: &str) -> Result<(), String> {
     let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
     clipboard
         .set_text(text.to_owned())
         .map_err(|e| e.to_string())
 }
 
-// ===== Amount masking (no regex, keeps punctuation/currency) =====
-fn mask_digits_if(s: &str, hide: bool) -> String {
-    if !hide {
-        return s.to_string();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{click, mouse};
+
+    #[test]
+    fn hits_only_inside() {
+        let area = Rect::new(2, 3, 4, 2);
+        assert!(hit(area, click(2, 3)));
+        assert!(hit(area, click(5, 4)));
+        // Right and bottom edges are exclusive
+        assert!(!hit(area, click(6, 4)));
+        assert!(!hit(area, click(5, 5)));
+        assert!(!hit(area, click(1, 3)));
+        assert!(hit(area, mouse(MouseEventKind::ScrollDown, 3, 3)));
+        assert!(!hit(Rect::new(2, 3, 0, 0), click(2, 3)));
+    }
+
+    #[test]
+    fn dialogs_leave_room_to_click_outside() {
+        let screens = [
+            Screen::AddressBook,
+            Screen::Explorer,
+            Screen::Psbt,
+            Screen::Message,
+            Screen::Passphrase,
+            Screen::Wallets,
+            Screen::History,
+            Screen::Palette,
+        ];
+        for size in [Rect::new(0, 0, 80, 24), Rect::new(0, 0, 203, 61)] {
+            assert_eq!(dialog_area(Screen::Main, size), None);
+            for screen in screens {
+                let area = dialog_area(screen, size).unwrap();
+                assert!(area.width > 0 && area.height > 0, "{screen:?}");
+                assert_eq!(area.intersection(size), area, "{screen:?}");
+                assert!(!hit(area, click(0, 0)), "{screen:?}");
+                let centre = click(size.width / 2, size.height / 2);
+                assert!(hit(area, centre), "{screen:?}");
+            }
+        }
     }
-    s.chars()
-        .map(|c| if c.is_ascii_digit() { 'X' } else { c })
-        .collect()
 }
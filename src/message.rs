@@ -0,0 +1,395 @@
+use core::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Modifier,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::sign_message::{MessageSignature, signed_msg_hash};
+use bitcoin::Address;
+
+use crate::cli::{rpc_error, run_bitcoin_cli_args, run_bitcoin_cli_wallet};
+use crate::file::AddressEntry;
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::privacy::Privacy;
+use crate::{centered_rect, copy_to_clipboard};
+use crate::theme::Theme;
+
+// ===== Message signing / verification =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageFocus {
+    Address,
+    Message,
+    Signature,
+}
+
+pub(crate) struct MessageTool {
+    pub address: TextInput,
+    pub message: TextInput,
+    pub signature: TextInput,
+    pub focus: MessageFocus,
+    /// Index of the address book entry shown in the address field.
+    pub entry: Option<usize>,
+    /// Outcome of the last verification: (valid, how it was checked).
+    pub verdict: Option<(bool, String)>,
+    pub status: String,
+}
+
+/// Verifies a signature locally. Like Bitcoin Core, only legacy (P2PKH)
+/// addresses can sign messages.
+pub(crate) fn verify_offline(address: &str, signature: &str, message: &str) -> Result<bool, String> {
+    let address = Address::from_str(address.trim())
+        .map_err(|e| format!("invalid address: {e}"))?
+        .assume_checked();
+    let signature = MessageSignature::from_base64(signature.trim()).map_err(|e| format!("invalid signature: {e}"))?;
+    let secp = Secp256k1::verification_only();
+    signature
+        .is_signed_by_address(&secp, &address, signed_msg_hash(message))
+        .map_err(|e| e.to_string())
+}
+
+impl MessageTool {
+    pub(crate) fn new() -> Self {
+        Self {
+            address: TextInput::new("", false),
+            message: TextInput::new("", true),
+            signature: TextInput::new("", false),
+            focus: MessageFocus::Address,
+            entry: None,
+            verdict: None,
+            status: String::new(),
+        }
+    }
+
+    /// Opens the tool, starting with the first saved address if the field is empty.
+    pub(crate) fn open(&mut self, book: &[AddressEntry]) {
+        if self.address.value.is_empty() {
+            self.step(book, 0);
+        }
+        self.status.clear();
+    }
+
+    /// Moves through the address book entries that are single addresses.
+    fn step(&mut self, book: &[AddressEntry], delta: isize) {
+        let candidates: Vec<usize> = (0..book.len()).filter(|&i| !book[i].is_descriptor()).collect();
+        if candidates.is_empty() {
+            return;
+        }
+        let pos = self
+            .entry
+            .and_then(|e| candidates.iter().position(|&i| i == e))
+            .map(|p| (p as isize + delta).rem_euclid(candidates.len() as isize) as usize)
+            .unwrap_or(0);
+        let i = candidates[pos];
+        self.entry = Some(i);
+        self.address.set(&book[i].address);
+        self.verdict = None;
+    }
+
+    fn selected_entry<'a>(&self, book: &'a [AddressEntry]) -> Option<&'a AddressEntry> {
+        self.entry
+            .and_then(|i| book.get(i))
+            .filter(|e| e.address == self.address.value.trim())
+    }
+
+    fn sign(&mut self, book: &[AddressEntry]) {
+        let address = self.address.value.trim().to_string();
+        let args = [address.as_str(), self.message.value.as_str()];
+        let wallet = self.selected_entry(book).and_then(|e| e.wallet.clone());
+        let result = match &wallet {
+            Some(w) => run_bitcoin_cli_wallet(w, "signmessage", &args),
+            None => run_bitcoin_cli_args("signmessage", &args),
+        };
+        self.verdict = None;
+        match result {
+            Ok(out) => match rpc_error(&out) {
+                Some(e) => self.status = format!("signmessage failed: {e}"),
+                None => {
+                    self.signature.set(out.trim());
+                    self.status = "Signed".to_string();
+                    self.focus = MessageFocus::Signature;
+                }
+            },
+            Err(e) => self.status = format!("signmessage failed: {e}"),
+        }
+    }
+
+    /// Asks the node first and falls back to the local check when the
+    /// node can't be reached.
+    fn verify(&mut self) {
+        let address = self.address.value.trim();
+        let signature = self.signature.value.trim();
+        let message = self.message.value.as_str();
+        let node = run_bitcoin_cli_args("verifymessage", &[address, signature, message])
+            .map_err(|e| e.to_string())
+            .and_then(|out| match rpc_error(&out) {
+                Some(e) => Err(e),
+                None => Ok(out.trim() == "true"),
+            });
+        self.status.clear();
+        self.verdict = match node {
+            Ok(valid) => Some((valid, "verified by the node".to_string())),
+            Err(node_err) => match verify_offline(address, signature, message) {
+                Ok(valid) => Some((valid, "verified offline".to_string())),
+                Err(e) => {
+                    self.status = format!("Cannot verify: {e} (node: {node_err})");
+                    None
+                }
+            },
+        };
+    }
+
+    /// Pasted text goes to the focused field.
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        match self.focus {
+            MessageFocus::Address => self.address.insert_str(text.trim()),
+            MessageFocus::Message => self.message.insert_str(text),
+            MessageFocus::Signature => self.signature.insert_str(text.trim()),
+        }
+        self.verdict = None;
+    }
+
+    /// Handles a key. Returns false when the tool should close.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, book: &[AddressEntry], keymap: &Keymap) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match (keymap.action(Scope::Message, key), key.code) {
+            (Some(Action::MessageClose), _) | (_, KeyCode::Esc) => return false,
+            (Some(Action::MessageSign), _) => self.sign(book),
+            (Some(Action::MessageVerify), _) => self.verify(),
+            (Some(Action::MessageCopy), _) if !self.signature.value.is_empty() => {
+                self.status = match copy_to_clipboard(self.signature.value.trim()) {
+                    Ok(()) => "Signature copied".to_string(),
+                    Err(e) => format!("Copy failed: {e}"),
+                };
+            }
+            (_, KeyCode::Tab) => {
+                self.focus = match self.focus {
+                    MessageFocus::Address => MessageFocus::Message,
+                    MessageFocus::Message => MessageFocus::Signature,
+                    MessageFocus::Signature => MessageFocus::Address,
+                };
+            }
+            (_, KeyCode::BackTab) => {
+                self.focus = match self.focus {
+                    MessageFocus::Address => MessageFocus::Signature,
+                    MessageFocus::Message => MessageFocus::Address,
+                    MessageFocus::Signature => MessageFocus::Message,
+                };
+            }
+            (_, KeyCode::Up) if self.focus == MessageFocus::Address => self.step(book, -1),
+            (_, KeyCode::Down) if self.focus == MessageFocus::Address => self.step(book, 1),
+            (_, KeyCode::Enter) => match self.focus {
+                MessageFocus::Signature => self.verify(),
+                _ => self.sign(book),
+            },
+            (_, code) if !ctrl => {
+                let changed = match self.focus {
+                    MessageFocus::Address => self.address.handle_key(code),
+                    MessageFocus::Message => self.message.handle_key(code),
+                    MessageFocus::Signature => self.signature.handle_key(code),
+                };
+                if changed {
+                    self.verdict = None;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(75, 60, size)
+    }
+
+    pub(crate) fn draw(
+        &self,
+        f: &mut Frame,
+        size: Rect,
+        book: &[AddressEntry],
+        keymap: &Keymap,
+        privacy: Privacy,
+        theme: &Theme,
+    ) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(" Sign / verify message ");
+        if !self.status.is_empty() {
+            outer = outer.title_bottom(Span::styled(format!(" {} ", self.status), theme.warn()));
+        }
+        f.render_widget(outer, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(5),
+                Constraint::Length(4),
+                Constraint::Min(2),
+            ])
+            .split(area);
+
+        let focused = |on: bool| {
+            if on {
+                theme.focused()
+            } else {
+                theme.muted()
+            }
+        };
+
+        // Extra keys after Enter, e.g. "Enter / Ctrl+S = sign"
+        let or = |action| match keymap.keys(action) {
+            keys if keys.is_empty() => String::new(),
+            keys => format!(" / {keys}"),
+        };
+        let address_title = match self.selected_entry(book) {
+            Some(e) if !e.label.is_empty() => format!(" Address — {} (↑/↓ = address book) ", e.label),
+            _ => " Address (↑/↓ = address book) ".to_string(),
+        };
+        let fields = [
+            (MessageFocus::Address, &self.address, address_title, rows[0]),
+            (MessageFocus::Message, &self.message, format!(" Message (Enter{} = sign) ", or(Action::MessageSign)), rows[1]),
+            (
+                MessageFocus::Signature,
+                &self.signature,
+                format!(
+                    " Signature, base64 (Enter{} = verify{}) ",
+                    or(Action::MessageVerify),
+                    keymap.hint(Action::MessageCopy).map(|h| format!(" • {h}")).unwrap_or_default()
+                ),
+                rows[2],
+            ),
+        ];
+        for (focus, field, title, rect) in &fields {
+            let shown = match focus {
+                MessageFocus::Address if !field.value.is_empty() => privacy.address(&field.value),
+                _ => field.value.clone(),
+            };
+            f.render_widget(
+                Paragraph::new(shown).wrap(Wrap { trim: false }).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(focused(self.focus == *focus))
+                        .title(title.as_str()),
+                ),
+                *rect,
+            );
+        }
+
+        let result = match &self.verdict {
+            Some((true, how)) => Line::from(Span::styled(
+                format!("✔ Valid signature for this address and message ({how})"),
+                theme.ok().add_modifier(Modifier::BOLD),
+            )),
+            Some((false, how)) => Line::from(Span::styled(
+                format!("✘ Signature does not match this address and message ({how})"),
+                theme.error().add_modifier(Modifier::BOLD),
+            )),
+            None => Line::from(Span::styled(
+                "Only legacy (1…/m…/n…) addresses can sign messages.",
+                theme.muted(),
+            )),
+        };
+        f.render_widget(Paragraph::new(result), rows[3]);
+
+        // Cursor in the focused field (single-line position; long values wrap)
+        let (field, rect) = match self.focus {
+            MessageFocus::Address => (&self.address, rows[0]),
+            MessageFocus::Message => (&self.message, rows[1]),
+            MessageFocus::Signature => (&self.signature, rows[2]),
+        };
+        let inner = rect.width.saturating_sub(2).max(1);
+        let cursor = field.cursor as u16;
+        let y = (rect.y + 1 + cursor / inner).min(rect.y + rect.height.saturating_sub(2));
+        f.set_cursor(rect.x + 1 + cursor % inner, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::key;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Message, SecretKey};
+    use bitcoin::{CompressedPublicKey, Network};
+
+    #[test]
+    fn core_vector() {
+        // From Bitcoin Core's rpc_signmessage.py
+        let address = "mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB";
+        let signature = "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=";
+        assert_eq!(verify_offline(address, signature, "This is just a test message"), Ok(true));
+        assert_eq!(verify_offline(address, signature, "This is just a test message!"), Ok(false));
+    }
+
+    #[test]
+    fn signs_and_verifies_locally() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[1; 32]).unwrap();
+        let public = CompressedPublicKey(secret.public_key(&secp));
+        let hash = signed_msg_hash("hello");
+        let sig = secp.sign_ecdsa_recoverable(&Message::from_digest(hash.to_byte_array()), &secret);
+        let signature = MessageSignature::new(sig, true).to_base64();
+
+        let legacy = Address::p2pkh(public, Network::Bitcoin).to_string();
+        assert_eq!(verify_offline(&legacy, &signature, "hello"), Ok(true));
+        assert_eq!(verify_offline(&legacy, &signature, "Hello"), Ok(false));
+        // Only P2PKH can be checked
+        let segwit = Address::p2wpkh(&public, Network::Bitcoin).to_string();
+        assert!(verify_offline(&segwit, &signature, "hello").is_err());
+        assert!(verify_offline("nope", &signature, "hello").unwrap_err().starts_with("invalid address"));
+        assert!(verify_offline(&legacy, "nope", "hello").unwrap_err().starts_with("invalid signature"));
+    }
+
+    #[test]
+    fn steps_through_single_addresses() {
+        let book: Vec<AddressEntry> = serde_json::from_value(serde_json::json!([
+            {"created_at": "2024-01-01T00:00:00Z", "address": "1First"},
+            {"created_at": "2024-01-01T00:00:00Z", "address": "wpkh(xpub/0/*)"},
+            {"created_at": "2024-01-01T00:00:00Z", "address": "1Second", "label": "two"},
+        ]))
+        .unwrap();
+        let keymap = Keymap::defaults();
+        let mut tool = MessageTool::new();
+        tool.open(&book);
+        assert_eq!((tool.address.value.as_str(), tool.entry), ("1First", Some(0)));
+        tool.handle_key(key(KeyCode::Down), &book, &keymap);
+        assert_eq!(tool.address.value, "1Second");
+        assert_eq!(tool.selected_entry(&book).unwrap().label, "two");
+        tool.handle_key(key(KeyCode::Down), &book, &keymap);
+        assert_eq!(tool.address.value, "1First");
+        tool.handle_key(key(KeyCode::Up), &book, &keymap);
+        assert_eq!(tool.address.value, "1Second");
+        // Once edited, the field no longer stands for the entry
+        tool.handle_key(key(KeyCode::Backspace), &book, &keymap);
+        assert!(tool.selected_entry(&book).is_none());
+    }
+
+    #[test]
+    fn keys_move_focus() {
+        let keymap = Keymap::defaults();
+        let mut tool = MessageTool::new();
+        tool.handle_key(key(KeyCode::Tab), &[], &keymap);
+        tool.handle_paste("two\nlines");
+        assert_eq!((tool.focus, tool.message.value.as_str()), (MessageFocus::Message, "twolines"));
+        tool.handle_key(key(KeyCode::Tab), &[], &keymap);
+        assert_eq!(tool.focus, MessageFocus::Signature);
+        tool.handle_key(key(KeyCode::BackTab), &[], &keymap);
+        tool.handle_key(key(KeyCode::BackTab), &[], &keymap);
+        assert_eq!(tool.focus, MessageFocus::Address);
+        assert!(!tool.handle_key(key(KeyCode::Esc), &[], &keymap));
+    }
+}
@@ -1,4 +1,10 @@
-use crate::cli::run_bitcoin_cli;
+use std::collections::HashMap;
+
+use bitcoin::{Amount, Denomination, Network, SignedAmount};
+
+use crate::cli::{rpc_error, run_bitcoin_cli, run_bitcoin_cli_args, run_bitcoin_cli_wallet};
+use crate::privacy::Privacy;
+use crate::units::Unit;
 
 pub(crate) fn fetch_node_info() -> Result<String, Box<dyn std::error::Error>> {
     // uptime is not a standard bitcoin-cli RPC call, so fallback if it fails
@@ -15,19 +21,43 @@ pub(crate) fn fetch_node_info() -> Result<String, Box<dyn std::error::Error>> {
     ))
 }
 
-pub(crate) fn fetch_wallet_info() -> Result<String, Box<dyn std::error::Error>> {
+/// Wallet Info panel contents; amounts are formatted when drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WalletInfo {
+    pub name: String,
+    pub tx_count: u64,
+    pub keypool_size: u64,
+    pub balances: Balances,
+}
+
+impl WalletInfo {
+    pub(crate) fn lines(&self, unit: Unit, privacy: Privacy) -> String {
+        let mut info = format!(
+            "Wallet: {} ({} txs, keypool {})\nTrusted: {}\nPending: {}\nImmature: {}",
+            self.name,
+            self.tx_count,
+            self.keypool_size,
+            privacy.amount(&unit.format(self.balances.trusted)),
+            privacy.amount(&unit.format(self.balances.pending)),
+            privacy.amount(&unit.format(self.balances.immature))
+        );
+        if let Some(w) = self.balances.watch_only {
+            info.push_str(&format!("\nWatch-only: {}", privacy.amount(&unit.format(w))));
+        }
+        info
+    }
+}
+
+pub(crate) fn fetch_wallet_info() -> Result<WalletInfo, Box<dyn std::error::Error>> {
     let output = run_bitcoin_cli("getwalletinfo")?;
     let json: serde_json::Value = serde_json::from_str(&output)?;
 
-    let wallet_name = json["walletname"].as_str().unwrap_or("N/A");
-    let balance = json["balance"].as_f64().unwrap_or(0.0);
-    let tx_count = json["txcount"].as_u64().unwrap_or(0);
-    let keypool_size = json["keypoolsize"].as_u64().unwrap_or(0);
-
-    Ok(format!(
-        "Wallet: {}\nBalance: {:.8} BTC\nTransactions: {}\nKeypool Size: {}",
-        wallet_name, balance, tx_count, keypool_size
-    ))
+    Ok(WalletInfo {
+        name: json["walletname"].as_str().unwrap_or("N/A").to_string(),
+        tx_count: json["txcount"].as_u64().unwrap_or(0),
+        keypool_size: json["keypoolsize"].as_u64().unwrap_or(0),
+        balances: fetch_balances()?,
+    })
 }
 
 fn format_uptime(seconds: u64) -> String {
@@ -45,3 +75,258 @@ fn format_uptime(seconds: u64) -> String {
         format!("{} minute(s)", minutes)
     }
 }
+
+/// Encryption state of the default wallet (`getwalletinfo.unlocked_until`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockState {
+    Unencrypted,
+    Locked,
+    /// Unix time at which the wallet locks again.
+    UnlockedUntil(i64),
+}
+
+impl LockState {
+    /// Panel line with the remaining unlock time, as of `now` (unix seconds).
+    pub(crate) fn describe(self, now: i64) -> String {
+        match self {
+            LockState::Unencrypted => "Encryption: off".to_string(),
+            LockState::UnlockedUntil(t) if t > now => {
+                let left = t - now;
+                format!("🔓 Unlocked ({}:{:02} left)", left / 60, left % 60)
+            }
+            _ => "🔒 Locked".to_string(),
+        }
+    }
+}
+
+pub(crate) fn fetch_lock_state() -> Option<LockState> {
+    let output = run_bitcoin_cli("getwalletinfo").ok()?;
+    let json: serde_json::Value = serde_json::from_str(&output).ok()?;
+    Some(match json["unlocked_until"].as_i64() {
+        None => LockState::Unencrypted,
+        Some(0) => LockState::Locked,
+        Some(t) => LockState::UnlockedUntil(t),
+    })
+}
+
+/// `getbalances` of the default wallet, in exact amounts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Balances {
+    pub trusted: Amount,
+    pub pending: Amount,
+    pub immature: Amount,
+    /// Total of watch-only coins; only legacy wallets report these.
+    pub watch_only: Option<Amount>,
+}
+
+/// Parses a BTC amount from RPC JSON. Numbers keep their text (serde_json
+/// `arbitrary_precision`), so the decimal is read exactly, never via a float.
+pub(crate) fn json_amount(v: &serde_json::Value) -> Result<Amount, String> {
+    match v {
+        serde_json::Value::Number(n) => {
+            Amount::from_str_in(&n.to_string(), Denomination::Bitcoin).map_err(|e| format!("amount {n}: {e}"))
+        }
+        other => Err(format!("expected a BTC amount, got {other}")),
+    }
+}
+
+/// Like [`json_amount`], for fields that can be negative (sends, fees).
+pub(crate) fn json_signed_amount(v: &serde_json::Value) -> Result<SignedAmount, String> {
+    match v {
+        serde_json::Value::Number(n) => {
+            SignedAmount::from_str_in(&n.to_string(), Denomination::Bitcoin).map_err(|e| format!("amount {n}: {e}"))
+        }
+        other => Err(format!("expected a BTC amount, got {other}")),
+    }
+}
+
+pub(crate) fn fetch_balances() -> Result<Balances, String> {
+    let out = run_bitcoin_cli("getbalances").map_err(|e| e.to_string())?;
+    if let Some(e) = rpc_error(&out) {
+        return Err(e);
+    }
+    let json: serde_json::Value = serde_json::from_str(&out).map_err(|e| e.to_string())?;
+    let mine = &json["mine"];
+    let watch = &json["watchonly"];
+    let watch_only = match watch.is_object() {
+        true => Some(json_amount(&watch["trusted"])? + json_amount(&watch["untrusted_pending"])? + json_amount(&watch["immature"])?),
+        false => None,
+    };
+    Ok(Balances {
+        trusted: json_amount(&mine["trusted"])?,
+        pending: json_amount(&mine["untrusted_pending"])?,
+        immature: json_amount(&mine["immature"])?,
+        watch_only,
+    })
+}
+
+/// Chain the node runs on (`getblockchaininfo.chain`).
+pub(crate) fn fetch_chain() -> Option<Network> {
+    let output = run_bitcoin_cli("getblockchaininfo").ok()?;
+    let json: serde_json::Value = serde_json::from_str(&output).ok()?;
+    Network::from_core_arg(json["chain"].as_str()?).ok()
+}
+
+/// Name of the wallet bitcoin-cli currently talks to, if any is loaded.
+pub(crate) fn fetch_wallet_name() -> Option<String> {
+    let output = run_bitcoin_cli("getwalletinfo").ok()?;
+    let json: serde_json::Value = serde_json::from_str(&output).ok()?;
+    json["walletname"].as_str().map(|s| s.to_string())
+}
+
+/// How an address has been used on chain, as far as the node can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AddrUsage {
+    /// Total received (wallet addresses) or currently unspent (scanned).
+    pub amount: Amount,
+    /// Transactions paying to the address (wallet) or unspent outputs (scan).
+    pub tx_count: usize,
+    /// True if the figures come from `scantxoutset` rather than the wallet.
+    pub scanned: bool,
+}
+
+impl AddrUsage {
+    /// More than one payment to the same address hurts privacy.
+    pub(crate) fn reused(&self) -> bool {
+        self.tx_count > 1
+    }
+}
+
+/// Received amount and tx count per wallet address, including addresses
+/// that never received anything (`listreceivedbyaddress 0 true`). Without
+/// a wallet name the node's default wallet is asked.
+pub(crate) fn fetch_address_usage(
+    wallet: Option<&str>,
+) -> Result<HashMap<String, AddrUsage>, Box<dyn std::error::Error>> {
+    let output = match wallet {
+        Some(w) => run_bitcoin_cli_wallet(w, "listreceivedbyaddress", &["0", "true"])?,
+        None => run_bitcoin_cli("listreceivedbyaddress 0 true")?,
+    };
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+    Ok(received_usage(&json)?)
+}
+
+/// Parses `listreceivedbyaddress` output.
+fn received_usage(json: &serde_json::Value) -> Result<HashMap<String, AddrUsage>, String> {
+    let mut usage = HashMap::new();
+    for item in json.as_array().into_iter().flatten() {
+        if let Some(addr) = item["address"].as_str() {
+            let amount = json_amount(&item["amount"])?;
+            let tx_count = item["txids"].as_array().map(|t| t.len()).unwrap_or(0);
+            usage.insert(
+                addr.to_string(),
+                AddrUsage { amount, tx_count, scanned: false },
+            );
+        }
+    }
+    Ok(usage)
+}
+
+/// Looks up addresses the wallet does not know with `scantxoutset`. This
+/// walks the whole UTXO set and can take minutes, so run it off the UI
+/// thread. Only unspent outputs are visible to it.
+pub(crate) fn scan_addresses(addresses: &[String]) -> Result<HashMap<String, AddrUsage>, String> {
+    let descriptors: Vec<String> = addresses.iter().map(|a| format!("addr({a})")).collect();
+    let arg = serde_json::to_string(&descriptors).map_err(|e| e.to_string())?;
+    let output = run_bitcoin_cli_args("scantxoutset", &["start", &arg]).map_err(|e| e.to_string())?;
+    if output.starts_with("Error:") {
+        return Err(output.trim().to_string());
+    }
+    let json: serde_json::Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+    scanned_usage(addresses, &json)
+}
+
+/// Parses `scantxoutset` output. Every address asked about gets an entry,
+/// zero if none of its outputs are unspent.
+fn scanned_usage(
+    addresses: &[String],
+    json: &serde_json::Value,
+) -> Result<HashMap<String, AddrUsage>, String> {
+    let mut usage: HashMap<String, AddrUsage> = addresses
+        .iter()
+        .map(|a| (a.clone(), AddrUsage { amount: Amount::ZERO, tx_count: 0, scanned: true }))
+        .collect();
+    for utxo in json["unspents"].as_array().into_iter().flatten() {
+        // desc looks like "addr(bc1q...)#checksum"
+        let desc = utxo["desc"].as_str().unwrap_or("");
+        let addr = desc
+            .strip_prefix("addr(")
+            .and_then(|d| d.split(')').next())
+            .unwrap_or("");
+        let amount = json_amount(&utxo["amount"])?;
+        if let Some(u) = usage.get_mut(addr) {
+            u.amount += amount;
+            u.tx_count += 1;
+        }
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(text: &str) -> Result<Amount, String> {
+        json_amount(&serde_json::from_str(text).unwrap())
+    }
+
+    #[test]
+    fn amounts_are_exact() {
+        assert_eq!(amount("20999999.99999999"), Ok(Amount::from_sat(2_099_999_999_999_999)));
+        assert_eq!(amount("0.1"), Ok(Amount::from_sat(10_000_000)));
+        assert_eq!(amount("0.00000001"), Ok(Amount::from_sat(1)));
+        assert_eq!(amount("0"), Ok(Amount::ZERO));
+        let signed: serde_json::Value = serde_json::from_str("-0.00012345").unwrap();
+        assert_eq!(json_signed_amount(&signed), Ok(SignedAmount::from_sat(-12_345)));
+    }
+
+    #[test]
+    fn bad_amounts_are_errors() {
+        assert!(amount("0.000000001").is_err());
+        assert!(amount("-1").is_err());
+        assert!(amount("\"1.0\"").is_err());
+        assert!(amount("null").is_err());
+    }
+
+    #[test]
+    fn parses_received_usage() {
+        let json = serde_json::json!([
+            {"address": "bc1qpaid", "amount": 0.0015, "txids": ["aa", "bb"]},
+            {"address": "bc1qfresh", "amount": 0, "txids": []},
+            {"amount": 1}
+        ]);
+        let usage = received_usage(&json).unwrap();
+        assert_eq!(usage.len(), 2);
+        let paid = usage["bc1qpaid"];
+        assert_eq!((paid.amount, paid.tx_count, paid.scanned), (Amount::from_sat(150_000), 2, false));
+        assert!(paid.reused());
+        assert_eq!(usage["bc1qfresh"].tx_count, 0);
+        assert!(!usage["bc1qfresh"].reused());
+
+        let bad = serde_json::json!([{"address": "bc1qx", "amount": "1", "txids": []}]);
+        assert!(received_usage(&bad).is_err());
+    }
+
+    #[test]
+    fn parses_scanned_usage() {
+        let addresses = vec!["bc1qone".to_string(), "bc1qtwo".to_string()];
+        let json = serde_json::json!({
+            "success": true,
+            "unspents": [
+                {"desc": "addr(bc1qone)#abcd1234", "amount": 0.0001},
+                {"desc": "addr(bc1qone)#abcd1234", "amount": 0.0002},
+                {"desc": "addr(bc1qother)#efgh5678", "amount": 1}
+            ]
+        });
+        let usage = scanned_usage(&addresses, &json).unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(
+            usage["bc1qone"],
+            AddrUsage { amount: Amount::from_sat(30_000), tx_count: 2, scanned: true }
+        );
+        assert_eq!(
+            usage["bc1qtwo"],
+            AddrUsage { amount: Amount::ZERO, tx_count: 0, scanned: true }
+        );
+    }
+}
@@ -0,0 +1,1522 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap, block::Title},
+};
+
+use qrcode::EcLevel;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use bitcoin::{Amount, Denomination, Network};
+use chrono::Utc;
+
+use crate::address::{AddrValidity, AddressCheck, AddressType, check_address};
+use crate::cli::{run_bitcoin_cli_args, run_bitcoin_cli_wallet};
+use crate::crypto::BookCipher;
+use crate::fiat::{FiatRate, with_fiat};
+use crate::file::{AddressEntry, save_address_book, unlock_address_book};
+use crate::fuzzy::fuzzy_score;
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::qr::{QrSize, ec_label, export_qr, next_ec, render_fitting};
+use crate::privacy::Privacy;
+use crate::node::{AddrUsage, fetch_address_usage, fetch_chain, fetch_wallet_name, scan_addresses};
+use crate::units::Unit;
+use crate::watch::{WATCH_WALLET, import_watch, watch_request};
+use crate::{centered_rect, copy_to_clipboard, hit};
+use crate::theme::Theme;
+
+// ===== Address book & QR overlay =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Focus {
+    Address,
+    Label,
+    /// Optional amount for a BIP21 payment request in the QR.
+    Amount,
+}
+
+/// Field of the selected entry being edited in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EditTarget {
+    Label,
+    Note,
+}
+
+/// Ordering of the address list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortKey {
+    Date,
+    Label,
+    Balance,
+}
+
+impl SortKey {
+    fn next(self) -> SortKey {
+        match self {
+            SortKey::Date => SortKey::Label,
+            SortKey::Label => SortKey::Balance,
+            SortKey::Balance => SortKey::Date,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Date => "date",
+            SortKey::Label => "label",
+            SortKey::Balance => "balance",
+        }
+    }
+}
+
+pub(crate) struct AddressOverlay {
+    pub address: TextInput,
+    pub label: TextInput,
+    /// Requested amount in any unit; empty for a plain address QR.
+    pub amount: TextInput,
+    pub focus: Focus,
+    pub addr_type: Option<AddressType>,
+    pub selected: usize,
+    pub status: String,
+    pub editing: Option<EditTarget>,
+    pub editor: TextInput,
+    pub search: TextInput,
+    pub searching: bool,
+    pub sort: SortKey,
+    pub confirm_delete: bool,
+    /// Balance and tx count per address, from the wallet or a UTXO scan.
+    pub usage: HashMap<String, AddrUsage>,
+    /// Pending `scantxoutset` running on a background thread.
+    pub scan: Option<Receiver<Result<HashMap<String, AddrUsage>, String>>>,
+    pub book_path: PathBuf,
+    /// Why changes cannot be saved (unreadable file, locked by another
+    /// instance). Edits still apply in memory for this session.
+    pub read_only: Option<String>,
+    /// Session key of an encrypted book, once unlocked.
+    pub cipher: Option<BookCipher>,
+    /// Encrypted file contents still waiting for the passphrase. Nothing
+    /// can be saved while this is set.
+    pub locked: Option<Vec<u8>>,
+    pub unlock: TextInput,
+    /// Pending `importdescriptors` (with rescan) for the entry at this
+    /// address, running on a background thread.
+    pub watch_import: Option<(String, Receiver<Result<String, String>>)>,
+    /// Chain of the connected node, to flag addresses for other networks.
+    pub node_chain: Option<Network>,
+    /// Unit for balances; main keeps it in step with the settings.
+    pub unit: Unit,
+    /// Fiat rate for balances, kept in step with the feed the same way.
+    pub fiat: Option<FiatRate>,
+    pub qr_ec: EcLevel,
+    pub qr_size: QrSize,
+    /// Path prompt while exporting the QR to a file.
+    pub export: Option<TextInput>,
+    pub export_px: u32,
+    /// Validation of the address input, redone only when it changes.
+    validity: AddressCheck,
+}
+
+impl AddressOverlay {
+    pub(crate) fn new(book: &[AddressEntry], book_path: PathBuf) -> Self {
+        Self {
+            address: TextInput::new("bc1qfpacvgpjms0eu6mszhwgjjs03yldesmmcgzad0", false),
+            label: TextInput::new("", true),
+            amount: TextInput::new("", true),
+            focus: Focus::Address,
+            addr_type: None,
+            selected: book.len().saturating_sub(1),
+            status: String::new(),
+            editing: None,
+            editor: TextInput::new("", true),
+            search: TextInput::new("", true),
+            searching: false,
+            sort: SortKey::Date,
+            confirm_delete: false,
+            usage: HashMap::new(),
+            scan: None,
+            book_path,
+            read_only: None,
+            cipher: None,
+            locked: None,
+            unlock: TextInput::secret(),
+            watch_import: None,
+            node_chain: None,
+            unit: Unit::default(),
+            fiat: None,
+            qr_ec: EcLevel::M,
+            qr_size: QrSize::Compact,
+            export: None,
+            export_px: 8,
+            validity: AddressCheck::default(),
+        }
+    }
+
+    /// Called when the overlay is opened from the main view.
+    pub(crate) fn open(&mut self, book: &[AddressEntry]) {
+        if let Some(e) = book.get(self.selected) {
+            self.address.set(&e.address);
+        }
+        self.node_chain = fetch_chain();
+        self.focus = Focus::Address;
+        self.editing = None;
+        self.searching = false;
+        self.confirm_delete = false;
+        self.status = match &self.read_only {
+            Some(reason) => format!("Read-only: {reason}"),
+            None => String::new(),
+        };
+    }
+
+    /// Opens the overlay showing `address` (e.g. from the explorer).
+    pub(crate) fn open_with(&mut self, book: &[AddressEntry], address: &str) {
+        self.open(book);
+        self.address.set(address);
+    }
+
+    /// Adds an entry created elsewhere, selects it and saves.
+    pub(crate) fn add_entry(&mut self, book: &mut Vec<AddressEntry>, entry: AddressEntry) -> Result<(), String> {
+        if self.locked.is_some() {
+            return Err("Unlock the address book first (w)".to_string());
+        }
+        let address = entry.address.clone();
+        book.push(entry);
+        self.selected = book.len() - 1;
+        self.save(book);
+        self.selected = book.iter().position(|e| e.address == address).unwrap_or(self.selected);
+        match &self.read_only {
+            Some(reason) => Err(format!("Not saved: {reason}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Indices into the book of the entries matching the search, in the
+    /// current sort order.
+    pub(crate) fn view(&self, book: &[AddressEntry]) -> Vec<usize> {
+        let query = self.search.value.trim();
+        let mut idx: Vec<usize> = (0..book.len())
+            .filter(|&i| {
+                query.is_empty()
+                    || fuzzy_score(query, &book[i].address).is_some()
+                    || fuzzy_score(query, &book[i].label).is_some()
+            })
+            .collect();
+
+        match self.sort {
+            SortKey::Date => idx.sort_by_key(|&i| book[i].created_at),
+            // Unlabelled entries go last
+            SortKey::Label => idx.sort_by_key(|&i| {
+                (book[i].label.is_empty(), book[i].label.to_lowercase())
+            }),
+            SortKey::Balance => idx.sort_by_key(|&i| {
+                std::cmp::Reverse(self.usage.get(&book[i].address).map(|u| u.amount).unwrap_or(Amount::ZERO))
+            }),
+        }
+        idx
+    }
+
+    /// Moves the selection by `delta` rows within the visible list.
+    fn step(&mut self, book: &[AddressEntry], delta: isize) {
+        let view = self.view(book);
+        if view.is_empty() {
+            return;
+        }
+        let pos = match view.iter().position(|&i| i == self.selected) {
+            Some(p) => (p as isize + delta).clamp(0, view.len() as isize - 1) as usize,
+            None => 0,
+        };
+        if view[pos] != self.selected {
+            self.selected = view[pos];
+            self.address.set(&book[self.selected].address);
+        }
+    }
+
+    fn type_label(&self) -> &'static str {
+        self.addr_type.map(|t| t.label()).unwrap_or("node default")
+    }
+
+    /// Asks the node for a fresh address of the selected type. Change
+    /// addresses cannot carry a label, so the label is only sent for receive.
+    fn request_address(&self, change: bool) -> Result<String, String> {
+        let label = self.label.value.trim();
+        let mut args: Vec<&str> = Vec::new();
+        let method = if change {
+            if let Some(t) = self.addr_type {
+                args.push(t.rpc_name());
+            }
+            "getrawchangeaddress"
+        } else {
+            if !label.is_empty() || self.addr_type.is_some() {
+                args.push(label);
+            }
+            if let Some(t) = self.addr_type {
+                args.push(t.rpc_name());
+            }
+            "getnewaddress"
+        };
+
+        let s = run_bitcoin_cli_args(method, &args).map_err(|e| e.to_string())?;
+        let new_addr = s.trim().to_string();
+        if matches!(check_address(&new_addr), AddrValidity::Valid(_)) {
+            Ok(new_addr)
+        } else {
+            Err(new_addr)
+        }
+    }
+
+    fn generate_and_save(&mut self, book: &mut Vec<AddressEntry>, change: bool) {
+        match self.request_address(change) {
+            Ok(new_addr) => {
+                let network = match check_address(&new_addr) {
+                    AddrValidity::Valid(info) => Some(info.pick(self.node_chain)),
+                    _ => None,
+                };
+                let entry = AddressEntry {
+                    created_at: Utc::now(),
+                    address: new_addr.clone(),
+                    address_type: self.addr_type.or_else(|| AddressType::of_address(&new_addr)),
+                    label: if change { String::new() } else { self.label.value.trim().to_string() },
+                    note: String::new(),
+                    network,
+                    wallet: fetch_wallet_name(),
+                    used: false,
+                    watch_only: false,
+                    descriptor: None,
+                };
+                book.push(entry);
+                self.selected = book.len() - 1;
+                self.save(book);
+                self.address.set(&new_addr);
+                self.label.clear();
+            }
+            Err(e) => self.status = e,
+        }
+    }
+
+    /// Persists the book. Duplicates are merged on save, so the selection
+    /// is re-found by address afterwards.
+    fn save(&mut self, book: &mut Vec<AddressEntry>) {
+        if self.locked.is_some() {
+            return;
+        }
+        if let Some(reason) = &self.read_only {
+            self.status = format!("Not saved: {reason}");
+            return;
+        }
+        let current = book.get(self.selected).map(|e| e.address.clone());
+        match save_address_book(&self.book_path, book, self.cipher.as_ref()) {
+            Ok(0) => self.status.clear(),
+            Ok(n) => self.status = format!("Merged {n} duplicate address(es)"),
+            Err(e) => self.status = format!("Save failed: {e}"),
+        }
+        if let Some(i) = current.and_then(|a| book.iter().position(|e| e.address == a)) {
+            self.selected = i;
+        }
+        self.selected = self.selected.min(book.len().saturating_sub(1));
+    }
+
+    /// The selected entry if the list shows it. A search can hide it, and
+    /// actions on the selection must not reach an entry the user can't see.
+    fn shown_selection(&self, book: &[AddressEntry]) -> Option<usize> {
+        self.view(book).contains(&self.selected).then_some(self.selected)
+    }
+
+    fn delete_selected(&mut self, book: &mut Vec<AddressEntry>) {
+        if self.shown_selection(book).is_some() {
+            book.remove(self.selected);
+            self.selected = self.selected.min(book.len().saturating_sub(1));
+            self.save(book);
+            if let Some(e) = book.get(self.selected) {
+                self.address.set(&e.address);
+            }
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+    }
+
+    /// Refreshes wallet address usage and marks addresses that have been
+    /// paid to as used. Scanned figures for non-wallet addresses are kept.
+    pub(crate) fn refresh_usage(&mut self, book: &mut Vec<AddressEntry>) {
+        match fetch_address_usage(None) {
+            Ok(u) => self.usage.extend(u),
+            Err(e) => {
+                self.status = format!("listreceivedbyaddress failed: {e}");
+                return;
+            }
+        }
+        if book.iter().any(|e| e.watch_only)
+            && let Ok(u) = fetch_address_usage(Some(WATCH_WALLET))
+        {
+            self.usage.extend(u);
+        }
+        self.mark_used(book);
+    }
+
+    fn mark_used(&mut self, book: &mut Vec<AddressEntry>) {
+        let mut changed = false;
+        for e in book.iter_mut() {
+            if !e.used && self.usage.get(&e.address).is_some_and(|u| u.tx_count > 0) {
+                e.used = true;
+                changed = true;
+            }
+        }
+        if changed {
+            self.save(book);
+        }
+    }
+
+    /// Starts a background `scantxoutset` for entries the wallet does not
+    /// know about.
+    fn start_scan(&mut self, book: &[AddressEntry]) {
+        if self.scan.is_some() {
+            self.status = "Scan already running".to_string();
+            return;
+        }
+        let targets: Vec<String> = book
+            .iter()
+            .filter(|e| !e.is_descriptor() && self.usage.get(&e.address).is_none_or(|u| u.scanned))
+            .map(|e| e.address.clone())
+            .collect();
+        if targets.is_empty() {
+            self.status = "All saved addresses belong to the wallet".to_string();
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(scan_addresses(&targets));
+        });
+        self.scan = Some(rx);
+        self.status = "Scanning UTXO set (scantxoutset)…".to_string();
+    }
+
+    /// Picks up a finished scan. Called once per main loop iteration.
+    pub(crate) fn poll(&mut self, book: &mut Vec<AddressEntry>) {
+        self.poll_watch_import(book);
+        let Some(rx) = &self.scan else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Ok(u)) => {
+                self.scan = None;
+                self.status = format!("Scanned {} address(es)", u.len());
+                self.usage.extend(u);
+                self.mark_used(book);
+            }
+            Ok(Err(e)) => {
+                self.scan = None;
+                self.status = format!("scantxoutset failed: {e}");
+            }
+            Err(TryRecvError::Disconnected) => self.scan = None,
+            Err(TryRecvError::Empty) => {}
+        }
+    }
+
+    /// Marks the address or descriptor in the input as watch-only and
+    /// imports it into the watch wallet, adding it to the book if needed.
+    /// On an entry that is already watched, stops watching it instead.
+    fn toggle_watch(&mut self, book: &mut Vec<AddressEntry>) {
+        let input = self.address.value.trim().to_string();
+        if let Some(e) = book.iter_mut().find(|e| e.address == input && e.watch_only) {
+            e.watch_only = false;
+            self.save(book);
+            self.status = format!("No longer watched (the descriptor stays in the {WATCH_WALLET} wallet)");
+            return;
+        }
+        if self.watch_import.is_some() {
+            self.status = "A watch-only import is already running".to_string();
+            return;
+        }
+
+        let is_descriptor = input.contains('(');
+        let network = match check_address(&input) {
+            AddrValidity::Valid(info) => Some(info.pick(self.node_chain)),
+            _ if is_descriptor => None,
+            _ if input.starts_with("xpub") || input.starts_with("tpub") || input.starts_with("zpub") => {
+                self.status = format!("Wrap the key in a descriptor, e.g. wpkh({}/0/*)", input);
+                return;
+            }
+            _ => {
+                self.status = "Enter an address or a descriptor to watch".to_string();
+                return;
+            }
+        };
+        let label = self.label.value.trim().to_string();
+        let req = match watch_request(&input, &label) {
+            Ok(r) => r,
+            Err(e) => {
+                self.status = format!("getdescriptorinfo failed: {e}");
+                return;
+            }
+        };
+
+        // Descriptor entries are keyed by the checksummed form
+        let key = if is_descriptor { req.descriptor.clone() } else { input.clone() };
+        let stem = key.split('#').next().unwrap_or(&key).to_string();
+        match book
+            .iter()
+            .position(|e| e.address == key || (is_descriptor && e.address.split('#').next() == Some(stem.as_str())))
+        {
+            Some(i) => self.selected = i,
+            None => {
+                book.push(AddressEntry {
+                    created_at: Utc::now(),
+                    address: key.clone(),
+                    address_type: if is_descriptor { None } else { AddressType::of_address(&key) },
+                    label: label.clone(),
+                    note: String::new(),
+                    network,
+                    wallet: None,
+                    used: false,
+                    watch_only: false,
+                    descriptor: None,
+                });
+                self.selected = book.len() - 1;
+            }
+        }
+        let entry = &mut book[self.selected];
+        entry.address = key.clone();
+        entry.watch_only = true;
+        entry.descriptor = Some(req.descriptor.clone());
+        if entry.wallet.is_none() && !is_descriptor {
+            entry.wallet = Some(WATCH_WALLET.to_string());
+        }
+        if entry.label.is_empty() {
+            entry.label = label;
+        }
+        self.save(book);
+        self.address.set(&key);
+        self.label.clear();
+
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(import_watch(&[req], true));
+        });
+        self.watch_import = Some((key, rx));
+        self.status = "Importing into watch-only wallet and rescanning…".to_string();
+    }
+
+    /// True while an import (and its rescan) is in progress.
+    pub(crate) fn watch_importing(&self) -> bool {
+        self.watch_import.is_some()
+    }
+
+    /// Picks up a finished watch-only import. A failed import clears the
+    /// watch flag again so it can be retried.
+    fn poll_watch_import(&mut self, book: &mut Vec<AddressEntry>) {
+        let Some((key, rx)) = &self.watch_import else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(r) => r,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("import thread ended".to_string()),
+        };
+        let key = key.clone();
+        self.watch_import = None;
+        match result {
+            Ok(msg) => {
+                self.status = msg;
+                self.refresh_usage(book);
+            }
+            Err(e) => {
+                if let Some(entry) = book.iter_mut().find(|e| e.address == key) {
+                    entry.watch_only = false;
+                }
+                self.save(book);
+                self.status = format!("importdescriptors failed: {e}");
+            }
+        }
+    }
+
+    /// What the QR encodes: the address, or a `bitcoin:` URI (BIP21) when
+    /// an amount is requested. Errors describe a bad amount.
+    fn qr_data(&self) -> Result<String, String> {
+        let addr = self.address.value.trim();
+        if self.amount.value.trim().is_empty() {
+            return Ok(addr.to_string());
+        }
+        let amount = self.unit.parse(&self.amount.value)?;
+        if amount == Amount::ZERO {
+            return Err("the amount is zero".to_string());
+        }
+        Ok(format!("bitcoin:{addr}?amount={}", amount.to_string_in(Denomination::Bitcoin)))
+    }
+
+    /// Opens the export prompt with a file name next to the address book.
+    fn start_export(&mut self) {
+        let addr = self.address.value.trim();
+        if !matches!(check_address(addr), AddrValidity::Valid(_)) {
+            self.status = "Nothing to export: enter a valid address".to_string();
+            return;
+        }
+        if let Err(e) = self.qr_data() {
+            self.status = format!("Nothing to export: {e}");
+            return;
+        }
+        let name = format!("qr-{}.png", addr.chars().take(12).collect::<String>());
+        let dir = self.book_path.parent().map(PathBuf::from).unwrap_or_default();
+        self.export = Some(TextInput::new(&dir.join(name).to_string_lossy(), true));
+    }
+
+    /// Balance column text with its fiat value, masked in privacy mode.
+    fn amount_text(&self, address: &str, privacy: Privacy, unit: Unit) -> Option<String> {
+        self.usage.get(address).map(|u| {
+            let shown = with_fiat(unit.format(u.amount), u.amount.to_sat() as i64, self.fiat.as_ref());
+            privacy.amount(&shown)
+        })
+    }
+
+    fn start_edit(&mut self, target: EditTarget, book: &[AddressEntry]) {
+        if let Some(e) = self.shown_selection(book).and_then(|i| book.get(i)) {
+            let current = match target {
+                EditTarget::Label => &e.label,
+                EditTarget::Note => &e.note,
+            };
+            self.editor.set(current);
+            self.editing = Some(target);
+        }
+    }
+
+    /// Stores the edited field. Labels of wallet addresses are pushed to
+    /// the node with `setlabel` so both sides stay in sync.
+    fn commit_edit(&mut self, target: EditTarget, book: &mut Vec<AddressEntry>) {
+        let value = self.editor.value.trim().to_string();
+        let Some(entry) = book.get_mut(self.selected) else {
+            return;
+        };
+        match target {
+            EditTarget::Label => entry.label = value.clone(),
+            EditTarget::Note => entry.note = value.clone(),
+        }
+        let sync = match (target, &entry.wallet) {
+            (EditTarget::Label, Some(w)) if !entry.is_descriptor() => Some(run_bitcoin_cli_wallet(
+                w,
+                "setlabel",
+                &[entry.address.as_str(), value.as_str()],
+            )),
+            _ => None,
+        };
+        self.save(book);
+        if let Some(res) = sync {
+            match res {
+                Ok(out) if out.starts_with("Error:") => {
+                    self.status = format!("Saved locally; setlabel failed: {}", out.trim());
+                }
+                Ok(_) => {}
+                Err(e) => self.status = format!("Saved locally; setlabel failed: {e}"),
+            }
+        }
+    }
+
+    /// Tries the typed passphrase on the encrypted book and, on success,
+    /// replaces the (empty) in-memory book with its contents.
+    fn try_unlock(&mut self, book: &mut Vec<AddressEntry>) {
+        let Some(data) = &self.locked else {
+            return;
+        };
+        match unlock_address_book(data, &self.unlock.value) {
+            Ok((cipher, entries)) => {
+                *book = entries;
+                self.cipher = Some(cipher);
+                self.locked = None;
+                self.selected = book.len().saturating_sub(1);
+                self.open(book);
+                self.refresh_usage(book);
+            }
+            Err(e) => self.status = format!("Unlock failed: {e}"),
+        }
+        self.unlock.clear();
+    }
+
+    /// Pasted text goes to whichever input currently takes keys.
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        let input = if self.locked.is_some() {
+            &mut self.unlock
+        } else if let Some(path) = &mut self.export {
+            path
+        } else if self.editing.is_some() {
+            &mut self.editor
+        } else if self.searching {
+            &mut self.search
+        } else {
+            match self.focus {
+                Focus::Address => &mut self.address,
+                Focus::Label => &mut self.label,
+                Focus::Amount => &mut self.amount,
+            }
+        };
+        input.insert_str(text.trim());
+    }
+
+    /// Handles a key while the overlay is open. Returns false when the
+    /// overlay should close.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, book: &mut Vec<AddressEntry>, keymap: &Keymap) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let action = keymap.action(Scope::AddressBook, key);
+
+        // ---- Passphrase prompt for an encrypted book ----
+        if self.locked.is_some() {
+            match key.code {
+                KeyCode::Enter => self.try_unlock(book),
+                KeyCode::Esc => return false,
+                _ if action == Some(Action::BookClose) => return false,
+                code if !ctrl => {
+                    self.unlock.handle_key(code);
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        // ---- QR export path prompt ----
+        if let Some(path) = &mut self.export {
+            match key.code {
+                KeyCode::Enter => {
+                    let target = PathBuf::from(path.value.trim());
+                    let data = self.qr_data().unwrap_or_else(|_| self.address.value.trim().to_string());
+                    self.status = match export_qr(&data, self.qr_ec, self.export_px, &target) {
+                        Ok(()) => format!("QR saved to {}", target.display()),
+                        Err(e) => format!("Export failed: {e}"),
+                    };
+                    self.export = None;
+                }
+                KeyCode::Esc => self.export = None,
+                KeyCode::Up => self.export_px = (self.export_px + 2).min(32),
+                KeyCode::Down => self.export_px = self.export_px.saturating_sub(2).max(2),
+                code if !ctrl => {
+                    path.handle_key(code);
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        // ---- In-place editing of label / note ----
+        if let Some(target) = self.editing {
+            match key.code {
+                KeyCode::Enter => {
+                    self.commit_edit(target, book);
+                    self.editing = None;
+                }
+                KeyCode::Esc => self.editing = None,
+                code if !ctrl => {
+                    self.editor.handle_key(code);
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        // ---- Delete confirmation ----
+        if self.confirm_delete {
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                self.delete_selected(book);
+            }
+            self.confirm_delete = false;
+            return true;
+        }
+
+        // ---- Search box ----
+        if self.searching {
+            match key.code {
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.searching = false;
+                }
+                KeyCode::Up => self.step(book, -1),
+                KeyCode::Down => self.step(book, 1),
+                code if !ctrl && self.search.handle_key(code) => self.step(book, 0),
+                _ => {}
+            }
+            return true;
+        }
+
+        match action {
+            Some(Action::BookNew) => self.generate_and_save(book, false),
+            Some(Action::BookChange) => self.generate_and_save(book, true),
+            Some(Action::BookGetNew) => match self.request_address(false) {
+                Ok(a) => self.address.set(&a),
+                Err(e) => self.status = e,
+            },
+            Some(Action::BookType) => {
+                self.addr_type = AddressType::cycle(self.addr_type);
+            }
+            Some(Action::BookQrLevel) => self.qr_ec = next_ec(self.qr_ec),
+            Some(Action::BookQrSize) => self.qr_size = self.qr_size.next(),
+            Some(Action::BookExport) => self.start_export(),
+            Some(Action::BookCopy) => {
+                let _ = copy_to_clipboard(&self.address.value);
+            }
+            Some(Action::BookClose) => return false,
+            Some(Action::BookEditLabel) => self.start_edit(EditTarget::Label, book),
+            Some(Action::BookEditNote) => self.start_edit(EditTarget::Note, book),
+            Some(Action::BookSearch) => self.searching = true,
+            Some(Action::BookSort) => self.cycle_sort(),
+            Some(Action::BookScan) => self.start_scan(book),
+            Some(Action::BookWatch) => self.toggle_watch(book),
+            Some(Action::BookDelete) => {
+                if self.shown_selection(book).is_some() {
+                    self.confirm_delete = true;
+                }
+            }
+            Some(Action::BookUsed) => {
+                if let Some(e) = self.shown_selection(book).and_then(|i| book.get_mut(i)) {
+                    e.used = !e.used;
+                    self.save(book);
+                }
+            }
+            Some(_) => {}
+            None => self.handle_edit_key(key, book),
+        }
+        true
+    }
+
+    /// Keys that are not bound to an action: focus, list selection and
+    /// editing the focused input.
+    fn handle_edit_key(&mut self, key: KeyEvent, book: &[AddressEntry]) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            // ---- Focus ----
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Address => Focus::Label,
+                    Focus::Label => Focus::Amount,
+                    Focus::Amount => Focus::Address,
+                };
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Address => Focus::Amount,
+                    Focus::Label => Focus::Address,
+                    Focus::Amount => Focus::Label,
+                };
+            }
+
+            // ---- Navigation in list ----
+            KeyCode::Up => self.step(book, -1),
+            KeyCode::Down => self.step(book, 1),
+
+            // ---- Editing the focused input ----
+            code => {
+                if !ctrl {
+                    match self.focus {
+                        Focus::Address => self.address.handle_key(code),
+                        Focus::Label => self.label.handle_key(code),
+                        Focus::Amount => self.amount.handle_key(code),
+                    };
+                }
+            }
+        }
+    }
+
+    pub(crate) fn draw(
+        &self,
+        f: &mut Frame,
+        size: Rect,
+        book: &[AddressEntry],
+        keymap: &Keymap,
+        privacy: Privacy,
+        theme: &Theme,
+    ) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        // Outer box
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(" Address Book & QR (edit left • list right) ");
+        if !self.status.is_empty() {
+            outer = outer.title_bottom(Span::styled(
+                format!(" {} ", self.status.trim()),
+                theme.warn(),
+            ));
+        }
+        f.render_widget(outer, area);
+
+        if self.locked.is_some() {
+            self.draw_unlock(f, area, theme);
+            return;
+        }
+
+        let (left, meta, right) = Self::columns(area);
+
+        // Validation
+        let validity = self.validity.check(&self.address.value);
+        let green = theme.ok();
+        let red = theme.error();
+        let yellow = theme.warn();
+        let (input_title, input_title_style, qr_title, qr_title_style, qr_dim): (String, Style, String, Style, bool) =
+            match &validity {
+                AddrValidity::Empty => (
+                    " BTC Address ".to_string(),
+                    yellow,
+                    " Bitcoin QR Code — (enter an address) ".to_string(),
+                    yellow,
+                    true,
+                ),
+                AddrValidity::Invalid(_) if self.address.value.contains('(') => (
+                    format!(" Descriptor{} ", keymap.note(&[Action::BookWatch])),
+                    yellow,
+                    " Bitcoin QR Code — (not an address) ".to_string(),
+                    yellow,
+                    true,
+                ),
+                AddrValidity::Invalid(e) => (
+                    format!(" BTC Address — INVALID: {} ", e.message),
+                    red,
+                    " Bitcoin QR Code — INVALID ".to_string(),
+                    red,
+                    true,
+                ),
+                AddrValidity::Valid(info) => {
+                    let nets = info.network_label();
+                    match self.node_chain {
+                        Some(chain) if !info.valid_on(chain) => (
+                            format!(" BTC Address — VALID {} ({nets}) ", info.script_label()),
+                            yellow,
+                            format!(" ⚠ {nets} address, node is on {chain} "),
+                            yellow.add_modifier(Modifier::BOLD),
+                            false,
+                        ),
+                        _ => (
+                            format!(" BTC Address — VALID {} ", info.script_label()),
+                            green,
+                            format!(" Bitcoin QR Code — VALID ({nets}) "),
+                            green,
+                            false,
+                        ),
+                    }
+                }
+            };
+
+        let focused = |on: bool| {
+            if on {
+                theme.focused()
+            } else {
+                theme.muted()
+            }
+        };
+
+        // Input box
+        let input_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(focused(self.focus == Focus::Address))
+            .title(Span::styled(input_title, input_title_style));
+        // Underline the character that broke the checksum or charset
+        let input_line = match &validity {
+            _ if privacy.hides_addresses() && !self.address.value.is_empty() => Line::from(privacy.address(&self.address.value)),
+            AddrValidity::Invalid(e) if e.position.is_some() => {
+                let pos = e.position.unwrap_or(0);
+                let chars: Vec<char> = self.address.value.trim().chars().collect();
+                let lead = self.address.value.len() - self.address.value.trim_start().len();
+                let head: String = " ".repeat(lead) + &chars[..pos.min(chars.len())].iter().collect::<String>();
+                let bad: String = chars.get(pos).map(|c| c.to_string()).unwrap_or_default();
+                let tail: String = chars.iter().skip(pos + 1).collect();
+                Line::from(vec![
+                    Span::raw(head),
+                    Span::styled(bad, red.add_modifier(Modifier::UNDERLINED | Modifier::BOLD)),
+                    Span::raw(tail),
+                ])
+            }
+            _ => Line::from(self.address.value.clone()),
+        };
+        let input = Paragraph::new(input_line).block(input_block);
+        f.render_widget(input, left[0]);
+
+        // Label box
+        let label_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(focused(self.focus == Focus::Label))
+            .title(" Label (optional, Tab) ");
+        let label = Paragraph::new(self.label.value.clone()).block(label_block);
+        f.render_widget(label, meta[0]);
+
+        // Requested amount
+        let qr_data = self.qr_data();
+        let amount_title = match &qr_data {
+            Err(e) => Span::styled(format!(" Amount — {e} "), red),
+            Ok(_) => Span::raw(format!(" Amount ({}) ", self.unit.label())),
+        };
+        let amount_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(focused(self.focus == Focus::Amount))
+            .title(amount_title);
+        let amount = Paragraph::new(privacy.amount(&self.amount.value)).block(amount_block);
+        f.render_widget(amount, meta[1]);
+
+        // Type selector
+        let type_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(format!(" Type{} ", keymap.note(&[Action::BookType])));
+        let type_par = Paragraph::new(Span::styled(
+            self.type_label(),
+            theme.selected(),
+        ))
+        .block(type_block);
+        f.render_widget(type_par, meta[2]);
+
+        // Cursor inside the focused input
+        let (field, rect) = match self.focus {
+            Focus::Address => (&self.address, left[0]),
+            Focus::Label => (&self.label, meta[0]),
+            Focus::Amount => (&self.amount, meta[1]),
+        };
+        let cursor_x = (rect.x + 1).saturating_add(field.cursor as u16);
+        f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+
+        // QR box
+        let qr_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(Span::styled(qr_title, qr_title_style));
+        let qr_block = match &validity {
+            AddrValidity::Valid(info) if !privacy.hides_addresses() => qr_block.title_bottom(Span::styled(
+                format!(" scriptPubKey {} ", info.script_hex()),
+                theme.muted(),
+            )),
+            _ => qr_block,
+        };
+        let settings = format!(
+            " EC {} • {}{} ",
+            ec_label(self.qr_ec),
+            self.qr_size.label(),
+            keymap.note(&[Action::BookQrLevel, Action::BookQrSize])
+        );
+        let (qr_text, qr_note, qr_dim) = if qr_dim {
+            (String::new(), None, true)
+        } else if privacy.hides_addresses() {
+            ("Hidden in privacy mode".to_string(), None, true)
+        } else {
+            let inner = qr_block.inner(left[2]);
+            // A bad amount leaves the plain address in the QR
+            let data = qr_data.unwrap_or_else(|_| self.address.value.trim().to_string());
+            match render_fitting(
+                &data,
+                self.qr_ec,
+                self.qr_size,
+                inner.width as usize,
+                inner.height as usize,
+            ) {
+                Ok((text, note)) => (text, note, false),
+                Err(e) => (e, None, true),
+            }
+        };
+        let qr_block = qr_block.title(
+            Title::from(Span::styled(
+                match qr_note {
+                    Some(n) => format!(" fallback: {n} "),
+                    None => settings,
+                },
+                theme.muted(),
+            ))
+            .alignment(Alignment::Right),
+        );
+        let mut qr_par = Paragraph::new(qr_text).block(qr_block);
+        if qr_dim {
+            qr_par = qr_par.style(theme.muted()).wrap(Wrap { trim: true });
+        }
+        f.render_widget(qr_par, left[2]);
+
+        // QR export prompt
+        if let Some(path) = &self.export {
+            let rect = Rect {
+                x: left[2].x,
+                y: left[2].y,
+                width: left[2].width,
+                height: 3.min(left[2].height),
+            };
+            f.render_widget(Clear, rect);
+            f.render_widget(Block::default().style(theme.base()), rect);
+            f.render_widget(
+                Paragraph::new(path.value.clone()).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(theme.focused())
+                        .title(format!(
+                            " Export QR (.png/.svg) • {} px/module (↑/↓) • Enter=save • Esc=cancel ",
+                            self.export_px
+                        )),
+                ),
+                rect,
+            );
+            let cursor_x = (rect.x + 1).saturating_add(path.cursor as u16);
+            f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+        }
+
+        // Right column: address list + details of the selected entry
+        // Search box
+        let search_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(focused(self.searching))
+            .title(format!(" Search{} ", keymap.note(&[Action::BookSearch])));
+        f.render_widget(
+            Paragraph::new(self.search.value.clone()).block(search_block),
+            right[0],
+        );
+        if self.searching {
+            let rect = right[0];
+            let cursor_x = (rect.x + 1).saturating_add(self.search.cursor as u16);
+            f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+        }
+
+        let view = self.view(book);
+        let list_items: Vec<ListItem> = view
+            .iter()
+            .map(|&i| (i, &book[i]))
+            .map(|(i, e)| {
+                let date_str = e.created_at.format("%Y-%m-%d %H:%M").to_string();
+                let type_str = if e.is_descriptor() {
+                    "desc"
+                } else {
+                    e.address_type.map(|t| t.label()).unwrap_or("?")
+                };
+                let mut shown = if privacy.hides_addresses() {
+                    format!("{}  {:<7} {}", date_str, type_str, privacy.address(&e.address))
+                } else {
+                    format!("{}  {:<7} {}", date_str, type_str, shorten(&e.address))
+                };
+                if let Some(amount) = self.amount_text(&e.address, privacy, self.unit) {
+                    shown.push_str(&format!("  {amount}"));
+                }
+                let mut spans = vec![Span::raw(shown)];
+                if self.usage.get(&e.address).is_some_and(|u| u.reused()) {
+                    spans.push(Span::styled(" ⚠ reused", theme.error()));
+                }
+                if e.watch_only {
+                    spans.push(Span::styled(" 👁", theme.info()));
+                }
+                if !e.label.is_empty() {
+                    spans.push(Span::raw(format!("  [{}]", e.label)));
+                }
+                let mut item = ListItem::new(Line::from(spans));
+                if i == self.selected {
+                    item = item.style(
+                        theme.selected(),
+                    );
+                } else if e.used {
+                    item = item.style(theme.muted());
+                }
+                item
+            })
+            .collect();
+
+        let list_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(format!(
+                " Addresses {}/{} (↑/↓ select) • sort: {}{} ",
+                view.len(),
+                book.len(),
+                self.sort.label(),
+                keymap.note(&[Action::BookSort])
+            ));
+        let list = List::new(list_items).block(list_block);
+        let mut list_state = ListState::default();
+        list_state.select(view.iter().position(|&i| i == self.selected));
+        f.render_stateful_widget(list, right[1], &mut list_state);
+
+        let details = match book.get(self.selected) {
+            Some(e) => vec![
+                Line::from(privacy.address(&e.address)),
+                Line::from(format!("Label:   {}", e.label)),
+                Line::from(format!("Note:    {}", e.note)),
+                Line::from(format!(
+                    "Network: {}   Wallet: {}",
+                    e.network.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string()),
+                    e.wallet.as_deref().unwrap_or("-"),
+                )),
+                Line::from(format!(
+                    "Used:    {}   Watch-only: {}",
+                    if e.used { "yes" } else { "no" },
+                    if e.watch_only { "yes" } else { "no" },
+                )),
+                match self.usage.get(&e.address) {
+                    Some(u) => {
+                        let amount = self.amount_text(&e.address, privacy, self.unit).unwrap_or_default();
+                        let what = if u.scanned { "unspent in" } else { "received in" };
+                        let kind = if u.scanned { "UTXO(s)" } else { "tx(s)" };
+                        let mut spans = vec![Span::raw(format!(
+                            "Balance: {amount} {what} {} {kind}",
+                            u.tx_count
+                        ))];
+                        if u.reused() {
+                            spans.push(Span::styled(
+                                "  ⚠ address reused",
+                                theme.error().add_modifier(Modifier::BOLD),
+                            ));
+                        }
+                        Line::from(spans)
+                    }
+                    None => Line::from(format!("Balance: unknown{}", keymap.note(&[Action::BookScan]))),
+                },
+            ],
+            None => vec![Line::from("(no saved addresses)")],
+        };
+        let details_block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(format!(
+                " Details{} ",
+                keymap.note(&[Action::BookEditLabel, Action::BookEditNote, Action::BookUsed])
+            ));
+        f.render_widget(
+            Paragraph::new(details).block(details_block).wrap(Wrap { trim: true }),
+            right[2],
+        );
+
+        // Inline editor for label / note
+        if let Some(target) = self.editing {
+            let title = match target {
+                EditTarget::Label => " Edit label (Enter=save • Esc=cancel) ",
+                EditTarget::Note => " Edit note (Enter=save • Esc=cancel) ",
+            };
+            let rect = Rect {
+                x: right[2].x,
+                y: right[2].y,
+                width: right[2].width,
+                height: 3.min(right[2].height),
+            };
+            f.render_widget(Clear, rect);
+            f.render_widget(Block::default().style(theme.base()), rect);
+            let edit = Paragraph::new(self.editor.value.clone()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.focused())
+                    .title(title),
+            );
+            f.render_widget(edit, rect);
+            let cursor_x = (rect.x + 1).saturating_add(self.editor.cursor as u16);
+            f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+        }
+
+        // Delete confirmation
+        if self.confirm_delete
+            && let Some(e) = book.get(self.selected)
+        {
+            let rect = centered_rect(60, 20, area);
+            f.render_widget(Clear, rect);
+            f.render_widget(Block::default().style(theme.base()), rect);
+            let prompt = Paragraph::new(vec![
+                Line::from(format!("Delete {}?", privacy.address(&e.address))),
+                Line::from("y = delete • any other key = cancel"),
+            ])
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.error())
+                    .title(" Confirm delete "),
+            );
+            f.render_widget(prompt, rect);
+        }
+    }
+}
+
+impl AddressOverlay {
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(80, 75, size)
+    }
+
+    /// Splits the overlay into the left column (address input, label +
+    /// type row, QR), that row, and the right column (search, list,
+    /// details). Shared by drawing and mouse clicks.
+    fn columns(area: Rect) -> ([Rect; 3], [Rect; 3], [Rect; 3]) {
+        let cols = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(1)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area);
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(8)])
+            .split(cols[0]);
+        let meta = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(30), Constraint::Percentage(30)])
+            .split(left[1]);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(3), Constraint::Length(9)])
+            .split(cols[1]);
+        ([left[0], left[1], left[2]], [meta[0], meta[1], meta[2]], [right[0], right[1], right[2]])
+    }
+
+    /// Clicks select list entries and focus the address and label fields;
+    /// the wheel moves the selection. Ignored while a prompt is open.
+    pub(crate) fn handle_mouse(&mut self, mouse: MouseEvent, size: Rect, book: &[AddressEntry]) {
+        if self.locked.is_some() || self.editing.is_some() || self.export.is_some() || self.confirm_delete {
+            return;
+        }
+        let (left, meta, right) = Self::columns(Self::area(size));
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.step(book, -1),
+            MouseEventKind::ScrollDown => self.step(book, 1),
+            MouseEventKind::Down(MouseButton::Left) if hit(left[0], mouse) => {
+                self.searching = false;
+                self.focus = Focus::Address;
+            }
+            MouseEventKind::Down(MouseButton::Left) if hit(meta[0], mouse) => {
+                self.searching = false;
+                self.focus = Focus::Label;
+            }
+            MouseEventKind::Down(MouseButton::Left) if hit(meta[1], mouse) => {
+                self.searching = false;
+                self.focus = Focus::Amount;
+            }
+            MouseEventKind::Down(MouseButton::Left) if hit(right[0], mouse) => self.searching = true,
+            MouseEventKind::Down(MouseButton::Left) if hit(right[1], mouse) => {
+                // The list scrolls just far enough to show the selection
+                let view = self.view(book);
+                let rows = right[1].inner(&Margin { vertical: 1, horizontal: 1 });
+                let pos = view.iter().position(|&i| i == self.selected).unwrap_or(0);
+                let offset = (pos + 1).saturating_sub(rows.height as usize);
+                let row = mouse.row.saturating_sub(rows.y) as usize;
+                if hit(rows, mouse)
+                    && let Some(&i) = view.get(offset + row)
+                {
+                    self.selected = i;
+                    self.address.set(&book[i].address);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_unlock(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let rect = centered_rect(60, 30, area);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Length(3), Constraint::Min(0)])
+            .split(rect);
+
+        f.render_widget(
+            Paragraph::new("The address book is encrypted. Enter the passphrase (Esc = close).")
+                .wrap(Wrap { trim: true }),
+            rows[0],
+        );
+        let input = Paragraph::new(self.unlock.shown()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.focused())
+                .title(" Passphrase "),
+        );
+        f.render_widget(input, rows[1]);
+        let cursor_x = (rows[1].x + 1).saturating_add(self.unlock.cursor as u16);
+        f.set_cursor(cursor_x.min(rows[1].x + rows[1].width.saturating_sub(2)), rows[1].y + 1);
+    }
+}
+
+/// Long addresses and descriptors as their first 12 and last 8 chars.
+/// Counts chars, not bytes, as descriptors and labels may be non-ASCII.
+fn shorten(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= 22 {
+        return text.to_string();
+    }
+    let head: String = chars[..12].iter().collect();
+    let tail: String = chars[chars.len() - 8..].iter().collect();
+    format!("{head}…{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{click, ctrl, key, mouse, temp_dir};
+
+    #[test]
+    fn shortens_by_char() {
+        assert_eq!(shorten("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"), "bc1qcr8te4kr…8z306fyu");
+        assert_eq!(shorten("1LqBGSKuX5yYUonjxT5qGf"), "1LqBGSKuX5yYUonjxT5qGf");
+        // Multi-byte chars at both cut points
+        assert_eq!(shorten("wpkh([d34db33f/84’/0’/0’]xpub…/0/*)#ééééééé"), "wpkh([d34db3…#ééééééé");
+        assert_eq!(shorten(&"é".repeat(23)), format!("{}…{}", "é".repeat(12), "é".repeat(8)));
+    }
+
+    fn book() -> Vec<AddressEntry> {
+        serde_json::from_value(serde_json::json!([
+            {"created_at": "2024-03-01T00:00:00Z", "address": "bc1qcoffee", "label": "coffee"},
+            {"created_at": "2024-01-01T00:00:00Z", "address": "bc1qnolabel", "label": ""},
+            {"created_at": "2024-02-01T00:00:00Z", "address": "1Rent", "label": "Rent"},
+        ]))
+        .unwrap()
+    }
+
+    fn overlay(book: &[AddressEntry]) -> AddressOverlay {
+        AddressOverlay::new(book, PathBuf::from("addresses.json"))
+    }
+
+    #[test]
+    fn sorts_the_view() {
+        let book = book();
+        let mut o = overlay(&book);
+        assert_eq!(o.view(&book), [1, 2, 0]);
+        o.cycle_sort();
+        assert_eq!(o.sort, SortKey::Label);
+        // Case-insensitive, unlabelled last
+        assert_eq!(o.view(&book), [0, 2, 1]);
+        o.cycle_sort();
+        let usage = |sat| AddrUsage { amount: Amount::from_sat(sat), tx_count: 1, scanned: false };
+        o.usage.insert("1Rent".to_string(), usage(5));
+        o.usage.insert("bc1qnolabel".to_string(), usage(7));
+        assert_eq!(o.view(&book), [1, 2, 0]);
+        o.cycle_sort();
+        assert_eq!(o.sort, SortKey::Date);
+    }
+
+    #[test]
+    fn marks_paid_addresses_used() {
+        let dir = temp_dir("overlay-used");
+        let mut book = book();
+        let mut o = AddressOverlay::new(&book, dir.join("addresses.json"));
+        let (tx, rx) = mpsc::channel();
+        o.scan = Some(rx);
+        let usage = |tx_count| AddrUsage { amount: Amount::ZERO, tx_count, scanned: true };
+        tx.send(Ok(HashMap::from([
+            ("bc1qcoffee".to_string(), usage(1)),
+            ("1Rent".to_string(), usage(0)),
+        ])))
+        .unwrap();
+        o.poll(&mut book);
+        assert!(o.scan.is_none());
+        assert_eq!(book.iter().map(|e| e.used).collect::<Vec<_>>(), [true, false, false]);
+        let saved = std::fs::read_to_string(dir.join("addresses.json")).unwrap();
+        assert!(saved.contains("\"used\": true"));
+
+        // Nothing new to mark, so nothing is written
+        std::fs::remove_file(dir.join("addresses.json")).unwrap();
+        o.mark_used(&mut book);
+        assert!(!dir.join("addresses.json").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn searches_labels_and_addresses() {
+        let book = book();
+        let mut o = overlay(&book);
+        o.search.set("rent");
+        assert_eq!(o.view(&book), [2]);
+        o.search.set("nolab");
+        assert_eq!(o.view(&book), [1]);
+        o.search.set("cfe");
+        assert_eq!(o.view(&book), [0]);
+        o.search.set("zzz");
+        assert!(o.view(&book).is_empty());
+    }
+
+    #[test]
+    fn steps_within_the_view() {
+        let book = book();
+        let mut o = overlay(&book);
+        o.selected = 1;
+        o.step(&book, 1);
+        assert_eq!((o.selected, o.address.value.as_str()), (2, "1Rent"));
+        o.step(&book, 5);
+        assert_eq!(o.selected, 0);
+        o.step(&book, -9);
+        assert_eq!(o.selected, 1);
+        // A selection outside the filtered view jumps to its first row
+        o.search.set("coffee");
+        o.step(&book, 1);
+        assert_eq!(o.selected, 0);
+    }
+
+    #[test]
+    fn clicks_focus_fields_and_pick_rows() {
+        let book = book();
+        let mut o = overlay(&book);
+        let size = Rect::new(0, 0, 120, 40);
+        let (left, meta, right) = AddressOverlay::columns(AddressOverlay::area(size));
+        let inside = |r: Rect| click(r.x + 1, r.y + 1);
+
+        o.handle_mouse(inside(meta[0]), size, &book);
+        assert_eq!(o.focus, Focus::Label);
+        o.handle_mouse(inside(meta[1]), size, &book);
+        assert_eq!(o.focus, Focus::Amount);
+        o.handle_mouse(inside(right[0]), size, &book);
+        assert!(o.searching);
+        o.handle_mouse(inside(left[0]), size, &book);
+        assert!(!o.searching);
+        assert_eq!(o.focus, Focus::Address);
+
+        // The first list row is the oldest entry; rows past the end do nothing
+        o.handle_mouse(inside(right[1]), size, &book);
+        assert_eq!((o.selected, o.address.value.as_str()), (1, "bc1qnolabel"));
+        o.handle_mouse(click(right[1].x + 1, right[1].y + 3), size, &book);
+        assert_eq!(o.selected, 0);
+        o.handle_mouse(click(right[1].x + 1, right[1].y + 5), size, &book);
+        assert_eq!(o.selected, 0);
+        o.handle_mouse(mouse(MouseEventKind::ScrollUp, 0, 0), size, &book);
+        assert_eq!(o.selected, 2);
+
+        // A pending delete keeps the mouse from changing the selection
+        o.confirm_delete = true;
+        o.handle_mouse(inside(right[1]), size, &book);
+        assert_eq!(o.selected, 2);
+    }
+
+    #[test]
+    fn acts_only_on_a_shown_selection() {
+        let keymap = Keymap::defaults();
+        let mut book = book();
+        let mut o = overlay(&book);
+        o.selected = 2;
+        o.search.set("zzz");
+        o.step(&book, 0);
+        assert_eq!(o.selected, 2);
+        assert_eq!(o.shown_selection(&book), None);
+        let delete = ctrl('d');
+        o.handle_key(delete, &mut book, &keymap);
+        assert!(!o.confirm_delete);
+        o.delete_selected(&mut book);
+        assert_eq!(book.len(), 3);
+        o.start_edit(EditTarget::Label, &book);
+        assert_eq!(o.editing, None);
+
+        o.search.set("rent");
+        assert_eq!(o.shown_selection(&book), Some(2));
+        o.handle_key(delete, &mut book, &keymap);
+        assert!(o.confirm_delete);
+    }
+
+    #[test]
+    fn requests_amounts_in_the_qr() {
+        let book = book();
+        let mut o = overlay(&book);
+        o.address.set("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(o.qr_data().unwrap(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        o.amount.set("150,000 sat");
+        assert_eq!(o.qr_data().unwrap(), "bitcoin:bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4?amount=0.0015");
+        // A bare number is in the display unit
+        o.unit = Unit::Mbtc;
+        o.amount.set("20");
+        assert_eq!(o.qr_data().unwrap(), "bitcoin:bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4?amount=0.02");
+        o.amount.set("0.000001");
+        assert_eq!(o.qr_data().unwrap_err(), "0.000001 mBTC is finer than a satoshi");
+        o.amount.set("0");
+        assert_eq!(o.qr_data().unwrap_err(), "the amount is zero");
+
+        let keymap = Keymap::defaults();
+        let mut book = book;
+        for _ in 0..2 {
+            o.handle_key(key(KeyCode::Tab), &mut book, &keymap);
+        }
+        assert_eq!(o.focus, Focus::Amount);
+        o.amount.clear();
+        o.handle_paste("1.5 mBTC");
+        assert_eq!(o.amount.value, "1.5 mBTC");
+        o.handle_key(key(KeyCode::BackTab), &mut book, &keymap);
+        assert_eq!(o.focus, Focus::Label);
+    }
+}
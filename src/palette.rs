@@ -0,0 +1,304 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::centered_rect;
+use crate::cli::{rpc_result, run_bitcoin_cli};
+use crate::file::AddressEntry;
+use crate::fuzzy::fuzzy_score;
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::privacy::Privacy;
+use crate::theme::Theme;
+
+// ===== Command palette =====
+
+/// What a palette entry does when chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PaletteTarget {
+    Action(Action),
+    /// Index into commands.json.
+    Command(usize),
+    /// An RPC method from `help`; shows its help text, never runs it.
+    Rpc(String),
+    /// An address book entry, opened in the address book.
+    Address(String),
+}
+
+/// What the palette asks the main loop to do after a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PaletteAction {
+    None,
+    Close,
+    Run(PaletteTarget),
+}
+
+struct PaletteEntry {
+    kind: &'static str,
+    title: String,
+    /// Extra text matched but not shown, e.g. the address behind a label.
+    hidden: String,
+    key: String,
+    target: PaletteTarget,
+}
+
+pub(crate) struct CommandPalette {
+    pub input: TextInput,
+    /// Index into the current matches.
+    pub selected: usize,
+    entries: Vec<PaletteEntry>,
+    /// RPC methods from `help`, fetched on first open.
+    rpc_methods: Option<Vec<String>>,
+}
+
+/// Method names from the output of `help`, skipping the `== Section ==` lines.
+fn parse_help(out: &str) -> Vec<String> {
+    out.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("=="))
+        .filter_map(|l| l.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+impl CommandPalette {
+    pub(crate) fn new() -> Self {
+        Self {
+            input: TextInput::new("", true),
+            selected: 0,
+            entries: Vec::new(),
+            rpc_methods: None,
+        }
+    }
+
+    /// Rebuilds the entries from the current commands and address book.
+    pub(crate) fn open(&mut self, commands: &[String], book: &[AddressEntry], keymap: &Keymap) {
+        self.input.clear();
+        self.selected = 0;
+        let methods = self
+            .rpc_methods
+            .get_or_insert_with(|| rpc_result(run_bitcoin_cli("help")).map(|o| parse_help(&o)).unwrap_or_default());
+
+        let actions = Action::all()
+            .filter(|&a| a.scope() == Scope::Main && a != Action::Palette)
+            .map(|a| PaletteEntry {
+                kind: "action",
+                title: a.label().to_string(),
+                hidden: String::new(),
+                key: keymap.keys(a),
+                target: PaletteTarget::Action(a),
+            });
+        let cmds = commands.iter().enumerate().map(|(i, c)| PaletteEntry {
+            kind: "command",
+            title: c.clone(),
+            hidden: String::new(),
+            key: String::new(),
+            target: PaletteTarget::Command(i),
+        });
+        // Choosing one only shows the method's help; it never calls it
+        let rpcs = methods.iter().map(|m| PaletteEntry {
+            kind: "rpc help",
+            title: format!("help for {m}"),
+            hidden: m.clone(),
+            key: String::new(),
+            target: PaletteTarget::Rpc(m.clone()),
+        });
+        let addresses = book.iter().map(|e| PaletteEntry {
+            kind: "address",
+            title: e.label.clone(),
+            hidden: e.address.clone(),
+            key: String::new(),
+            target: PaletteTarget::Address(e.address.clone()),
+        });
+        self.entries = actions.chain(cmds).chain(rpcs).chain(addresses).collect();
+    }
+
+    /// Entries matching the query, best first.
+    fn matches(&self) -> Vec<&PaletteEntry> {
+        let query = self.input.value.as_str();
+        // Score and length of the better matching text, title or hidden
+        let mut scored: Vec<(i64, usize, &PaletteEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|e| {
+                [&e.title, &e.hidden]
+                    .into_iter()
+                    .filter_map(|t| fuzzy_score(query, t).map(|s| (s, t.len())))
+                    .max_by_key(|&(s, len)| (s, std::cmp::Reverse(len)))
+                    .map(|(s, len)| (s, len, e))
+            })
+            .collect();
+        // Among equal scores the shorter text is the closer match
+        let by_length = !query.trim().is_empty();
+        scored.sort_by_key(|&(s, len, _)| (-s, if by_length { len } else { 0 }));
+        scored.into_iter().map(|(_, _, e)| e).collect()
+    }
+
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        self.input.insert_str(text);
+        self.selected = 0;
+    }
+
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> PaletteAction {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if keymap.action(Scope::Palette, key) == Some(Action::PaletteClose) {
+            return PaletteAction::Close;
+        }
+        match key.code {
+            KeyCode::Esc => return PaletteAction::Close,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                let n = self.matches().len();
+                self.selected = (self.selected + 1).min(n.saturating_sub(1));
+            }
+            KeyCode::Enter => {
+                return match self.matches().get(self.selected) {
+                    Some(e) => PaletteAction::Run(e.target.clone()),
+                    None => PaletteAction::None,
+                };
+            }
+            code if !ctrl && self.input.handle_key(code) => self.selected = 0,
+            _ => {}
+        }
+        PaletteAction::None
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(60, 60, size)
+    }
+
+    pub(crate) fn draw(&self, f: &mut Frame, size: Rect, privacy: Privacy, theme: &Theme) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(3)])
+            .split(area);
+
+        f.render_widget(
+            Paragraph::new(self.input.value.clone()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.focused())
+                    .title(" Command palette (↑/↓ select • Enter run • Esc close) "),
+            ),
+            rows[0],
+        );
+        let cursor_x = (rows[0].x + 1).saturating_add(self.input.cursor as u16);
+        f.set_cursor(cursor_x.min(rows[0].x + rows[0].width.saturating_sub(2)), rows[0].y + 1);
+
+        let matches = self.matches();
+        let width = rows[1].width.saturating_sub(2) as usize;
+        let items: Vec<ListItem> = matches
+            .iter()
+            .map(|e| {
+                let title = match (&e.target, e.title.is_empty()) {
+                    (PaletteTarget::Address(a), true) => privacy.address(a),
+                    (PaletteTarget::Address(a), false) => format!("{} — {}", e.title, privacy.address(a)),
+                    _ => e.title.clone(),
+                };
+                let left = format!("{:<9} {title}", e.kind);
+                let pad = width.saturating_sub(left.chars().count() + e.key.chars().count());
+                ListItem::new(Line::from(vec![
+                    Span::raw(left),
+                    Span::raw(" ".repeat(pad)),
+                    Span::styled(e.key.clone(), theme.muted()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.accent())
+                    .title(format!(" {}/{} ", matches.len(), self.entries.len())),
+            )
+            .highlight_style(theme.selected());
+        let mut state = ListState::default();
+        state.select((!matches.is_empty()).then_some(self.selected));
+        f.render_stateful_widget(list, rows[1], &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ctrl, key};
+
+    fn palette() -> CommandPalette {
+        let mut p = CommandPalette::new();
+        p.rpc_methods = Some(vec!["getblock".to_string(), "getblockcount".to_string(), "stop".to_string()]);
+        let commands = ["getblockcount".to_string(), "getwalletinfo".to_string()];
+        let book: Vec<AddressEntry> = serde_json::from_str(
+            r#"[{"created_at": "2024-01-01T00:00:00Z", "address": "bc1qrentxyz", "label": "rent"}]"#,
+        )
+        .unwrap();
+        p.open(&commands, &book, &Keymap::defaults());
+        p
+    }
+
+    fn typed(p: &mut CommandPalette, text: &str) -> Vec<(&'static str, String)> {
+        p.input.clear();
+        p.handle_paste(text);
+        p.matches().iter().map(|e| (e.kind, e.title.clone())).collect()
+    }
+
+    #[test]
+    fn parses_help_output() {
+        let out = "== Blockchain ==\ngetbestblockhash\ngetblock \"blockhash\" ( verbosity )\n\n== Wallet ==\nabandontransaction \"txid\"\n";
+        assert_eq!(parse_help(out), ["getbestblockhash", "getblock", "abandontransaction"]);
+    }
+
+    #[test]
+    fn lists_every_kind() {
+        let mut p = palette();
+        let all = typed(&mut p, "");
+        assert!(all.contains(&("action", "Quit".to_string())));
+        assert!(!all.iter().any(|(_, t)| t == Action::Palette.label() || t == Action::BookCopy.label()));
+        assert!(all.contains(&("command", "getwalletinfo".to_string())));
+        assert!(all.contains(&("rpc help", "help for stop".to_string())));
+        assert!(all.contains(&("address", "rent".to_string())));
+    }
+
+    #[test]
+    fn ranks_matches() {
+        let mut p = palette();
+        // Same score for the command and the method; the shorter title wins
+        assert_eq!(
+            typed(&mut p, "getblockc"),
+            [("command", "getblockcount".to_string()), ("rpc help", "help for getblockcount".to_string())]
+        );
+        assert_eq!(typed(&mut p, "getblock")[0], ("rpc help", "help for getblock".to_string()));
+        assert_eq!(typed(&mut p, "QUIT")[0], ("action", "Quit".to_string()));
+        // Addresses match on the hidden address as well as the label
+        assert_eq!(typed(&mut p, "bc1qrent"), [("address", "rent".to_string())]);
+        assert!(typed(&mut p, "zzzz").is_empty());
+    }
+
+    #[test]
+    fn keys_select_and_run() {
+        let mut p = palette();
+        typed(&mut p, "stop");
+        assert_eq!(p.handle_key(key(KeyCode::Enter), &Keymap::defaults()), PaletteAction::Run(PaletteTarget::Rpc("stop".to_string())));
+
+        typed(&mut p, "getblockc");
+        let keymap = Keymap::defaults();
+        p.handle_key(key(KeyCode::Down), &keymap);
+        p.handle_key(key(KeyCode::Down), &keymap);
+        assert_eq!(p.selected, 1);
+        assert_eq!(p.handle_key(key(KeyCode::Enter), &keymap), PaletteAction::Run(PaletteTarget::Rpc("getblockcount".to_string())));
+        p.handle_key(key(KeyCode::Backspace), &keymap);
+        assert_eq!(p.selected, 0);
+
+        assert_eq!(p.handle_key(key(KeyCode::Esc), &keymap), PaletteAction::Close);
+        let ctrl_x = ctrl('x');
+        assert_eq!(p.handle_key(ctrl_x, &keymap), PaletteAction::Close);
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+
+// ===== Config & data locations =====
+
+const APP_DIR: &str = "bitatui";
+pub const CONFIG_DIR_ENV: &str = "BITATUI_CONFIG_DIR";
+pub const DATA_DIR_ENV: &str = "BITATUI_DATA_DIR";
+
+const COMMANDS_FILE: &str = "commands.json";
+const SETTINGS_FILE: &str = "config.json";
+const KEYMAP_FILE: &str = "keys.json";
+const ADDRESS_BOOK_FILE: &str = "addresses.json";
+const LABELS_FILE: &str = "labels.jsonl";
+const FIAT_CACHE_FILE: &str = "fiat-rate.json";
+
+/// Where bitatui keeps its files. Config (commands.json) lives in the
+/// platform config dir, user data (address book, labels) in the data dir,
+/// e.g. ~/.config/bitatui and ~/.local/share/bitatui on Linux.
+#[derive(Debug, Clone)]
+pub(crate) struct Paths {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+}
+
+impl Paths {
+    /// Resolves each dir from the command line flag, then the environment
+    /// variable, then the platform default. Falls back to the working
+    /// directory if the platform has no such dir.
+    pub(crate) fn resolve(config_flag: Option<PathBuf>, data_flag: Option<PathBuf>) -> Paths {
+        let pick = |flag: Option<PathBuf>, env: &str, default: Option<PathBuf>| {
+            flag.or_else(|| std::env::var_os(env).map(PathBuf::from))
+                .or_else(|| default.map(|d| d.join(APP_DIR)))
+                .unwrap_or_else(|| PathBuf::from("."))
+        };
+        Paths {
+            config_dir: pick(config_flag, CONFIG_DIR_ENV, dirs::config_dir()),
+            data_dir: pick(data_flag, DATA_DIR_ENV, dirs::data_dir()),
+        }
+    }
+
+    pub(crate) fn commands(&self) -> PathBuf {
+        self.config_dir.join(COMMANDS_FILE)
+    }
+
+    pub(crate) fn settings(&self) -> PathBuf {
+        self.config_dir.join(SETTINGS_FILE)
+    }
+
+    pub(crate) fn keymap(&self) -> PathBuf {
+        self.config_dir.join(KEYMAP_FILE)
+    }
+
+    pub(crate) fn address_book(&self) -> PathBuf {
+        self.data_dir.join(ADDRESS_BOOK_FILE)
+    }
+
+    pub(crate) fn labels(&self) -> PathBuf {
+        self.data_dir.join(LABELS_FILE)
+    }
+
+    pub(crate) fn fiat_cache(&self) -> PathBuf {
+        self.data_dir.join(FIAT_CACHE_FILE)
+    }
+
+    /// Creates the dirs and brings files left in the working directory by
+    /// older versions into place. Existing files are never overwritten.
+    /// The address book and labels are moved, so no plaintext copy stays
+    /// behind once the book is encrypted; commands.json is only copied.
+    /// Returns what was migrated.
+    pub(crate) fn prepare(&self) -> Result<Vec<String>, String> {
+        self.prepare_from(Path::new("."))
+    }
+
+    /// An addresses.json an older version left in the working directory,
+    /// unless it is the book itself.
+    pub(crate) fn legacy_address_book(&self) -> Option<PathBuf> {
+        self.legacy_address_book_in(Path::new("."))
+    }
+
+    fn legacy_address_book_in(&self, legacy_dir: &Path) -> Option<PathBuf> {
+        let legacy = legacy_dir.join(ADDRESS_BOOK_FILE).canonicalize().ok()?;
+        (self.address_book().canonicalize().ok() != Some(legacy.clone())).then_some(legacy)
+    }
+
+    fn prepare_from(&self, legacy_dir: &Path) -> Result<Vec<String>, String> {
+        for dir in [&self.config_dir, &self.data_dir] {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+
+        let mut migrated = Vec::new();
+        for (name, target, keep) in [
+            (COMMANDS_FILE, self.commands(), true),
+            (ADDRESS_BOOK_FILE, self.address_book(), false),
+            (LABELS_FILE, self.labels(), false),
+        ] {
+            let legacy = legacy_dir.join(name);
+            if target.exists() || !legacy.is_file() {
+                continue;
+            }
+            if keep {
+                std::fs::copy(&legacy, &target).map_err(|e| format!("{}: {e}", target.display()))?;
+                migrated.push(format!("{name} -> {}", target.display()));
+            } else {
+                move_file(&legacy, &target)?;
+                migrated.push(format!("{name} -> {} (moved)", target.display()));
+            }
+        }
+        Ok(migrated)
+    }
+}
+
+/// Renames `from` to `to`, or copies it across file systems and removes
+/// the original once the copy reads back the same.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let data = std::fs::read(from).map_err(|e| format!("{}: {e}", from.display()))?;
+    std::fs::write(to, &data).map_err(|e| format!("{}: {e}", to.display()))?;
+    if std::fs::read(to).ok().as_deref() != Some(data.as_slice()) {
+        let _ = std::fs::remove_file(to);
+        return Err(format!("{}: copy did not verify, original kept", to.display()));
+    }
+    std::fs::remove_file(from).map_err(|e| format!("{}: copied, but not removed: {e}", from.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn flags_win() {
+        let paths = Paths::resolve(Some(PathBuf::from("/c")), Some(PathBuf::from("/d")));
+        assert_eq!(paths.commands(), Path::new("/c/commands.json"));
+        assert_eq!(paths.settings(), Path::new("/c/config.json"));
+        assert_eq!(paths.keymap(), Path::new("/c/keys.json"));
+        assert_eq!(paths.address_book(), Path::new("/d/addresses.json"));
+        assert_eq!(paths.labels(), Path::new("/d/labels.jsonl"));
+        assert_eq!(paths.fiat_cache(), Path::new("/d/fiat-rate.json"));
+    }
+
+    #[test]
+    fn migrates_without_overwriting() {
+        let dir = temp_dir("paths-migrate");
+        let legacy = dir.join("cwd");
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join(COMMANDS_FILE), "[\"getblockcount\"]").unwrap();
+        std::fs::write(legacy.join(ADDRESS_BOOK_FILE), "[]").unwrap();
+        let paths = Paths::resolve(Some(dir.join("config")), Some(dir.join("data")));
+        std::fs::create_dir_all(&paths.data_dir).unwrap();
+        std::fs::write(paths.address_book(), "[{}]").unwrap();
+
+        std::fs::write(legacy.join(LABELS_FILE), "{}\n").unwrap();
+
+        let migrated = paths.prepare_from(&legacy).unwrap();
+        assert_eq!(
+            migrated,
+            [
+                format!("commands.json -> {}", paths.commands().display()),
+                format!("labels.jsonl -> {} (moved)", paths.labels().display()),
+            ]
+        );
+        assert_eq!(std::fs::read_to_string(paths.commands()).unwrap(), "[\"getblockcount\"]");
+        assert_eq!(std::fs::read_to_string(paths.labels()).unwrap(), "{}\n");
+        // An existing book is not overwritten, and its legacy copy is left alone
+        assert_eq!(std::fs::read_to_string(paths.address_book()).unwrap(), "[{}]");
+        assert!(legacy.join(ADDRESS_BOOK_FILE).exists());
+        // Commands are copied, private data is moved
+        assert!(legacy.join(COMMANDS_FILE).exists());
+        assert!(!legacy.join(LABELS_FILE).exists());
+        assert!(paths.prepare_from(&legacy).unwrap().is_empty());
+
+        assert_eq!(paths.legacy_address_book_in(&legacy), legacy.join(ADDRESS_BOOK_FILE).canonicalize().ok());
+        std::fs::remove_file(paths.address_book()).unwrap();
+        assert_eq!(paths.prepare_from(&legacy).unwrap().len(), 1);
+        assert_eq!(paths.legacy_address_book_in(&legacy), None);
+        assert_eq!(paths.legacy_address_book_in(&paths.data_dir), None);
+        assert_eq!(std::fs::read_to_string(paths.address_book()).unwrap(), "[]");
+        assert!(!legacy.join(ADDRESS_BOOK_FILE).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
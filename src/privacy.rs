@@ -0,0 +1,334 @@
+use serde::{Deserialize, Serialize};
+
+// ===== Privacy mode =====
+
+/// Shown in place of a hidden value.
+const MASK: &str = "•••••";
+
+/// JSON fields holding wallet amounts in bitcoin-cli output.
+pub(crate) const AMOUNT_KEYS: &[&str] = &[
+    "amount",
+    "balance",
+    "unconfirmed_balance",
+    "immature_balance",
+    "fee",
+    "trusted",
+    "untrusted_pending",
+    "immature",
+    "used",
+    "value",
+    "total_amount",
+];
+/// Fields holding addresses or descriptors (which give the addresses away).
+const ADDRESS_KEYS: &[&str] = &["address", "addresses", "desc", "parent_descs", "scriptPubKey"];
+/// Fields holding transaction ids, and the blocks that confirm them.
+const TXID_KEYS: &[&str] = &["txid", "txids", "wtxid", "walletconflicts", "replaced_by_txid", "replaces_txid", "blockhash"];
+/// Methods listing addresses and amounts in arrays without keys, with the
+/// position of each in the innermost arrays, e.g. `[address, amount, label]`.
+const POSITIONAL_METHODS: &[(&str, usize, usize)] = &[("listaddressgroupings", 0, 1)];
+/// Methods whose whole result is a single amount.
+pub(crate) const AMOUNT_METHODS: &[&str] = &["getbalance", "getunconfirmedbalance", "getreceivedbyaddress", "getreceivedbylabel"];
+
+/// The `privacy` section of config.json.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PrivacySettings {
+    /// Start with privacy mode on.
+    pub start_hidden: bool,
+    /// Also hide addresses and descriptors while privacy mode is on.
+    pub addresses: bool,
+    /// Also hide txids while privacy mode is on.
+    pub txids: bool,
+}
+
+/// What is hidden right now. `h` toggles `on`; the rest comes from the
+/// settings and only applies while it is on.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Privacy {
+    pub on: bool,
+    pub addresses: bool,
+    pub txids: bool,
+}
+
+impl Privacy {
+    pub(crate) fn new(settings: PrivacySettings) -> Self {
+        Self {
+            on: settings.start_hidden,
+            addresses: settings.addresses,
+            txids: settings.txids,
+        }
+    }
+
+    pub(crate) fn hides_addresses(self) -> bool {
+        self.on && self.addresses
+    }
+
+    pub(crate) fn hides_txids(self) -> bool {
+        self.on && self.txids
+    }
+
+    /// Hides the figures in a formatted amount but keeps signs and labels,
+    /// e.g. `-1,234 sat` becomes `••••• sat`.
+    pub(crate) fn amount(self, text: &str) -> String {
+        if !self.on {
+            return text.to_string();
+        }
+        text.split(' ')
+            .map(|w| if w.chars().any(|c| c.is_ascii_digit()) { MASK } else { w })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub(crate) fn address(self, address: &str) -> String {
+        if self.hides_addresses() { MASK.to_string() } else { address.to_string() }
+    }
+
+    /// Masks the hidden fields in `bitcoin-cli` output. Amounts already
+    /// shown in another unit (`"0.5 mBTC"`) keep their unit.
+    pub(crate) fn output(self, command: &str, lines: &[String]) -> Vec<String> {
+        if !self.on {
+            return lines.to_vec();
+        }
+        if let Some(only) = bare_amount(command, lines) {
+            return lines.iter().map(|l| if l.trim().is_empty() { l.clone() } else { self.amount(only) }).collect();
+        }
+        let mask_amount = |value: &str| match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(text) => Some(format!("\"{}\"", self.amount(text))),
+            None => value.parse::<f64>().is_ok().then(|| MASK.to_string()),
+        };
+        if let Some(masked) = map_items(command, lines, |item, value| match item {
+            Item::Amount => mask_amount(value),
+            Item::Address => (self.addresses && value.starts_with('"')).then(|| format!("\"{MASK}\"")),
+        }) {
+            return masked;
+        }
+        map_fields(lines, |field, value| {
+            let quoted = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+            if AMOUNT_KEYS.contains(&field) {
+                return mask_amount(value);
+            }
+            let hide = (self.addresses && ADDRESS_KEYS.contains(&field)) || (self.txids && TXID_KEYS.contains(&field));
+            (hide && quoted.is_some()).then(|| format!("\"{MASK}\""))
+        })
+    }
+}
+
+/// The value of a method whose whole result is one amount, like
+/// `getbalance`; None for other methods and for errors.
+pub(crate) fn bare_amount<'a>(command: &str, lines: &'a [String]) -> Option<&'a str> {
+    let method = command.split_whitespace().next().unwrap_or_default();
+    let mut values = lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty());
+    match (values.next(), values.next()) {
+        (Some(only), None) if AMOUNT_METHODS.contains(&method) => {
+            let number = only.split(' ').next().unwrap_or_default().replace(',', "");
+            number.parse::<f64>().is_ok().then_some(only)
+        }
+        _ => None,
+    }
+}
+
+/// Rewrites field values in `bitcoin-cli` output, which is pretty printed
+/// with one value per line. Keys are tracked through nested objects and
+/// arrays, so array items are passed with their parent key. `f` gets the
+/// key and the value without its trailing comma, and returns the new value.
+pub(crate) fn map_fields(lines: &[String], mut f: impl FnMut(&str, &str) -> Option<String>) -> Vec<String> {
+    let mut parents: Vec<String> = Vec::new();
+    lines
+        .iter()
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with(['}', ']']) {
+                parents.pop();
+                return line.clone();
+            }
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let (key, value) = match split_field(trimmed) {
+                Some((k, v)) => (Some(k), v),
+                None => (None, trimmed),
+            };
+            if value.ends_with(['{', '[']) {
+                let parent = key.or(parents.last().map(|p| p.as_str())).unwrap_or_default().to_string();
+                parents.push(parent);
+                return line.clone();
+            }
+            let Some(field) = key.or(parents.last().map(|p| p.as_str())) else {
+                return line.clone();
+            };
+            let (bare, comma) = match value.strip_suffix(',') {
+                Some(v) => (v, ","),
+                None => (value, ""),
+            };
+            match (f(field, bare), key) {
+                (None, _) => line.clone(),
+                (Some(new), Some(k)) => format!("{indent}\"{k}\": {new}{comma}"),
+                (Some(new), None) => format!("{indent}{new}{comma}"),
+            }
+        })
+        .collect()
+}
+
+/// What a positional item holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Item {
+    Address,
+    Amount,
+}
+
+/// Like `map_fields` for the methods in `POSITIONAL_METHODS`, passing the
+/// addresses and amounts by their position in the innermost arrays. None
+/// for other methods.
+pub(crate) fn map_items(
+    command: &str,
+    lines: &[String],
+    mut f: impl FnMut(Item, &str) -> Option<String>,
+) -> Option<Vec<String>> {
+    let method = command.split_whitespace().next().unwrap_or_default();
+    let &(_, address_at, amount_at) = POSITIONAL_METHODS.iter().find(|(m, _, _)| *m == method)?;
+    let (mut depth, mut index) = (0, 0);
+    let out = lines
+        .iter()
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.ends_with('[') {
+                (depth, index) = (depth + 1, 0);
+                return line.clone();
+            }
+            if trimmed.starts_with(']') {
+                depth -= 1;
+                return line.clone();
+            }
+            // Errors and anything else outside the arrays stay as they are
+            if depth == 0 || trimmed.is_empty() || trimmed.starts_with(['{', '}']) || split_field(trimmed).is_some() {
+                return line.clone();
+            }
+            let item = match index {
+                i if i == address_at => Some(Item::Address),
+                i if i == amount_at => Some(Item::Amount),
+                _ => None,
+            };
+            index += 1;
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let (bare, comma) = match trimmed.strip_suffix(',') {
+                Some(v) => (v, ","),
+                None => (trimmed, ""),
+            };
+            match item.and_then(|item| f(item, bare)) {
+                Some(new) => format!("{indent}{new}{comma}"),
+                None => line.clone(),
+            }
+        })
+        .collect();
+    Some(out)
+}
+
+/// Splits `"key": value` into its key and value.
+fn split_field(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('"')?;
+    let end = rest.find("\": ")?;
+    Some((&rest[..end], rest[end + 3..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    const ALL: Privacy = Privacy { on: true, addresses: true, txids: true };
+
+    #[test]
+    fn amounts_keep_units_and_signs() {
+        assert_eq!(ALL.amount("-1,234 sat"), "••••• sat");
+        assert_eq!(ALL.amount("0.5 BTC"), "••••• BTC");
+        assert_eq!(Privacy::default().amount("0.5 BTC"), "0.5 BTC");
+    }
+
+    #[test]
+    fn address_groupings() {
+        let out = lines(
+            r#"[
+  [
+    [
+      "bc1qfirst",
+      0.5,
+      "savings"
+    ],
+    [
+      "bc1qchange",
+      0.00001000
+    ]
+  ]
+]"#,
+        );
+        let amounts_only = Privacy { on: true, addresses: false, txids: false };
+        let masked = amounts_only.output("listaddressgroupings", &out);
+        assert_eq!(masked[4], "      •••••,");
+        assert_eq!(masked[5], r#"      "savings""#);
+        assert_eq!(masked[9], "      •••••");
+        assert_eq!(masked[3], r#"      "bc1qfirst","#);
+        let masked = ALL.output("listaddressgroupings", &out);
+        assert_eq!([masked[3].as_str(), masked[8].as_str()], [r#"      "•••••","#, r#"      "•••••","#]);
+        assert_eq!(masked[5], r#"      "savings""#);
+        // Errors pass through
+        let error = lines("error code: -18\nerror message:\nNo wallet is loaded.");
+        assert_eq!(ALL.output("listaddressgroupings", &error), error);
+        // Converted amounts keep their unit
+        let sats = crate::units::Unit::Sats.output("listaddressgroupings", &out, None);
+        assert_eq!(sats[4], r#"      "50,000,000 sat","#);
+        assert_eq!(amounts_only.output("listaddressgroupings", &sats)[4], r#"      "••••• sat","#);
+    }
+
+    #[test]
+    fn nested_output_fields() {
+        let out = lines(
+            r#"[
+  {
+    "address": "bc1qxyz",
+    "txids": [
+      "aa11"
+    ],
+    "amount": 0.5,
+    "confirmations": 12,
+    "label": "rent 2024"
+  }
+]"#,
+        );
+        let masked = ALL.output("listreceivedbyaddress", &out).join("\n");
+        assert_eq!(
+            masked,
+            r#"[
+  {
+    "address": "•••••",
+    "txids": [
+      "•••••"
+    ],
+    "amount": •••••,
+    "confirmations": 12,
+    "label": "rent 2024"
+  }
+]"#
+        );
+        let amounts_only = Privacy { on: true, addresses: false, txids: false };
+        let masked = amounts_only.output("listreceivedbyaddress", &out);
+        assert_eq!(masked[2], r#"    "address": "bc1qxyz","#);
+        assert_eq!(masked[4], r#"      "aa11""#);
+        assert_eq!(masked[6], r#"    "amount": •••••,"#);
+    }
+
+    #[test]
+    fn bare_amount_results() {
+        assert_eq!(ALL.output("getbalance", &lines("1.23000000\n")), ["•••••"]);
+        assert_eq!(ALL.output("getblockcount", &lines("840000")), ["840000"]);
+        assert_eq!(Privacy::default().output("getbalance", &lines("1.2")), ["1.2"]);
+    }
+
+    #[test]
+    fn addresses_and_txids_only_when_on() {
+        assert_eq!(ALL.address("bc1q"), "•••••");
+        assert!(!Privacy { on: false, ..ALL }.hides_addresses());
+        assert!(!Privacy { on: false, ..ALL }.hides_txids());
+        assert_eq!(Privacy { on: false, ..ALL }.address("bc1q"), "bc1q");
+    }
+}
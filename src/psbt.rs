@@ -0,0 +1,493 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use bitcoin::Psbt;
+use bitcoin::hex::FromHex;
+use core::str::FromStr;
+use qrcode::EcLevel;
+
+use crate::fiat::{FiatRate, with_fiat};
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::privacy::Privacy;
+use crate::qr::{QrSize, render_fitting};
+use crate::units::Unit;
+use crate::ur::{PSBT_TYPE, UrDecoder, UrEncoder, cbor_bytes, cbor_unwrap_bytes};
+use crate::{centered_rect, copy_to_clipboard};
+use crate::theme::Theme;
+
+// ===== PSBT animated QR (BC-UR) =====
+
+const FPS_RANGE: (u32, u32) = (1, 10);
+const FRAGMENT_STEPS: [usize; 7] = [30, 60, 100, 150, 200, 300, 500];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PsbtMode {
+    /// Show a PSBT as an animated ur:crypto-psbt QR.
+    Show,
+    /// Collect UR parts pasted from a scanner.
+    Import,
+}
+
+pub(crate) struct PsbtScreen {
+    pub mode: PsbtMode,
+    pub input: TextInput,
+    pub psbt: Option<Psbt>,
+    pub encoder: Option<UrEncoder>,
+    pub decoder: UrDecoder,
+    pub fps: u32,
+    /// Index into `FRAGMENT_STEPS`.
+    pub fragment: usize,
+    pub started: Instant,
+    pub status: String,
+    /// Where imported PSBTs are saved.
+    pub save_dir: PathBuf,
+    pub unit: Unit,
+    pub fiat: Option<FiatRate>,
+}
+
+/// Reads a PSBT given as base64, hex, or a path to a binary/base64 file.
+fn load_psbt(text: &str) -> Result<Psbt, String> {
+    let text = text.trim();
+    if let Ok(p) = Psbt::from_str(text) {
+        return Ok(p);
+    }
+    if let Ok(bytes) = Vec::<u8>::from_hex(text) {
+        return Psbt::deserialize(&bytes).map_err(|e| e.to_string());
+    }
+    let path = Path::new(text);
+    if path.is_file() {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        return match Psbt::deserialize(&data) {
+            Ok(p) => Ok(p),
+            Err(_) => Psbt::from_str(String::from_utf8_lossy(&data).trim()).map_err(|e| e.to_string()),
+        };
+    }
+    Err("not a PSBT (base64, hex or file path)".to_string())
+}
+
+fn summary(psbt: &Psbt, unit: Unit, fiat: Option<&FiatRate>, privacy: Privacy) -> String {
+    let fee = psbt
+        .fee()
+        .map(|f| format!("fee {}", privacy.amount(&with_fiat(unit.format(f), f.to_sat() as i64, fiat))))
+        .unwrap_or_else(|_| "fee unknown".to_string());
+    format!(
+        "{} input(s), {} output(s), {fee}, {} bytes",
+        psbt.inputs.len(),
+        psbt.outputs.len(),
+        psbt.serialize().len()
+    )
+}
+
+impl PsbtScreen {
+    pub(crate) fn new(save_dir: PathBuf) -> Self {
+        Self {
+            mode: PsbtMode::Show,
+            input: TextInput::new("", false),
+            psbt: None,
+            encoder: None,
+            decoder: UrDecoder::default(),
+            fps: 4,
+            fragment: 3,
+            started: Instant::now(),
+            status: String::new(),
+            save_dir,
+            unit: Unit::Btc,
+            fiat: None,
+        }
+    }
+
+    fn encode(&mut self) {
+        self.encoder = self
+            .psbt
+            .as_ref()
+            .map(|p| UrEncoder::new(PSBT_TYPE, &cbor_bytes(&p.serialize()), FRAGMENT_STEPS[self.fragment]));
+        self.started = Instant::now();
+    }
+
+    fn load_input(&mut self) {
+        match load_psbt(&self.input.value) {
+            Ok(p) => {
+                self.status.clear();
+                self.psbt = Some(p);
+                self.encode();
+            }
+            Err(e) => self.status = e,
+        }
+    }
+
+    /// Feeds one or more whitespace separated UR parts to the decoder.
+    fn receive_parts(&mut self, text: &str) {
+        for part in text.split_whitespace() {
+            if let Err(e) = self.decoder.receive(part) {
+                self.status = format!("Rejected part: {e}");
+                return;
+            }
+        }
+        let (have, total) = self.decoder.progress();
+        self.status = match self.decoder.message().map(cbor_unwrap_bytes) {
+            Some(Ok(bytes)) => match Psbt::deserialize(&bytes) {
+                Ok(p) => {
+                    self.psbt = Some(p);
+                    "PSBT received".to_string()
+                }
+                Err(e) => format!("Received data is not a PSBT: {e}"),
+            },
+            Some(Err(e)) => format!("Bad UR body: {e}"),
+            None => format!("Received {have}/{total} fragments — keep scanning"),
+        };
+    }
+
+    fn save_imported(&mut self) {
+        let Some(p) = &self.psbt else {
+            return;
+        };
+        let name = format!("psbt-{}.psbt", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let path = self.save_dir.join(name);
+        self.status = match crate::file::write_atomic(&path, &p.serialize()) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => format!("{}: {e}", path.display()),
+        };
+    }
+
+    /// Pasted text: UR parts in import mode, else the PSBT itself.
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        match self.mode {
+            PsbtMode::Import => self.receive_parts(text),
+            PsbtMode::Show => {
+                self.input.set(text.trim());
+                self.load_input();
+            }
+        }
+    }
+
+    /// Handles a key. Returns false when the screen should close.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match (keymap.action(Scope::Psbt, key), key.code) {
+            (Some(Action::PsbtClose), _) | (_, KeyCode::Esc) => return false,
+            (Some(Action::PsbtCopy), _) => {
+                if let Some(p) = &self.psbt {
+                    self.status = match copy_to_clipboard(&p.to_string()) {
+                        Ok(()) => "PSBT (base64) copied".to_string(),
+                        Err(e) => format!("Copy failed: {e}"),
+                    };
+                }
+            }
+            (Some(Action::PsbtSave), _) => self.save_imported(),
+            (Some(Action::PsbtShowImported), _) if self.psbt.is_some() => {
+                self.mode = PsbtMode::Show;
+                self.encode();
+            }
+            (Some(Action::PsbtReset), _) => {
+                self.decoder = UrDecoder::default();
+                self.status = "Import reset".to_string();
+            }
+            (_, KeyCode::Tab | KeyCode::BackTab) => {
+                self.mode = match self.mode {
+                    PsbtMode::Show => PsbtMode::Import,
+                    PsbtMode::Import => PsbtMode::Show,
+                };
+                self.input.clear();
+                self.status.clear();
+            }
+            (_, KeyCode::Up) => self.fps = (self.fps + 1).min(FPS_RANGE.1),
+            (_, KeyCode::Down) => self.fps = self.fps.saturating_sub(1).max(FPS_RANGE.0),
+            (_, KeyCode::PageUp) if self.fragment + 1 < FRAGMENT_STEPS.len() => {
+                self.fragment += 1;
+                self.encode();
+            }
+            (_, KeyCode::PageDown) if self.fragment > 0 => {
+                self.fragment -= 1;
+                self.encode();
+            }
+            (_, KeyCode::Enter) => {
+                let text = self.input.value.clone();
+                match self.mode {
+                    PsbtMode::Show => self.load_input(),
+                    PsbtMode::Import => {
+                        self.receive_parts(&text);
+                        self.input.clear();
+                    }
+                }
+            }
+            (_, code) if !ctrl => {
+                self.input.handle_key(code);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(80, 90, size)
+    }
+
+    /// The PSBT spells out every input and output, so it is hidden along
+    /// with addresses or txids.
+    fn hides_psbt(privacy: Privacy) -> bool {
+        privacy.hides_addresses() || privacy.hides_txids()
+    }
+
+    pub(crate) fn draw(&self, f: &mut Frame, size: Rect, keymap: &Keymap, privacy: Privacy, theme: &Theme) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+        let title = match self.mode {
+            PsbtMode::Show => " PSBT → animated QR (ur:crypto-psbt) • Tab = import ",
+            PsbtMode::Import => " Import UR parts from scanner • Tab = show ",
+        };
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(title);
+        if !self.status.is_empty() {
+            outer = outer.title_bottom(Span::styled(format!(" {} ", self.status), theme.warn()));
+        } else if let Some(p) = self.psbt.as_ref().filter(|_| self.mode == PsbtMode::Show) {
+            outer = outer.title_bottom(Span::styled(format!(" {} ", summary(p, self.unit, self.fiat.as_ref(), privacy)), theme.ok()));
+        }
+        f.render_widget(outer, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(3), Constraint::Length(1), Constraint::Min(5)])
+            .split(area);
+
+        let input_title = match self.mode {
+            PsbtMode::Show => " PSBT: base64, hex or file path (Enter = load, paste works) ".to_string(),
+            PsbtMode::Import => match keymap.hint(Action::PsbtReset) {
+                Some(reset) => format!(" UR part(s) (Enter or paste • {reset}) "),
+                None => " UR part(s) (Enter or paste) ".to_string(),
+            },
+        };
+        let shown = if Self::hides_psbt(privacy) && !self.input.value.is_empty() {
+            privacy.address(&self.input.value)
+        } else {
+            self.input.value.clone()
+        };
+        f.render_widget(
+            Paragraph::new(shown).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.focused())
+                    .title(input_title),
+            ),
+            rows[0],
+        );
+        let cursor_x = (rows[0].x + 1).saturating_add(self.input.cursor as u16);
+        f.set_cursor(cursor_x.min(rows[0].x + rows[0].width.saturating_sub(2)), rows[0].y + 1);
+
+        match self.mode {
+            PsbtMode::Show => self.draw_animation(f, rows[1], rows[2], keymap, privacy, theme),
+            PsbtMode::Import => self.draw_import(f, rows[1], rows[2], privacy, theme),
+        }
+    }
+
+    fn draw_animation(
+        &self,
+        f: &mut Frame,
+        info: Rect,
+        qr_area: Rect,
+        keymap: &Keymap,
+        privacy: Privacy,
+        theme: &Theme,
+    ) {
+        let Some(enc) = &self.encoder else {
+            f.render_widget(
+                Paragraph::new("Load a PSBT to show it as an animated QR for air-gapped signers.")
+                    .style(theme.muted()),
+                qr_area,
+            );
+            return;
+        };
+        let frame = (self.started.elapsed().as_millis() * self.fps as u128 / 1000) as u32;
+        let seq_num = frame + 1;
+        let part = enc.part(seq_num);
+        f.render_widget(
+            Paragraph::new(format!(
+                "part {} of {} (cycles forever) • {} fps (↑/↓) • fragment {} bytes (PgUp/PgDn){}",
+                seq_num,
+                enc.seq_len(),
+                self.fps,
+                FRAGMENT_STEPS[self.fragment],
+                keymap.hint(Action::PsbtCopy).map(|h| format!(" • {h}")).unwrap_or_default()
+            )),
+            info,
+        );
+        if Self::hides_psbt(privacy) {
+            f.render_widget(Paragraph::new("Hidden in privacy mode").style(theme.muted()), qr_area);
+            return;
+        }
+        let text = match render_fitting(&part, EcLevel::L, QrSize::Large, qr_area.width as usize, qr_area.height as usize) {
+            Ok((qr, _)) => qr,
+            Err(e) => format!("{e}\nLower the fragment size (PgDn)."),
+        };
+        f.render_widget(Paragraph::new(text), qr_area);
+    }
+
+    fn draw_import(&self, f: &mut Frame, info: Rect, body: Rect, privacy: Privacy, theme: &Theme) {
+        let (have, total) = self.decoder.progress();
+        let pct = (have * 100).checked_div(total).unwrap_or(0);
+        f.render_widget(
+            Paragraph::new(format!(
+                "{} • {have}/{total} fragments ({pct}%)",
+                self.decoder.ur_type.as_deref().map(|t| format!("ur:{t}")).unwrap_or_else(|| "waiting for first part".to_string())
+            )),
+            info,
+        );
+        let lines: Vec<Line> = match &self.psbt {
+            Some(p) if self.decoder.message().is_some() => vec![
+                Line::from(Span::styled(summary(p, self.unit, self.fiat.as_ref(), privacy), theme.ok())),
+                Line::from(""),
+                Line::from(if Self::hides_psbt(privacy) { "Hidden in privacy mode".to_string() } else { p.to_string() }),
+            ],
+            _ => vec![Line::from(
+                "Paste the ur:crypto-psbt/… parts your scanner produces, one or many at a time. \
+                 Parts can arrive in any order; mixed fountain parts fill in missed frames.",
+            )],
+        };
+        f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ctrl, key, temp_dir};
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hex::DisplayHex;
+    use bitcoin::transaction::Version;
+    use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness};
+
+    /// One input worth 100,000 sat paying 99,000 sat, so the fee is known.
+    fn psbt() -> Psbt {
+        let script = ScriptBuf::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::from_str(
+                    "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0",
+                )
+                .unwrap(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut { value: Amount::from_sat(99_000), script_pubkey: script.clone() }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut { value: Amount::from_sat(100_000), script_pubkey: script });
+        psbt
+    }
+
+    #[test]
+    fn loads_every_encoding() {
+        let p = psbt();
+        assert_eq!(load_psbt(&format!(" {p} ")).unwrap(), p);
+        assert_eq!(load_psbt(&p.serialize().to_lower_hex_string()).unwrap(), p);
+
+        let dir = temp_dir("psbt-load");
+        let binary = dir.join("a.psbt");
+        std::fs::write(&binary, p.serialize()).unwrap();
+        assert_eq!(load_psbt(binary.to_str().unwrap()).unwrap(), p);
+        let text = dir.join("a.txt");
+        std::fs::write(&text, format!("{p}\n")).unwrap();
+        assert_eq!(load_psbt(text.to_str().unwrap()).unwrap(), p);
+        assert_eq!(load_psbt("cHNidP8=nope").unwrap_err(), "not a PSBT (base64, hex or file path)");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn summarises() {
+        let p = psbt();
+        let len = p.serialize().len();
+        assert_eq!(
+            summary(&p, Unit::Sats, None, Privacy::default()),
+            format!("1 input(s), 1 output(s), fee 1,000 sat, {len} bytes")
+        );
+        let hidden = Privacy { on: true, ..Privacy::default() };
+        assert!(summary(&p, Unit::Sats, None, hidden).contains("fee ••••• sat"));
+
+        let mut unknown = p.clone();
+        unknown.inputs[0].witness_utxo = None;
+        assert!(summary(&unknown, Unit::Btc, None, Privacy::default()).contains("fee unknown"));
+    }
+
+    #[test]
+    fn shows_and_imports_parts() {
+        let p = psbt();
+        let mut show = PsbtScreen::new(PathBuf::new());
+        show.fragment = 0;
+        show.handle_paste(&p.to_string());
+        let encoder = show.encoder.as_ref().unwrap();
+        assert!(encoder.seq_len() > 1);
+        let parts: Vec<String> = (1..=encoder.seq_len() as u32).map(|n| encoder.part(n)).collect();
+
+        let dir = temp_dir("psbt-import");
+        let mut import = PsbtScreen::new(dir.clone());
+        let keymap = Keymap::defaults();
+        import.handle_key(key(KeyCode::Tab), &keymap);
+        assert_eq!(import.mode, PsbtMode::Import);
+        import.handle_paste(&parts[0]);
+        assert!(import.status.ends_with("fragments — keep scanning"), "{}", import.status);
+        import.handle_paste("ur:crypto-psbt/nonsense");
+        assert!(import.status.starts_with("Rejected part"));
+        import.handle_paste(&parts[1..].join("\n"));
+        assert_eq!(import.status, "PSBT received");
+        assert_eq!(import.psbt.as_ref(), Some(&p));
+
+        import.handle_key(ctrl('s'), &keymap);
+        let saved: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(std::fs::read(saved[0].as_ref().unwrap().path()).unwrap(), p.serialize());
+
+        import.handle_key(ctrl('l'), &keymap);
+        assert_eq!(import.mode, PsbtMode::Show);
+        assert!(import.encoder.is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keys_adjust_the_animation() {
+        let keymap = Keymap::defaults();
+        let mut s = PsbtScreen::new(PathBuf::new());
+        s.handle_paste(&psbt().to_string());
+        for _ in 0..20 {
+            s.handle_key(key(KeyCode::Up), &keymap);
+        }
+        assert_eq!(s.fps, FPS_RANGE.1);
+        for _ in 0..20 {
+            s.handle_key(key(KeyCode::Down), &keymap);
+        }
+        assert_eq!(s.fps, FPS_RANGE.0);
+
+        let parts = s.encoder.as_ref().unwrap().seq_len();
+        s.handle_key(key(KeyCode::PageDown), &keymap);
+        assert_eq!(s.fragment, 2);
+        assert!(s.encoder.as_ref().unwrap().seq_len() > parts);
+        for _ in 0..10 {
+            s.handle_key(key(KeyCode::PageUp), &keymap);
+        }
+        assert_eq!(s.fragment, FRAGMENT_STEPS.len() - 1);
+
+        s.handle_key(key(KeyCode::PageDown), &keymap);
+        let first = s.encoder.as_ref().unwrap().part(1);
+        s.handle_key(key(KeyCode::Tab), &keymap);
+        s.handle_paste(&first);
+        assert_eq!(s.decoder.progress().0, 1);
+        s.handle_key(ctrl('r'), &keymap);
+        assert_eq!((s.status.as_str(), s.decoder.progress().0), ("Import reset", 0));
+        assert!(!s.handle_key(key(KeyCode::Esc), &keymap));
+    }
+}
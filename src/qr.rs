@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use qrcode::render::{svg, unicode};
+use qrcode::{Color as QrColor, EcLevel, QrCode};
+
+// ===== QR rendering & export =====
+
+/// Terminal module size: half-height blocks (1 column × ½ row per module)
+/// or full blocks (2 columns × 1 row, closer to square on most fonts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QrSize {
+    Compact,
+    Large,
+}
+
+impl QrSize {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            QrSize::Compact => "compact",
+            QrSize::Large => "large",
+        }
+    }
+
+    pub(crate) fn next(self) -> QrSize {
+        match self {
+            QrSize::Compact => QrSize::Large,
+            QrSize::Large => QrSize::Compact,
+        }
+    }
+
+    /// Columns and rows needed for a code `modules` wide.
+    fn cells(self, modules: usize) -> (usize, usize) {
+        match self {
+            QrSize::Compact => (modules, modules.div_ceil(2)),
+            QrSize::Large => (modules * 2, modules),
+        }
+    }
+}
+
+pub(crate) fn ec_label(ec: EcLevel) -> &'static str {
+    match ec {
+        EcLevel::L => "L 7%",
+        EcLevel::M => "M 15%",
+        EcLevel::Q => "Q 25%",
+        EcLevel::H => "H 30%",
+    }
+}
+
+pub(crate) fn next_ec(ec: EcLevel) -> EcLevel {
+    match ec {
+        EcLevel::L => EcLevel::M,
+        EcLevel::M => EcLevel::Q,
+        EcLevel::Q => EcLevel::H,
+        EcLevel::H => EcLevel::L,
+    }
+}
+
+/// Bech32 is case-insensitive, and in upper case it fits QR alphanumeric
+/// mode, which gives a smaller code (BIP-173).
+fn qr_payload(data: &str) -> Option<String> {
+    let lower = data.to_ascii_lowercase();
+    let bech32 = lower.starts_with("bc1") || lower.starts_with("tb1") || lower.starts_with("bcrt1");
+    (bech32 && !data.contains(':')).then(|| data.to_ascii_uppercase())
+}
+
+fn render(code: &QrCode, size: QrSize) -> String {
+    match size {
+        QrSize::Compact => code.render::<unicode::Dense1x2>().quiet_zone(false).build(),
+        QrSize::Large => {
+            let width = code.width();
+            code.to_colors()
+                .chunks(width)
+                .map(|row| {
+                    row.iter()
+                        .map(|c| if *c == QrColor::Dark { "██" } else { "  " })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+/// Renders `data` to fit in `width` × `height` cells. If the chosen size
+/// and EC level do not fit it falls back to upper-case bech32, the compact
+/// size and lower EC levels, in that order. Returns the text and a note
+/// about any fallback, or an error when nothing fits.
+pub(crate) fn render_fitting(
+    data: &str,
+    ec: EcLevel,
+    size: QrSize,
+    width: usize,
+    height: usize,
+) -> Result<(String, Option<String>), String> {
+    let mut payloads = vec![(data.to_string(), false)];
+    if let Some(upper) = qr_payload(data) {
+        payloads.push((upper, true));
+    }
+    let mut levels = vec![ec];
+    levels.extend([EcLevel::Q, EcLevel::M, EcLevel::L].into_iter().filter(|l| (*l as u8) < (ec as u8)));
+    let mut sizes = vec![size];
+    if size == QrSize::Large {
+        sizes.push(QrSize::Compact);
+    }
+
+    let mut smallest: Option<(usize, usize)> = None;
+    for level in &levels {
+        for s in &sizes {
+            for (payload, upper) in &payloads {
+                let Ok(code) = QrCode::with_error_correction_level(payload.as_bytes(), *level) else {
+                    continue;
+                };
+                let (w, h) = s.cells(code.width());
+                if w <= width && h <= height {
+                    let mut notes = Vec::new();
+                    if *s != size {
+                        notes.push(s.label().to_string());
+                    }
+                    if level != &ec {
+                        notes.push(format!("EC {}", ec_label(*level)));
+                    }
+                    if *upper {
+                        notes.push("upper case".to_string());
+                    }
+                    let note = (!notes.is_empty()).then(|| notes.join(", "));
+                    return Ok((render(&code, *s), note));
+                }
+                if smallest.is_none_or(|(sw, sh)| w * h < sw * sh) {
+                    smallest = Some((w, h));
+                }
+            }
+        }
+    }
+    Err(match smallest {
+        Some((w, h)) => format!("Terminal too small for the QR code (needs {w}×{h}, have {width}×{height})"),
+        None => "Data too long for a QR code".to_string(),
+    })
+}
+
+/// Writes the QR for `data` as PNG or SVG, chosen by the file extension.
+/// Files get the standard 4-module quiet zone.
+pub(crate) fn export_qr(data: &str, ec: EcLevel, module_px: u32, path: &Path) -> Result<(), String> {
+    let payload = qr_payload(data).unwrap_or_else(|| data.to_string());
+    let code = QrCode::with_error_correction_level(payload.as_bytes(), ec).map_err(|e| e.to_string())?;
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => code
+            .render::<image::Luma<u8>>()
+            .module_dimensions(module_px, module_px)
+            .build()
+            .save(path)
+            .map_err(|e| format!("{}: {e}", path.display())),
+        "svg" => {
+            let image = code
+                .render::<svg::Color>()
+                .module_dimensions(module_px, module_px)
+                .build();
+            crate::file::write_atomic(path, image.as_bytes()).map_err(|e| format!("{}: {e}", path.display()))
+        }
+        _ => Err("use a .png or .svg file name".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn size(text: &str) -> (usize, usize) {
+        let lines: Vec<&str> = text.lines().collect();
+        (lines[0].chars().count(), lines.len())
+    }
+
+    #[test]
+    fn labels_and_cycles() {
+        assert_eq!(next_ec(next_ec(next_ec(next_ec(EcLevel::M)))), EcLevel::M);
+        assert_eq!(ec_label(EcLevel::Q), "Q 25%");
+        assert_eq!(QrSize::Large.next(), QrSize::Compact);
+        assert_eq!(QrSize::Compact.cells(29), (29, 15));
+        assert_eq!(QrSize::Large.cells(29), (58, 29));
+    }
+
+    #[test]
+    fn upper_cases_bech32_only() {
+        assert_eq!(qr_payload(ADDRESS).as_deref(), Some("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"));
+        assert_eq!(qr_payload("TB1QABC").as_deref(), Some("TB1QABC"));
+        assert_eq!(qr_payload("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"), None);
+        // A BIP-21 URI keeps its case-sensitive query
+        assert_eq!(qr_payload("bitcoin:bc1q?label=Rent"), None);
+    }
+
+    #[test]
+    fn renders_at_the_chosen_size() {
+        // 42 bytes at EC M is a version 3 code, 29 modules wide
+        let (text, note) = render_fitting(ADDRESS, EcLevel::M, QrSize::Compact, 100, 100).unwrap();
+        assert_eq!((size(&text), note), ((29, 15), None));
+        let (text, note) = render_fitting(ADDRESS, EcLevel::M, QrSize::Large, 58, 29).unwrap();
+        assert_eq!((size(&text), note), ((58, 29), None));
+    }
+
+    #[test]
+    fn falls_back_to_fit() {
+        let fit = |ec, qr_size, w, h| render_fitting(ADDRESS, ec, qr_size, w, h).map(|(t, n)| (size(&t), n));
+        assert_eq!(fit(EcLevel::M, QrSize::Large, 40, 40), Ok(((29, 15), Some("compact".to_string()))));
+        // At EC Q only alphanumeric mode keeps it at version 3
+        assert_eq!(fit(EcLevel::Q, QrSize::Compact, 29, 15), Ok(((29, 15), Some("upper case".to_string()))));
+        assert_eq!(
+            fit(EcLevel::H, QrSize::Large, 29, 15),
+            Ok(((29, 15), Some("compact, EC Q 25%, upper case".to_string())))
+        );
+
+        let err = fit(EcLevel::M, QrSize::Compact, 10, 5).unwrap_err();
+        assert!(err.starts_with("Terminal too small") && err.ends_with("have 10×5)"), "{err}");
+        assert_eq!(
+            render_fitting(&"x".repeat(3000), EcLevel::L, QrSize::Compact, 500, 500).unwrap_err(),
+            "Data too long for a QR code"
+        );
+    }
+
+    #[test]
+    fn exports_png_and_svg() {
+        let dir = temp_dir("qr-export");
+        let png = dir.join("a.PNG");
+        export_qr(ADDRESS, EcLevel::M, 4, &png).unwrap();
+        let data = std::fs::read(&png).unwrap();
+        assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+        // 29 modules plus a 4-module quiet zone each side, 4 px per module
+        assert_eq!(u32::from_be_bytes(data[16..20].try_into().unwrap()), (29 + 8) * 4);
+
+        let svg = dir.join("a.svg");
+        export_qr(ADDRESS, EcLevel::M, 4, &svg).unwrap();
+        assert!(std::fs::read_to_string(&svg).unwrap().contains("<svg"));
+        assert_eq!(export_qr(ADDRESS, EcLevel::M, 4, &dir.join("a.txt")).unwrap_err(), "use a .png or .svg file name");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
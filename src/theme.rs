@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+// ===== Color themes =====
+
+const BUILT_IN: [&str; 4] = ["dark", "light", "high-contrast", "monochrome"];
+
+/// Colors for every styled element. Draw code asks for styles through the
+/// helpers below rather than naming colors.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Theme {
+    pub name: String,
+    /// Screen background and default text.
+    pub background: Color,
+    pub text: Color,
+    /// Borders and titles of dialogs, focused fields.
+    pub accent: Color,
+    /// Headings in the help bar.
+    pub help: Color,
+    /// Selected list entries.
+    pub selected: Color,
+    /// Hints, unfocused fields, used entries.
+    pub muted: Color,
+    pub ok: Color,
+    pub warn: Color,
+    pub error: Color,
+    /// Watch-only markers, immature coins.
+    pub info: Color,
+    pub version: Color,
+    /// Shows selections in reverse video, for themes without colors.
+    pub reverse_selection: bool,
+}
+
+/// A user theme in config.json: a built-in theme to start from and the
+/// colors to change, as names (`yellow`, `dark-gray`) or `#rrggbb`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ThemeSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ok: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl Theme {
+    pub(crate) fn dark() -> Theme {
+        Theme {
+            name: "dark".to_string(),
+            background: Color::Reset,
+            text: Color::Reset,
+            accent: Color::Rgb(245, 200, 66),
+            help: Color::Rgb(255, 165, 0),
+            selected: Color::Yellow,
+            muted: Color::DarkGray,
+            ok: Color::Green,
+            warn: Color::Yellow,
+            error: Color::Red,
+            info: Color::Cyan,
+            version: Color::Rgb(180, 180, 180),
+            reverse_selection: false,
+        }
+    }
+
+    pub(crate) fn light() -> Theme {
+        Theme {
+            name: "light".to_string(),
+            background: Color::Rgb(250, 250, 245),
+            text: Color::Rgb(30, 30, 30),
+            accent: Color::Rgb(176, 112, 0),
+            help: Color::Rgb(176, 112, 0),
+            selected: Color::Rgb(0, 80, 170),
+            muted: Color::Rgb(120, 120, 120),
+            ok: Color::Rgb(0, 128, 0),
+            warn: Color::Rgb(170, 100, 0),
+            error: Color::Rgb(190, 0, 0),
+            info: Color::Rgb(0, 110, 140),
+            version: Color::Rgb(120, 120, 120),
+            reverse_selection: false,
+        }
+    }
+
+    pub(crate) fn high_contrast() -> Theme {
+        Theme {
+            name: "high-contrast".to_string(),
+            background: Color::Black,
+            text: Color::White,
+            accent: Color::LightYellow,
+            help: Color::LightYellow,
+            selected: Color::LightCyan,
+            muted: Color::Gray,
+            ok: Color::LightGreen,
+            warn: Color::LightYellow,
+            error: Color::LightRed,
+            info: Color::LightCyan,
+            version: Color::White,
+            reverse_selection: true,
+        }
+    }
+
+    /// No colors at all, only bold and reverse video.
+    pub(crate) fn monochrome() -> Theme {
+        Theme {
+            name: "monochrome".to_string(),
+            background: Color::Reset,
+            text: Color::Reset,
+            accent: Color::Reset,
+            help: Color::Reset,
+            selected: Color::Reset,
+            muted: Color::Reset,
+            ok: Color::Reset,
+            warn: Color::Reset,
+            error: Color::Reset,
+            info: Color::Reset,
+            version: Color::Reset,
+            reverse_selection: true,
+        }
+    }
+
+    fn built_in(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "monochrome" => Some(Theme::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Builds a user theme on top of its base (dark by default).
+    fn from_spec(name: &str, spec: &ThemeSpec) -> Result<Theme, String> {
+        let base = spec.base.as_deref().unwrap_or("dark");
+        let mut theme = Theme::built_in(base).ok_or_else(|| format!("theme \"{name}\": unknown base \"{base}\""))?;
+        theme.name = name.to_string();
+        let fields: [(&str, &Option<String>, &mut Color); 11] = [
+            ("background", &spec.background, &mut theme.background),
+            ("text", &spec.text, &mut theme.text),
+            ("accent", &spec.accent, &mut theme.accent),
+            ("help", &spec.help, &mut theme.help),
+            ("selected", &spec.selected, &mut theme.selected),
+            ("muted", &spec.muted, &mut theme.muted),
+            ("ok", &spec.ok, &mut theme.ok),
+            ("warn", &spec.warn, &mut theme.warn),
+            ("error", &spec.error, &mut theme.error),
+            ("info", &spec.info, &mut theme.info),
+            ("version", &spec.version, &mut theme.version),
+        ];
+        for (field, value, slot) in fields {
+            if let Some(v) = value {
+                *slot = v
+                    .parse()
+                    .map_err(|_| format!("theme \"{name}\": {field} \"{v}\" is not a color"))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Default text on the theme background; dialogs start from this
+    /// after clearing their area.
+    pub(crate) fn base(&self) -> Style {
+        Style::default().fg(self.text).bg(self.background)
+    }
+
+    pub(crate) fn fg(&self, color: Color) -> Style {
+        Style::default().fg(color)
+    }
+
+    pub(crate) fn accent(&self) -> Style {
+        self.fg(self.accent)
+    }
+
+    /// Focused field border, active tab.
+    pub(crate) fn focused(&self) -> Style {
+        self.accent().add_modifier(Modifier::BOLD)
+    }
+
+    pub(crate) fn muted(&self) -> Style {
+        self.fg(self.muted)
+    }
+
+    pub(crate) fn selected(&self) -> Style {
+        let style = self.fg(self.selected).add_modifier(Modifier::BOLD);
+        if self.reverse_selection { style.add_modifier(Modifier::REVERSED) } else { style }
+    }
+
+    pub(crate) fn heading(&self) -> Style {
+        self.fg(self.help).add_modifier(Modifier::BOLD)
+    }
+
+    pub(crate) fn ok(&self) -> Style {
+        self.fg(self.ok)
+    }
+
+    pub(crate) fn warn(&self) -> Style {
+        self.fg(self.warn)
+    }
+
+    pub(crate) fn error(&self) -> Style {
+        self.fg(self.error)
+    }
+
+    pub(crate) fn info(&self) -> Style {
+        self.fg(self.info)
+    }
+}
+
+/// Built-in and user themes, in switching order, and the active one.
+pub(crate) struct Themes {
+    pub themes: Vec<Theme>,
+    pub current: usize,
+}
+
+impl Themes {
+    /// Loads the user themes and picks `name`. With NO_COLOR set the
+    /// monochrome theme is used regardless. Returns warnings for themes
+    /// that could not be loaded.
+    pub(crate) fn load(name: &str, user: &BTreeMap<String, ThemeSpec>) -> (Themes, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut themes: Vec<Theme> = BUILT_IN.iter().filter_map(|n| Theme::built_in(n)).collect();
+        for (n, spec) in user {
+            match Theme::from_spec(n, spec) {
+                Ok(t) => match themes.iter_mut().find(|t| t.name == *n) {
+                    Some(existing) => *existing = t,
+                    None => themes.push(t),
+                },
+                Err(e) => warnings.push(e),
+            }
+        }
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let wanted = match name {
+            _ if no_color => "monochrome",
+            "" => "dark",
+            n => n,
+        };
+        let current = match themes.iter().position(|t| t.name == wanted) {
+            Some(i) => i,
+            None => {
+                warnings.push(format!("unknown theme \"{wanted}\", using dark"));
+                0
+            }
+        };
+        (Themes { themes, current }, warnings)
+    }
+
+    pub(crate) fn active(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// Switches to the next theme and returns its name.
+    pub(crate) fn next(&mut self) -> &str {
+        self.current = (self.current + 1) % self.themes.len();
+        &self.themes[self.current].name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_color() -> bool {
+        std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+    }
+
+    #[test]
+    fn builds_user_themes() {
+        let spec: ThemeSpec =
+            serde_json::from_str(r##"{"base": "light", "accent": "#ff8800", "muted": "dark-gray", "ok": "LightGreen"}"##)
+                .unwrap();
+        let theme = Theme::from_spec("mine", &spec).unwrap();
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.accent, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.muted, Color::DarkGray);
+        assert_eq!(theme.ok, Color::LightGreen);
+        assert_eq!(theme.error, Theme::light().error);
+        // Dark is the default base
+        assert_eq!(Theme::from_spec("plain", &ThemeSpec::default()).unwrap().accent, Theme::dark().accent);
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        let spec = ThemeSpec { base: Some("neon".into()), ..Default::default() };
+        assert_eq!(Theme::from_spec("x", &spec).unwrap_err(), "theme \"x\": unknown base \"neon\"");
+        let spec = ThemeSpec { warn: Some("#12345".into()), ..Default::default() };
+        assert_eq!(Theme::from_spec("x", &spec).unwrap_err(), "theme \"x\": warn \"#12345\" is not a color");
+        assert!(serde_json::from_str::<ThemeSpec>(r#"{"accnet": "red"}"#).is_err());
+    }
+
+    #[test]
+    fn loads_and_switches() {
+        let mut user = BTreeMap::new();
+        user.insert("dark".to_string(), ThemeSpec { accent: Some("blue".into()), ..Default::default() });
+        user.insert("solar".to_string(), ThemeSpec { base: Some("light".into()), ..Default::default() });
+        user.insert("broken".to_string(), ThemeSpec { text: Some("nope".into()), ..Default::default() });
+
+        let (mut themes, warnings) = Themes::load("solar", &user);
+        let names: Vec<&str> = themes.themes.iter().map(|t| t.name.as_str()).collect();
+        // User themes with a built-in name replace it in place
+        assert_eq!(names, ["dark", "light", "high-contrast", "monochrome", "solar"]);
+        assert_eq!(themes.themes[0].accent, Color::Blue);
+        assert_eq!(warnings, ["theme \"broken\": text \"nope\" is not a color"]);
+        if no_color() {
+            return;
+        }
+        assert_eq!(themes.active().name, "solar");
+        assert_eq!(themes.next(), "dark");
+
+        let (themes, warnings) = Themes::load("missing", &BTreeMap::new());
+        assert_eq!(themes.active().name, "dark");
+        assert_eq!(warnings, ["unknown theme \"missing\", using dark"]);
+    }
+
+    #[test]
+    fn monochrome_selection() {
+        assert!(Theme::monochrome().selected().add_modifier.contains(Modifier::REVERSED));
+        assert!(!Theme::dark().selected().add_modifier.contains(Modifier::REVERSED));
+    }
+}
@@ -0,0 +1,288 @@
+use bitcoin::{Amount, Denomination, SignedAmount};
+use serde::{Deserialize, Serialize};
+
+use crate::fiat::{FiatRate, with_fiat};
+use crate::privacy::{AMOUNT_KEYS, Item, bare_amount, map_fields, map_items};
+
+// ===== Display units =====
+
+/// Unit amounts are shown in. Cycled with `cycle_unit` (`b`) and saved in config.json.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Unit {
+    #[default]
+    Btc,
+    Mbtc,
+    /// µBTC, a.k.a. bits.
+    Bits,
+    Sats,
+}
+
+impl Unit {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Unit::Btc => "BTC",
+            Unit::Mbtc => "mBTC",
+            Unit::Bits => "bits",
+            Unit::Sats => "sat",
+        }
+    }
+
+    pub(crate) fn next(self) -> Unit {
+        match self {
+            Unit::Btc => Unit::Mbtc,
+            Unit::Mbtc => Unit::Bits,
+            Unit::Bits => Unit::Sats,
+            Unit::Sats => Unit::Btc,
+        }
+    }
+
+    /// Satoshis per unit and the decimals that keeps satoshi precision.
+    fn scale(self) -> (u64, usize) {
+        match self {
+            Unit::Btc => (100_000_000, 8),
+            Unit::Mbtc => (100_000, 5),
+            Unit::Bits => (100, 2),
+            Unit::Sats => (1, 0),
+        }
+    }
+
+    /// Exact amount with thousands separators and the unit, e.g.
+    /// `1,250.00000000 BTC` or `125,000,000 sat`.
+    pub(crate) fn format(self, amount: Amount) -> String {
+        format!("{} {}", self.format_value(amount.to_sat(), false), self.label())
+    }
+
+    pub(crate) fn format_signed(self, amount: SignedAmount) -> String {
+        let sats = amount.to_sat();
+        format!("{} {}", self.format_value(sats.unsigned_abs(), sats < 0), self.label())
+    }
+
+    /// Shows the amounts in `bitcoin-cli` output in this unit, as strings
+    /// like `"amount": "-1,234 sat"`, with their fiat value when there is a
+    /// rate. BTC output without a rate stays as the node printed it, and so
+    /// does anything that is not an exact BTC amount.
+    pub(crate) fn output(self, command: &str, lines: &[String], fiat: Option<&FiatRate>) -> Vec<String> {
+        if self == Unit::Btc && fiat.is_none() {
+            return lines.to_vec();
+        }
+        let convert = |text: &str| {
+            SignedAmount::from_str_in(text, Denomination::Bitcoin)
+                .ok()
+                .map(|a| with_fiat(self.format_signed(a), a.to_sat(), fiat))
+        };
+        if let Some(only) = bare_amount(command, lines)
+            && let Some(shown) = convert(only)
+        {
+            return lines.iter().map(|l| if l.trim().is_empty() { l.clone() } else { shown.clone() }).collect();
+        }
+        if let Some(out) = map_items(command, lines, |item, value| {
+            (item == Item::Amount).then(|| convert(value)).flatten().map(|a| format!("\"{a}\""))
+        }) {
+            return out;
+        }
+        map_fields(lines, |field, value| {
+            AMOUNT_KEYS.contains(&field).then(|| convert(value)).flatten().map(|a| format!("\"{a}\""))
+        })
+    }
+
+    fn denomination(self) -> Denomination {
+        match self {
+            Unit::Btc => Denomination::Bitcoin,
+            Unit::Mbtc => Denomination::MilliBitcoin,
+            Unit::Bits => Denomination::Bit,
+            Unit::Sats => Denomination::Satoshi,
+        }
+    }
+
+    /// Reads an amount typed in an input field, e.g. `1.5 mBTC`,
+    /// `150000 sat` or `150,000`. A number without a unit is in this unit.
+    /// Commas may only group thousands, and amounts finer than a satoshi
+    /// are refused rather than rounded.
+    pub(crate) fn parse(self, text: &str) -> Result<Amount, String> {
+        let text = text.trim();
+        let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ',')).unwrap_or(text.len());
+        let (number, suffix) = (&text[..split], text[split..].trim());
+        let unit = match suffix.to_lowercase().as_str() {
+            "" => self,
+            "btc" => Unit::Btc,
+            "mbtc" => Unit::Mbtc,
+            "bits" | "bit" | "ubtc" | "µbtc" => Unit::Bits,
+            "sat" | "sats" => Unit::Sats,
+            _ => return Err(format!("unknown unit \"{suffix}\"")),
+        };
+        let (whole, fraction) = match number.split_once('.') {
+            Some((w, f)) => (w, Some(f)),
+            None => (number, None),
+        };
+        let groups: Vec<&str> = whole.split(',').collect();
+        let grouped = groups.len() == 1
+            || ((1..=3).contains(&groups[0].len()) && groups[1..].iter().all(|g| g.len() == 3));
+        if whole.is_empty() || !grouped {
+            return Err(format!("\"{number}\" is not a number"));
+        }
+        let plain = match fraction {
+            Some(f) => format!("{}.{f}", whole.replace(',', "")),
+            None => whole.replace(',', ""),
+        };
+        Amount::from_str_in(&plain, unit.denomination()).map_err(|e| match e {
+            bitcoin::amount::ParseAmountError::TooPrecise(_) => {
+                format!("{} {} is finer than a satoshi", plain, unit.label())
+            }
+            e => format!("\"{number}\": {e}"),
+        })
+    }
+
+    /// Value of `sats` in this unit, for charts.
+    pub(crate) fn value(self, sats: i64) -> f64 {
+        sats as f64 / self.scale().0 as f64
+    }
+
+    fn format_value(self, sats: u64, negative: bool) -> String {
+        let (per_unit, decimals) = self.scale();
+        let sign = if negative { "-" } else { "" };
+        let whole = group_thousands(sats / per_unit);
+        if decimals == 0 {
+            format!("{sign}{whole}")
+        } else {
+            format!("{sign}{whole}.{:0decimals$}", sats % per_unit)
+        }
+    }
+}
+
+pub(crate) fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn formats_each_unit() {
+        let amount = Amount::from_sat(123_456_789_012);
+        assert_eq!(Unit::Btc.format(amount), "1,234.56789012 BTC");
+        assert_eq!(Unit::Mbtc.format(amount), "1,234,567.89012 mBTC");
+        assert_eq!(Unit::Bits.format(amount), "1,234,567,890.12 bits");
+        assert_eq!(Unit::Sats.format(amount), "123,456,789,012 sat");
+        assert_eq!(Unit::Btc.format(Amount::ZERO), "0.00000000 BTC");
+        assert_eq!(Unit::Sats.format_signed(SignedAmount::from_sat(-1_500)), "-1,500 sat");
+        assert_eq!(Unit::Bits.format_signed(SignedAmount::from_sat(-5)), "-0.05 bits");
+    }
+
+    #[test]
+    fn cycles_and_scales() {
+        let mut unit = Unit::default();
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(unit.label());
+            unit = unit.next();
+        }
+        assert_eq!(seen, ["BTC", "mBTC", "bits", "sat"]);
+        assert_eq!(unit, Unit::Btc);
+        assert_eq!(Unit::Mbtc.value(250_000), 2.5);
+        assert_eq!(Unit::Sats.value(-7), -7.0);
+    }
+
+    #[test]
+    fn groups_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1_000), "1,000");
+        assert_eq!(group_thousands(21_000_000), "21,000,000");
+    }
+
+    #[test]
+    fn converts_output_fields() {
+        let out = lines(
+            r#"[
+  {
+    "txid": "aa11",
+    "vout": 1,
+    "amount": 0.00012345,
+    "fee": -0.00000141,
+    "confirmations": 12,
+    "label": "0.5"
+  }
+]"#,
+        );
+        assert_eq!(Unit::Btc.output("listunspent", &out, None), out);
+        let shown = Unit::Sats.output("listunspent", &out, None);
+        assert_eq!(shown[3], r#"    "vout": 1,"#);
+        assert_eq!(shown[4], r#"    "amount": "12,345 sat","#);
+        assert_eq!(shown[5], r#"    "fee": "-141 sat","#);
+        assert_eq!(shown[6], r#"    "confirmations": 12,"#);
+        assert_eq!(shown[7], r#"    "label": "0.5""#);
+        assert_eq!(Unit::Mbtc.output("listunspent", &out, None)[4], r#"    "amount": "0.12345 mBTC","#);
+    }
+
+    #[test]
+    fn converts_bare_amounts() {
+        assert_eq!(Unit::Sats.output("getbalance", &lines("1.23000000\n"), None), ["123,000,000 sat"]);
+        assert_eq!(Unit::Sats.output("getblockcount", &lines("840000"), None), ["840000"]);
+        let error = lines("error code: -18\nerror message:\nRequested wallet does not exist");
+        assert_eq!(Unit::Sats.output("getbalance", &error, None), error);
+    }
+
+    #[test]
+    fn privacy_keeps_the_unit() {
+        let privacy = crate::privacy::Privacy { on: true, addresses: false, txids: false };
+        let out = Unit::Sats.output("listunspent", &lines("[\n  {\n    \"amount\": 0.5\n  }\n]"), None);
+        assert_eq!(privacy.output("listunspent", &out)[2], r#"    "amount": "••••• sat""#);
+        let bare = Unit::Bits.output("getbalance", &lines("0.001"), None);
+        assert_eq!(privacy.output("getbalance", &bare), ["••••• bits"]);
+    }
+
+    #[test]
+    fn parses_input() {
+        assert_eq!(Unit::Btc.parse("1.5 mBTC"), Ok(Amount::from_sat(150_000)));
+        assert_eq!(Unit::Btc.parse("150000 sat"), Ok(Amount::from_sat(150_000)));
+        assert_eq!(Unit::Sats.parse("150,000"), Ok(Amount::from_sat(150_000)));
+        assert_eq!(Unit::Btc.parse(" 0.00012345 "), Ok(Amount::from_sat(12_345)));
+        assert_eq!(Unit::Btc.parse("1,250.5 bits"), Ok(Amount::from_sat(125_050)));
+        assert_eq!(Unit::Mbtc.parse("2"), Ok(Amount::from_sat(200_000)));
+        assert_eq!(Unit::Btc.parse("5SATS"), Ok(Amount::from_sat(5)));
+        assert_eq!(Unit::Btc.parse(".5"), Err("\".5\" is not a number".to_string()));
+        // Formatted amounts read back as the same value
+        let amount = Amount::from_sat(123_456_789_012);
+        for unit in [Unit::Btc, Unit::Mbtc, Unit::Bits, Unit::Sats] {
+            assert_eq!(Unit::Btc.parse(&unit.format(amount)), Ok(amount));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(Unit::Btc.parse("0.000000001"), Err("0.000000001 BTC is finer than a satoshi".to_string()));
+        assert_eq!(Unit::Sats.parse("1.5"), Err("1.5 sat is finer than a satoshi".to_string()));
+        assert_eq!(Unit::Bits.parse("0.001 bits"), Err("0.001 bits is finer than a satoshi".to_string()));
+        assert_eq!(Unit::Btc.parse("1 eur"), Err("unknown unit \"eur\"".to_string()));
+        assert!(Unit::Sats.parse("15,00").is_err());
+        assert!(Unit::Sats.parse("1,5000").is_err());
+        assert!(Unit::Sats.parse("").is_err());
+        assert!(Unit::Btc.parse("-1").is_err());
+        assert!(Unit::Btc.parse("1.2.3").is_err());
+    }
+
+    #[test]
+    fn adds_fiat_values() {
+        let rate = FiatRate { currency: "EUR".to_string(), rate: 60000.0, fetched_at: chrono::Utc::now() };
+        let out = Unit::Btc.output("listunspent", &lines("[\n  {\n    \"amount\": 0.5\n  }\n]"), Some(&rate));
+        assert_eq!(out[2], r#"    "amount": "0.50000000 BTC (≈ 30,000.00 EUR)""#);
+        let bare = Unit::Sats.output("getbalance", &lines("-0.001"), Some(&rate));
+        assert_eq!(bare, ["-100,000 sat (≈ -60.00 EUR)"]);
+        let privacy = crate::privacy::Privacy { on: true, addresses: false, txids: false };
+        assert_eq!(privacy.output("listunspent", &out)[2], r#"    "amount": "••••• BTC (≈ ••••• EUR)""#);
+    }
+}
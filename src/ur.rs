@@ -0,0 +1,591 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::hashes::{Hash, sha256};
+
+// ===== Uniform Resources (BCR-2020-005) with fountain codes (BCR-2020-012) =====
+
+/// UR type for PSBTs. Newer tools also use plain "psbt", which is accepted
+/// when decoding.
+pub const PSBT_TYPE: &str = "crypto-psbt";
+
+/// Fragments are never made smaller than this.
+const MIN_FRAGMENT_LEN: usize = 10;
+/// Limits on what a scanned part may announce, so a bad or hostile part
+/// cannot make the decoder allocate or compute without bound.
+const MAX_SEQ_LEN: usize = 10_000;
+const MAX_MESSAGE_LEN: usize = 8 * 1024 * 1024;
+
+// Bytewords: each byte is a 4-letter word, written in minimal form as the
+// first and last letter.
+const BYTEWORDS: &str = "able acid also apex aqua arch atom aunt away axis back bald barn belt beta bias blue body brag brew bulb buzz calm cash cats chef city claw code cola cook cost crux curl cusp cyan dark data days deli dice diet door down draw drop drum dull duty each easy echo edge epic even exam exit eyes fact fair fern figs film fish fizz flap flew flux foxy free frog fuel fund gala game gear gems gift girl glow good gray grim guru gush gyro half hang hard hawk heat help high hill holy hope horn huts iced idea idle inch inky into iris iron item jade jazz join jolt jowl judo jugs jump junk jury keep keno kept keys kick kiln king kite kiwi knob lamb lava lazy leaf legs liar limp lion list logo loud love luau luck lung main many math maze memo menu meow mild mint miss monk nail navy need news next noon note numb obey oboe omit onyx open oval owls paid part peck play plus poem pool pose puff puma purr quad quiz race ramp real redo rich road rock roof ruby ruin runs rust safe saga scar sets silk skew slot soap solo song stub surf swan taco task taxi tent tied time tiny toil tomb toys trip tuna twin ugly undo unit urge user vast very veto vial vibe view visa void vows wall wand warm wasp wave waxy webs what when whiz wolf work yank yawn yell yoga yurt zaps zero zest zinc zone zoom";
+
+fn minimal_words() -> Vec<[u8; 2]> {
+    BYTEWORDS
+        .split(' ')
+        .map(|w| {
+            let b = w.as_bytes();
+            [b[0], b[3]]
+        })
+        .collect()
+}
+
+/// CRC-32 (IEEE), as used by bytewords and the fountain checksum.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn bytewords_encode(data: &[u8]) -> String {
+    let words = minimal_words();
+    let mut out = String::with_capacity((data.len() + 4) * 2);
+    for b in data.iter().chain(crc32(data).to_be_bytes().iter()) {
+        let w = words[*b as usize];
+        out.push(w[0] as char);
+        out.push(w[1] as char);
+    }
+    out
+}
+
+fn bytewords_decode(s: &str) -> Result<Vec<u8>, String> {
+    let words = minimal_words();
+    let s = s.to_ascii_lowercase();
+    if !s.len().is_multiple_of(2) || s.len() < 10 {
+        return Err("bytewords too short".to_string());
+    }
+    let mut data = s
+        .as_bytes()
+        .chunks(2)
+        .map(|c| {
+            words
+                .iter()
+                .position(|w| w[..] == c[..])
+                .map(|i| i as u8)
+                .ok_or_else(|| format!("'{}' is not a byteword", String::from_utf8_lossy(c)))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    let body_len = data.len() - 4;
+    let checksum = u32::from_be_bytes([data[body_len], data[body_len + 1], data[body_len + 2], data[body_len + 3]]);
+    data.truncate(body_len);
+    if crc32(&data) != checksum {
+        return Err("bytewords checksum mismatch".to_string());
+    }
+    Ok(data)
+}
+
+// ===== Minimal CBOR =====
+
+fn cbor_head(major: u8, value: u64, out: &mut Vec<u8>) {
+    let m = major << 5;
+    match value {
+        0..=23 => out.push(m | value as u8),
+        24..=0xff => out.extend([m | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(m | 25);
+            out.extend((value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(m | 26);
+            out.extend((value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(m | 27);
+            out.extend(value.to_be_bytes());
+        }
+    }
+}
+
+/// CBOR byte string, the body of a crypto-psbt UR.
+pub(crate) fn cbor_bytes(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 9);
+    cbor_head(2, data.len() as u64, &mut out);
+    out.extend_from_slice(data);
+    out
+}
+
+struct CborReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl CborReader<'_> {
+    fn head(&mut self, major: u8) -> Result<u64, String> {
+        let first = *self.data.get(self.pos).ok_or("truncated CBOR")?;
+        self.pos += 1;
+        if first >> 5 != major {
+            return Err(format!("expected CBOR major type {major}"));
+        }
+        let len = match first & 0x1f {
+            n @ 0..=23 => return Ok(n as u64),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err("unsupported CBOR length".to_string()),
+        };
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or("truncated CBOR")?;
+        self.pos += len;
+        Ok(bytes.iter().fold(0u64, |v, b| (v << 8) | *b as u64))
+    }
+
+    fn bytes(&mut self) -> Result<&[u8], String> {
+        let len = self.head(2)? as usize;
+        let out = self.data.get(self.pos..self.pos + len).ok_or("truncated CBOR")?;
+        self.pos += len;
+        Ok(out)
+    }
+}
+
+/// Unwraps the CBOR byte string of a crypto-psbt message.
+pub(crate) fn cbor_unwrap_bytes(data: &[u8]) -> Result<Vec<u8>, String> {
+    CborReader { data, pos: 0 }.bytes().map(|b| b.to_vec())
+}
+
+// ===== Fountain codes =====
+
+/// Xoshiro256** seeded from SHA-256, as in the reference implementation.
+struct Xoshiro256([u64; 4]);
+
+impl Xoshiro256 {
+    fn from_seed(seed: &[u8]) -> Self {
+        let hash = sha256::Hash::hash(seed).to_byte_array();
+        let mut s = [0u64; 4];
+        for (i, chunk) in hash.chunks(8).enumerate() {
+            s[i] = u64::from_be_bytes(chunk.try_into().unwrap_or_default());
+        }
+        Xoshiro256(s)
+    }
+
+    fn next(&mut self) -> u64 {
+        let s = &mut self.0;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn next_double(&mut self) -> f64 {
+        self.next() as f64 / 18_446_744_073_709_551_616.0
+    }
+
+    fn next_int(&mut self, low: usize, high: usize) -> usize {
+        (self.next_double() * (high - low + 1) as f64) as usize + low
+    }
+}
+
+/// Walker/Vose alias sampler for the degree distribution.
+fn choose_degree(seq_len: usize, rng: &mut Xoshiro256) -> usize {
+    let n = seq_len;
+    let weights: Vec<f64> = (1..=n).map(|i| 1.0 / i as f64).collect();
+    let total: f64 = weights.iter().sum();
+    let mut p: Vec<f64> = weights.iter().map(|w| w * n as f64 / total).collect();
+    let (mut small, mut large): (Vec<usize>, Vec<usize>) = (Vec::new(), Vec::new());
+    for i in (0..n).rev() {
+        if p[i] < 1.0 {
+            small.push(i);
+        } else {
+            large.push(i);
+        }
+    }
+    let mut probs = vec![0.0; n];
+    let mut aliases = vec![0usize; n];
+    while let (Some(&a), Some(&g)) = (small.last(), large.last()) {
+        small.pop();
+        large.pop();
+        probs[a] = p[a];
+        aliases[a] = g;
+        p[g] += p[a] - 1.0;
+        if p[g] < 1.0 {
+            small.push(g);
+        } else {
+            large.push(g);
+        }
+    }
+    for i in large.into_iter().chain(small) {
+        probs[i] = 1.0;
+    }
+
+    let r1 = rng.next_double();
+    let r2 = rng.next_double();
+    let i = (n as f64 * r1) as usize;
+    (if r2 < probs[i] { i } else { aliases[i] }) + 1
+}
+
+/// Indexes of the fragments XORed into part `seq_num`.
+fn choose_fragments(seq_num: u32, seq_len: usize, checksum: u32) -> BTreeSet<usize> {
+    if (1..=seq_len).contains(&(seq_num as usize)) {
+        return BTreeSet::from([seq_num as usize - 1]);
+    }
+    let mut seed = seq_num.to_be_bytes().to_vec();
+    seed.extend(checksum.to_be_bytes());
+    let mut rng = Xoshiro256::from_seed(&seed);
+    let degree = choose_degree(seq_len, &mut rng);
+    let mut remaining: Vec<usize> = (0..seq_len).collect();
+    let mut shuffled = Vec::with_capacity(seq_len);
+    while !remaining.is_empty() {
+        let i = rng.next_int(0, remaining.len() - 1);
+        shuffled.push(remaining.remove(i));
+    }
+    shuffled.into_iter().take(degree).collect()
+}
+
+fn fragment_len(message_len: usize, max_fragment_len: usize) -> usize {
+    let max_count = (message_len / MIN_FRAGMENT_LEN).max(1);
+    for count in 1..=max_count {
+        let len = message_len.div_ceil(count);
+        if len <= max_fragment_len {
+            return len;
+        }
+    }
+    message_len.div_ceil(max_count)
+}
+
+fn xor_into(target: &mut [u8], other: &[u8]) {
+    for (t, o) in target.iter_mut().zip(other) {
+        *t ^= o;
+    }
+}
+
+/// Produces an endless sequence of UR parts for one message. The first
+/// `seq_len` parts are the plain fragments, later ones are XOR mixes, so a
+/// scanner that missed frames still completes.
+pub(crate) struct UrEncoder {
+    ur_type: String,
+    message_len: usize,
+    checksum: u32,
+    fragments: Vec<Vec<u8>>,
+}
+
+impl UrEncoder {
+    pub(crate) fn new(ur_type: &str, message: &[u8], max_fragment_len: usize) -> Self {
+        let len = fragment_len(message.len(), max_fragment_len.max(MIN_FRAGMENT_LEN));
+        let mut padded = message.to_vec();
+        padded.resize(message.len().div_ceil(len) * len, 0);
+        UrEncoder {
+            ur_type: ur_type.to_string(),
+            message_len: message.len(),
+            checksum: crc32(message),
+            fragments: padded.chunks(len).map(|c| c.to_vec()).collect(),
+        }
+    }
+
+    pub(crate) fn seq_len(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// Part `seq_num` (1-based) as an upper-case UR string, which keeps
+    /// the QR in alphanumeric mode. A single fragment gives a single-part UR.
+    pub(crate) fn part(&self, seq_num: u32) -> String {
+        let seq_len = self.seq_len();
+        if seq_len == 1 {
+            let mut message = self.fragments[0].clone();
+            message.truncate(self.message_len);
+            return format!("ur:{}/{}", self.ur_type, bytewords_encode(&message)).to_ascii_uppercase();
+        }
+        let mut data = vec![0u8; self.fragments[0].len()];
+        for i in choose_fragments(seq_num, seq_len, self.checksum) {
+            xor_into(&mut data, &self.fragments[i]);
+        }
+        let mut cbor = Vec::new();
+        cbor_head(4, 5, &mut cbor);
+        cbor_head(0, seq_num as u64, &mut cbor);
+        cbor_head(0, seq_len as u64, &mut cbor);
+        cbor_head(0, self.message_len as u64, &mut cbor);
+        cbor_head(0, self.checksum as u64, &mut cbor);
+        cbor_head(2, data.len() as u64, &mut cbor);
+        cbor.extend(data);
+        format!("ur:{}/{}-{}/{}", self.ur_type, seq_num, seq_len, bytewords_encode(&cbor)).to_ascii_uppercase()
+    }
+}
+
+/// Collects UR parts in any order, including mixed ones, until the
+/// message can be rebuilt.
+#[derive(Default)]
+pub(crate) struct UrDecoder {
+    pub ur_type: Option<String>,
+    /// (seq_len, message_len, checksum, fragment length) of the message
+    /// being received.
+    header: Option<(usize, usize, u32, usize)>,
+    simple: BTreeMap<usize, Vec<u8>>,
+    mixed: Vec<(BTreeSet<usize>, Vec<u8>)>,
+    seen: BTreeSet<u32>,
+    message: Option<Vec<u8>>,
+}
+
+impl UrDecoder {
+    /// Fragments known so far and the total.
+    pub(crate) fn progress(&self) -> (usize, usize) {
+        match self.header {
+            Some((seq_len, ..)) => (self.simple.len(), seq_len),
+            None => (self.message.iter().count(), 1),
+        }
+    }
+
+    /// The reassembled message (CBOR), once complete.
+    pub(crate) fn message(&self) -> Option<&[u8]> {
+        self.message.as_deref()
+    }
+
+    /// Feeds one `ur:type/...` string. Returns false for duplicates.
+    pub(crate) fn receive(&mut self, part: &str) -> Result<bool, String> {
+        let lower = part.trim().to_ascii_lowercase();
+        let rest = lower.strip_prefix("ur:").ok_or("not a UR (missing ur: prefix)")?;
+        let (ur_type, rest) = rest.split_once('/').ok_or("missing UR type")?;
+        if self.ur_type.as_deref().is_some_and(|t| t != ur_type) {
+            return Err(format!("expected ur:{}, got ur:{ur_type}", self.ur_type.as_deref().unwrap_or("")));
+        }
+        self.ur_type = Some(ur_type.to_string());
+
+        let Some((seq, words)) = rest.split_once('/') else {
+            self.message = Some(bytewords_decode(rest)?);
+            return Ok(true);
+        };
+        let (seq_num, seq_len) = seq
+            .split_once('-')
+            .and_then(|(n, l)| Some((n.parse::<u32>().ok()?, l.parse::<usize>().ok()?)))
+            .ok_or("bad sequence number")?;
+        let cbor = bytewords_decode(words)?;
+        let mut r = CborReader { data: &cbor, pos: 0 };
+        if r.head(4)? != 5 {
+            return Err("bad multi-part header".to_string());
+        }
+        let (n, len) = (r.head(0)? as u32, r.head(0)? as usize);
+        let (message_len, checksum) = (r.head(0)? as usize, r.head(0)? as u32);
+        let fragment = r.bytes()?.to_vec();
+        if n != seq_num || len != seq_len {
+            return Err("sequence numbers do not match the part body".to_string());
+        }
+        if seq_num == 0 || seq_len == 0 {
+            return Err("sequence numbers start at 1".to_string());
+        }
+        if seq_len > MAX_SEQ_LEN || message_len > MAX_MESSAGE_LEN {
+            return Err(format!("message too large ({seq_len} parts, {message_len} bytes)"));
+        }
+        // The encoder pads the message to whole fragments, so the length
+        // must fall within the last one
+        let fragment_len = fragment.len();
+        if fragment_len == 0
+            || message_len > seq_len * fragment_len
+            || message_len <= (seq_len - 1) * fragment_len
+        {
+            return Err("part sizes do not add up to the message length".to_string());
+        }
+        let header = (seq_len, message_len, checksum, fragment_len);
+        match self.header {
+            Some(h) if h != header => {
+                return Err("part belongs to a different message".to_string());
+            }
+            _ => self.header = Some(header),
+        }
+        if self.message.is_some() || !self.seen.insert(seq_num) {
+            return Ok(false);
+        }
+
+        let indexes = choose_fragments(seq_num, seq_len, checksum);
+        self.add_mixed(indexes, fragment);
+        self.try_complete()?;
+        Ok(true)
+    }
+
+    /// Reduces a part by the fragments already known; single-fragment
+    /// results become known and are used to reduce the queued mixes.
+    fn add_mixed(&mut self, indexes: BTreeSet<usize>, data: Vec<u8>) {
+        let mut queue = vec![(indexes, data)];
+        while let Some((mut idx, mut data)) = queue.pop() {
+            for i in idx.clone() {
+                if let Some(known) = self.simple.get(&i) {
+                    xor_into(&mut data, known);
+                    idx.remove(&i);
+                }
+            }
+            match idx.len() {
+                0 => {}
+                1 => {
+                    let i = *idx.iter().next().unwrap_or(&0);
+                    self.simple.insert(i, data);
+                    // Anything waiting on this fragment may now reduce
+                    let (ready, waiting): (Vec<_>, Vec<_>) =
+                        self.mixed.drain(..).partition(|(m, _)| m.contains(&i));
+                    self.mixed = waiting;
+                    queue.extend(ready);
+                }
+                _ => self.mixed.push((idx, data)),
+            }
+        }
+    }
+
+    fn try_complete(&mut self) -> Result<(), String> {
+        let Some((seq_len, message_len, checksum, _)) = self.header else {
+            return Ok(());
+        };
+        if self.simple.len() < seq_len {
+            return Ok(());
+        }
+        let mut message: Vec<u8> = self.simple.values().flatten().copied().collect();
+        message.truncate(message_len);
+        if crc32(&message) != checksum {
+            return Err("reassembled message fails its checksum".to_string());
+        }
+        self.message = Some(message);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `make_message` of the reference implementation: random bytes from
+    /// Xoshiro256** seeded with `seed`.
+    fn make_message(len: usize, seed: &str) -> Vec<u8> {
+        let mut rng = Xoshiro256::from_seed(seed.as_bytes());
+        (0..len).map(|_| rng.next_int(0, 255) as u8).collect()
+    }
+
+    #[test]
+    fn crc32_vectors() {
+        assert_eq!(crc32(b"Hello, world!"), 0xebe6c6e6);
+        assert_eq!(crc32(b"Wolf"), 0x598c84dc);
+    }
+
+    #[test]
+    fn bytewords_vectors() {
+        assert_eq!(bytewords_encode(&[0, 1, 2, 128, 255]), "aeadaolazmjendeoti");
+        assert_eq!(bytewords_decode("aeadaolazmjendeoti").unwrap(), vec![0, 1, 2, 128, 255]);
+        assert!(bytewords_decode("aeadaolazmjendeotj").is_err());
+        assert!(bytewords_decode("aeadaolazmjendeo").is_err());
+    }
+
+    #[test]
+    fn xoshiro_vector() {
+        let mut rng = Xoshiro256::from_seed(b"Wolf");
+        let got: Vec<u64> = (0..100).map(|_| rng.next() % 100).collect();
+        let expected: [u64; 100] = [
+            42, 81, 85, 8, 82, 84, 76, 73, 70, 88, 2, 74, 40, 48, 77, 54, 88, 7, 5, 88, 37, 25, 82, 13, 69, 59, 30,
+            39, 11, 82, 19, 99, 45, 87, 30, 15, 32, 22, 89, 44, 92, 77, 29, 78, 4, 92, 44, 68, 92, 69, 1, 42, 89,
+            50, 37, 84, 63, 34, 32, 3, 17, 62, 40, 98, 82, 89, 24, 43, 85, 39, 15, 3, 99, 29, 20, 42, 27, 10, 85,
+            66, 50, 35, 69, 70, 70, 74, 30, 13, 72, 54, 11, 5, 70, 55, 91, 52, 10, 43, 43, 52,
+        ];
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn single_part_vector() {
+        let ur = cbor_bytes(&make_message(50, "Wolf"));
+        let part = UrEncoder::new("bytes", &ur, 1000).part(1);
+        assert_eq!(
+            part.to_ascii_lowercase(),
+            "ur:bytes/hdeymejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtgwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsdwkbrkch"
+        );
+        let mut decoder = UrDecoder::default();
+        assert!(decoder.receive(&part).unwrap());
+        assert_eq!(decoder.message(), Some(&ur[..]));
+        assert_eq!(cbor_unwrap_bytes(&ur).unwrap(), make_message(50, "Wolf"));
+    }
+
+    const MULTI_PART: [&str; 20] = [
+        "ur:bytes/1-9/lpadascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtdkgslpgh",
+        "ur:bytes/2-9/lpaoascfadaxcywenbpljkhdcagwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsgmghhkhstlrdcxaefz",
+        "ur:bytes/3-9/lpaxascfadaxcywenbpljkhdcahelbknlkuejnbadmssfhfrdpsbiegecpasvssovlgeykssjykklronvsjksopdzmol",
+        "ur:bytes/4-9/lpaaascfadaxcywenbpljkhdcasotkhemthydawydtaxneurlkosgwcekonertkbrlwmplssjtammdplolsbrdzcrtas",
+        "ur:bytes/5-9/lpahascfadaxcywenbpljkhdcatbbdfmssrkzmcwnezelennjpfzbgmuktrhtejscktelgfpdlrkfyfwdajldejokbwf",
+        "ur:bytes/6-9/lpamascfadaxcywenbpljkhdcackjlhkhybssklbwefectpfnbbectrljectpavyrolkzczcpkmwidmwoxkilghdsowp",
+        "ur:bytes/7-9/lpatascfadaxcywenbpljkhdcavszmwnjkwtclrtvaynhpahrtoxmwvwatmedibkaegdosftvandiodagdhthtrlnnhy",
+        "ur:bytes/8-9/lpayascfadaxcywenbpljkhdcadmsponkkbbhgsoltjntegepmttmoonftnbuoiyrehfrtsabzsttorodklubbuyaetk",
+        "ur:bytes/9-9/lpasascfadaxcywenbpljkhdcajskecpmdckihdyhphfotjojtfmlnwmadspaxrkytbztpbauotbgtgtaeaevtgavtny",
+        "ur:bytes/10-9/lpbkascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtwdkiplzs",
+        "ur:bytes/11-9/lpbdascfadaxcywenbpljkhdcahelbknlkuejnbadmssfhfrdpsbiegecpasvssovlgeykssjykklronvsjkvetiiapk",
+        "ur:bytes/12-9/lpbnascfadaxcywenbpljkhdcarllaluzmdmgstospeyiefmwejlwtpedamktksrvlcygmzemovovllarodtmtbnptrs",
+        "ur:bytes/13-9/lpbtascfadaxcywenbpljkhdcamtkgtpknghchchyketwsvwgwfdhpgmgtylctotzopdrpayoschcmhplffziachrfgd",
+        "ur:bytes/14-9/lpbaascfadaxcywenbpljkhdcapazewnvonnvdnsbyleynwtnsjkjndeoldydkbkdslgjkbbkortbelomueekgvstegt",
+        "ur:bytes/15-9/lpbsascfadaxcywenbpljkhdcaynmhpddpzmversbdqdfyrehnqzlugmjzmnmtwmrouohtstgsbsahpawkditkckynwt",
+        "ur:bytes/16-9/lpbeascfadaxcywenbpljkhdcawygekobamwtlihsnpalnsghenskkiynthdzotsimtojetprsttmukirlrsbtamjtpd",
+        "ur:bytes/17-9/lpbyascfadaxcywenbpljkhdcamklgftaxykpewyrtqzhydntpnytyisincxmhtbceaykolduortotiaiaiafhiaoyce",
+        "ur:bytes/18-9/lpbgascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtntwkbkwy",
+        "ur:bytes/19-9/lpbwascfadaxcywenbpljkhdcadekicpaajootjzpsdrbalpeywllbdsnbinaerkurspbncxgslgftvtsrjtksplcpeo",
+        "ur:bytes/20-9/lpbbascfadaxcywenbpljkhdcayapmrleeleaxpasfrtrdkncffwjyjzgyetdmlewtkpktgllepfrltataztksmhkbot",
+    ];
+
+    #[test]
+    fn multi_part_vector() {
+        let ur = cbor_bytes(&make_message(256, "Wolf"));
+        let encoder = UrEncoder::new("bytes", &ur, 30);
+        assert_eq!(encoder.seq_len(), 9);
+        for (i, expected) in MULTI_PART.iter().enumerate() {
+            assert_eq!(encoder.part(i as u32 + 1).to_ascii_lowercase(), *expected, "part {}", i + 1);
+        }
+    }
+
+    #[test]
+    fn decodes_from_mixed_parts() {
+        let ur = cbor_bytes(&make_message(256, "Wolf"));
+        // Skip most plain fragments so the mixed parts have to fill in
+        let mut decoder = UrDecoder::default();
+        for part in MULTI_PART.iter().skip(4).filter(|p| !p.contains("/6-9/")) {
+            decoder.receive(part).unwrap();
+            if decoder.message().is_some() {
+                break;
+            }
+        }
+        assert_eq!(decoder.message(), Some(&ur[..]));
+        assert_eq!(decoder.ur_type.as_deref(), Some("bytes"));
+    }
+
+    #[test]
+    fn round_trip_any_order() {
+        let message = cbor_bytes(&make_message(2000, "PSBT"));
+        let encoder = UrEncoder::new(PSBT_TYPE, &message, 100);
+        let mut decoder = UrDecoder::default();
+        let mut seq = (1..=encoder.seq_len() as u32 * 3).rev();
+        while decoder.message().is_none() {
+            let n = seq.next().expect("ran out of parts");
+            decoder.receive(&encoder.part(n)).unwrap();
+        }
+        assert_eq!(decoder.message(), Some(&message[..]));
+        assert!(!decoder.receive(&encoder.part(1)).unwrap());
+    }
+
+    #[test]
+    fn rejects_bad_parts() {
+        let ur = cbor_bytes(&make_message(256, "Wolf"));
+        let encoder = UrEncoder::new("bytes", &ur, 30);
+        // Part 0 would index fragment -1
+        assert!(UrDecoder::default().receive(&encoder.part(0)).is_err());
+        // A header claiming far more data than the fragments hold
+        let mut cbor = Vec::new();
+        cbor_head(4, 5, &mut cbor);
+        for v in [1, 1_000_000, 1_000_000_000, 0] {
+            cbor_head(0, v, &mut cbor);
+        }
+        cbor_head(2, 10, &mut cbor);
+        cbor.extend([0u8; 10]);
+        let huge = format!("ur:bytes/1-1000000/{}", bytewords_encode(&cbor));
+        assert!(UrDecoder::default().receive(&huge).is_err());
+        let mut cbor = Vec::new();
+        cbor_head(4, 5, &mut cbor);
+        for v in [1, 3, 100, 0] {
+            cbor_head(0, v, &mut cbor);
+        }
+        cbor_head(2, 10, &mut cbor);
+        cbor.extend([0u8; 10]);
+        let short = format!("ur:bytes/1-3/{}", bytewords_encode(&cbor));
+        assert!(UrDecoder::default().receive(&short).is_err());
+        // Parts of two different messages
+        let other = UrEncoder::new("bytes", &cbor_bytes(&make_message(256, "Fox")), 30);
+        let mut decoder = UrDecoder::default();
+        decoder.receive(&encoder.part(1)).unwrap();
+        assert!(decoder.receive(&other.part(2)).is_err());
+        assert!(decoder.receive("ur:psbt/aeadaolazmjendeoti").is_err());
+    }
+}
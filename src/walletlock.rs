@@ -0,0 +1,346 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::centered_rect;
+use crate::cli::{rpc_result, run_bitcoin_cli, run_bitcoin_cli_stdin};
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::theme::Theme;
+
+// ===== Wallet passphrase dialog =====
+
+/// Unlock durations offered by ↑/↓, in seconds.
+const TIMEOUTS: [u64; 6] = [60, 300, 900, 3600, 14400, 86400];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PassMode {
+    Unlock,
+    Change,
+    Encrypt,
+}
+
+impl PassMode {
+    fn title(self) -> &'static str {
+        match self {
+            PassMode::Unlock => "Unlock wallet",
+            PassMode::Change => "Change passphrase",
+            PassMode::Encrypt => "Encrypt wallet",
+        }
+    }
+
+    fn next(self) -> PassMode {
+        match self {
+            PassMode::Unlock => PassMode::Change,
+            PassMode::Change => PassMode::Encrypt,
+            PassMode::Encrypt => PassMode::Unlock,
+        }
+    }
+
+    /// Number of passphrase fields shown in this mode.
+    fn fields(self) -> usize {
+        match self {
+            PassMode::Unlock => 1,
+            PassMode::Change => 3,
+            PassMode::Encrypt => 2,
+        }
+    }
+}
+
+pub(crate) struct PassphraseDialog {
+    pub mode: PassMode,
+    pub current: TextInput,
+    pub new: TextInput,
+    pub confirm: TextInput,
+    /// Focused passphrase field, 0-based in display order.
+    pub focus: usize,
+    /// Index into `TIMEOUTS`.
+    pub timeout: usize,
+    pub status: String,
+    /// Set after an RPC that changes the lock state; the main loop
+    /// refreshes Wallet Info and clears it.
+    pub changed: bool,
+}
+
+fn format_timeout(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{} h", s / 3600),
+        s => format!("{} min", s / 60),
+    }
+}
+
+impl PassphraseDialog {
+    pub(crate) fn new() -> Self {
+        Self {
+            mode: PassMode::Unlock,
+            current: TextInput::secret(),
+            new: TextInput::secret(),
+            confirm: TextInput::secret(),
+            focus: 0,
+            timeout: 1,
+            status: String::new(),
+            changed: false,
+        }
+    }
+
+    pub(crate) fn open(&mut self, mode: PassMode) {
+        self.mode = mode;
+        self.focus = 0;
+        self.status.clear();
+        self.clear();
+    }
+
+    /// Wipes the typed passphrases.
+    pub(crate) fn clear(&mut self) {
+        self.current.clear();
+        self.new.clear();
+        self.confirm.clear();
+    }
+
+    /// Passphrase fields of the current mode, in display order.
+    fn field_mut(&mut self, i: usize) -> &mut TextInput {
+        match (self.mode, i) {
+            (PassMode::Change, 0) | (PassMode::Unlock, _) => &mut self.current,
+            (PassMode::Change, 1) | (PassMode::Encrypt, 0) => &mut self.new,
+            _ => &mut self.confirm,
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, &TextInput)> {
+        match self.mode {
+            PassMode::Unlock => vec![("Passphrase", &self.current)],
+            PassMode::Change => vec![
+                ("Current passphrase", &self.current),
+                ("New passphrase", &self.new),
+                ("Repeat new passphrase", &self.confirm),
+            ],
+            PassMode::Encrypt => vec![("New passphrase", &self.new), ("Repeat passphrase", &self.confirm)],
+        }
+    }
+
+    /// Runs the RPC for the current mode. Passphrases are cleared either way.
+    fn submit(&mut self) {
+        let result = match self.mode {
+            PassMode::Unlock if self.current.value.is_empty() => Err("Enter the passphrase".to_string()),
+            PassMode::Unlock => {
+                let secs = TIMEOUTS[self.timeout];
+                rpc_result(run_bitcoin_cli_stdin("walletpassphrase", &[&self.current.value, &secs.to_string()]))
+                    .map(|_| format!("Unlocked for {}", format_timeout(secs)))
+            }
+            _ if self.new.value.is_empty() => Err("The new passphrase is empty".to_string()),
+            _ if self.new.value != self.confirm.value => Err("The new passphrases don't match".to_string()),
+            PassMode::Change => rpc_result(run_bitcoin_cli_stdin(
+                "walletpassphrasechange",
+                &[&self.current.value, &self.new.value],
+            ))
+            .map(|_| "Passphrase changed".to_string()),
+            PassMode::Encrypt => rpc_result(run_bitcoin_cli_stdin("encryptwallet", &[&self.new.value]))
+                .map(|out| format!("{} Make a new backup now.", out.trim())),
+        };
+        self.clear();
+        self.focus = 0;
+        match result {
+            Ok(msg) => {
+                self.status = msg;
+                self.changed = true;
+            }
+            Err(e) => self.status = e,
+        }
+    }
+
+    fn lock(&mut self) {
+        self.status = match rpc_result(run_bitcoin_cli("walletlock")) {
+            Ok(_) => "Wallet locked".to_string(),
+            Err(e) => e,
+        };
+        self.changed = true;
+    }
+
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        let i = self.focus;
+        self.field_mut(i).insert_str(text.trim_end_matches(['\r', '\n']));
+    }
+
+    /// Handles a key. Returns false when the dialog should close.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let fields = self.mode.fields();
+        match (keymap.action(Scope::Passphrase, key), key.code) {
+            (Some(Action::PassphraseClose), _) | (_, KeyCode::Esc) => {
+                self.clear();
+                return false;
+            }
+            (Some(Action::PassphraseMode), _) => {
+                let next = self.mode.next();
+                self.open(next);
+            }
+            (Some(Action::PassphraseLock), _) => self.lock(),
+            (_, KeyCode::Tab | KeyCode::Down) if self.mode != PassMode::Unlock => self.focus = (self.focus + 1) % fields,
+            (_, KeyCode::BackTab | KeyCode::Up) if self.mode != PassMode::Unlock => {
+                self.focus = (self.focus + fields - 1) % fields;
+            }
+            (_, KeyCode::Up) => self.timeout = (self.timeout + 1).min(TIMEOUTS.len() - 1),
+            (_, KeyCode::Down) => self.timeout = self.timeout.saturating_sub(1),
+            (_, KeyCode::Enter) if self.focus + 1 < fields => self.focus += 1,
+            (_, KeyCode::Enter) => self.submit(),
+            (_, code) if !ctrl => {
+                let i = self.focus;
+                self.field_mut(i).handle_key(code);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(55, 50, size)
+    }
+
+    pub(crate) fn draw(&self, f: &mut Frame, size: Rect, lock_line: &str, keymap: &Keymap, theme: &Theme) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(format!(" {} • {lock_line} ", self.mode.title()));
+        if !self.status.is_empty() {
+            outer = outer.title_bottom(Span::styled(format!(" {} ", self.status), theme.warn()));
+        }
+        f.render_widget(outer, area);
+
+        let fields = self.fields();
+        let mut constraints = vec![Constraint::Length(3); fields.len()];
+        constraints.push(Constraint::Length(1));
+        constraints.push(Constraint::Min(1));
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(constraints)
+            .split(area);
+
+        for (i, (title, field)) in fields.iter().enumerate() {
+            let style = if i == self.focus {
+                theme.focused()
+            } else {
+                theme.muted()
+            };
+            f.render_widget(
+                Paragraph::new(field.shown())
+                    .block(Block::default().borders(Borders::ALL).border_style(style).title(format!(" {title} "))),
+                rows[i],
+            );
+        }
+
+        let extra = match self.mode {
+            PassMode::Unlock => Line::from(format!(
+                "Unlock for: {} (↑/↓)",
+                format_timeout(TIMEOUTS[self.timeout])
+            )),
+            PassMode::Change => Line::from(""),
+            PassMode::Encrypt => Line::from(Span::styled(
+                "Encrypting can't be undone. Losing the passphrase means losing the funds.",
+                theme.error(),
+            )),
+        };
+        f.render_widget(Paragraph::new(extra), rows[fields.len()]);
+        let mut keys = vec!["Enter=next field / confirm".to_string()];
+        keys.extend([Action::PassphraseMode, Action::PassphraseLock].iter().filter_map(|&a| keymap.hint(a)));
+        keys.push("Esc=close".to_string());
+        f.render_widget(
+            Paragraph::new(keys.join(" • "))
+                .style(theme.muted())
+                .wrap(Wrap { trim: true }),
+            rows[fields.len() + 1],
+        );
+
+        let rect = rows[self.focus];
+        let cursor_x = (rect.x + 1).saturating_add(fields[self.focus].1.cursor as u16);
+        f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ctrl, key};
+
+    fn typed(dialog: &mut PassphraseDialog, text: &str) {
+        for c in text.chars() {
+            dialog.handle_key(key(KeyCode::Char(c)), &Keymap::defaults());
+        }
+    }
+
+    #[test]
+    fn formats_timeouts() {
+        let shown: Vec<String> = TIMEOUTS.iter().map(|&s| format_timeout(s)).collect();
+        assert_eq!(shown, ["1 min", "5 min", "15 min", "1 h", "4 h", "24 h"]);
+    }
+
+    #[test]
+    fn modes_and_fields() {
+        let keymap = Keymap::defaults();
+        let mut d = PassphraseDialog::new();
+        for _ in 0..10 {
+            d.handle_key(key(KeyCode::Up), &keymap);
+        }
+        assert_eq!(d.timeout, TIMEOUTS.len() - 1);
+        d.handle_key(key(KeyCode::Down), &keymap);
+        assert_eq!(d.timeout, TIMEOUTS.len() - 2);
+
+        let ctrl_t = ctrl('t');
+        d.handle_key(ctrl_t, &keymap);
+        assert_eq!(d.mode, PassMode::Change);
+        // Enter moves down the fields in display order: current, new, repeat
+        typed(&mut d, "old");
+        d.handle_key(key(KeyCode::Enter), &keymap);
+        typed(&mut d, "new");
+        d.handle_key(key(KeyCode::Tab), &keymap);
+        typed(&mut d, "new");
+        d.handle_key(key(KeyCode::Tab), &keymap);
+        assert_eq!(d.focus, 0);
+        assert_eq!([&d.current.value, &d.new.value, &d.confirm.value], ["old", "new", "new"]);
+        d.handle_key(key(KeyCode::Up), &keymap);
+        assert_eq!(d.focus, 2);
+
+        d.handle_key(ctrl_t, &keymap);
+        assert_eq!((d.mode, d.focus), (PassMode::Encrypt, 0));
+        assert!(d.current.value.is_empty() && d.new.value.is_empty());
+        typed(&mut d, "x");
+        assert_eq!(d.new.value, "x");
+        d.handle_key(ctrl_t, &keymap);
+        assert_eq!(d.mode, PassMode::Unlock);
+    }
+
+    #[test]
+    fn checks_before_calling_the_node() {
+        let keymap = Keymap::defaults();
+        let mut d = PassphraseDialog::new();
+        d.handle_key(key(KeyCode::Enter), &keymap);
+        assert_eq!(d.status, "Enter the passphrase");
+
+        d.open(PassMode::Encrypt);
+        d.handle_key(key(KeyCode::Enter), &keymap);
+        d.handle_key(key(KeyCode::Enter), &keymap);
+        assert_eq!(d.status, "The new passphrase is empty");
+
+        d.handle_paste("one\n");
+        d.handle_key(key(KeyCode::Enter), &keymap);
+        d.handle_paste("two");
+        d.handle_key(key(KeyCode::Enter), &keymap);
+        assert_eq!(d.status, "The new passphrases don't match");
+        // Wiped after every attempt
+        assert!(d.new.value.is_empty() && d.confirm.value.is_empty());
+        assert!(!d.changed);
+
+        typed(&mut d, "secret");
+        assert!(!d.handle_key(key(KeyCode::Esc), &keymap));
+        assert!(d.new.value.is_empty());
+    }
+}
@@ -0,0 +1,623 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap},
+};
+
+use crate::centered_rect;
+use crate::cli::{rpc_result, run_bitcoin_cli, run_bitcoin_cli_args, run_bitcoin_cli_stdin, run_bitcoin_cli_wallet};
+use crate::input::TextInput;
+use crate::keymap::{Action, Keymap, Scope};
+use crate::theme::Theme;
+
+// ===== Wallet management =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WalletTab {
+    List,
+    Create,
+    Backup,
+    Restore,
+}
+
+impl WalletTab {
+    const ALL: [WalletTab; 4] = [WalletTab::List, WalletTab::Create, WalletTab::Backup, WalletTab::Restore];
+
+    fn title(self) -> &'static str {
+        match self {
+            WalletTab::List => "Wallets",
+            WalletTab::Create => "Create",
+            WalletTab::Backup => "Backup",
+            WalletTab::Restore => "Restore",
+        }
+    }
+
+    fn next(self) -> WalletTab {
+        let i = WalletTab::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        WalletTab::ALL[(i + 1) % WalletTab::ALL.len()]
+    }
+
+    /// Focusable rows on this tab.
+    fn fields(self) -> usize {
+        match self {
+            WalletTab::List => 1,
+            WalletTab::Create => 6,
+            WalletTab::Backup | WalletTab::Restore => 2,
+        }
+    }
+}
+
+/// A wallet in the node's wallet directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WalletDirEntry {
+    pub name: String,
+    pub loaded: bool,
+}
+
+/// `listwalletdir`, marked with what `listwallets` reports as loaded.
+pub(crate) fn fetch_wallet_dir() -> Result<Vec<WalletDirEntry>, String> {
+    let dir: serde_json::Value = serde_json::from_str(&rpc_result(run_bitcoin_cli("listwalletdir"))?)
+        .map_err(|e| e.to_string())?;
+    let loaded: Vec<String> = serde_json::from_str(&rpc_result(run_bitcoin_cli("listwallets"))?)
+        .map_err(|e| e.to_string())?;
+    let mut wallets: Vec<WalletDirEntry> = dir["wallets"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|w| w["name"].as_str()).map(str::to_string).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|name| WalletDirEntry {
+            loaded: loaded.contains(&name),
+            name,
+        })
+        .collect();
+    wallets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(wallets)
+}
+
+/// Backup file name: `<wallet>-<YYYYmmdd-HHMMSS>.bak` ("default" for the unnamed wallet).
+fn backup_name(wallet: &str) -> String {
+    let wallet = if wallet.is_empty() { "default" } else { wallet };
+    let safe: String = wallet
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{safe}-{}.bak", chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+pub(crate) struct WalletManager {
+    pub tab: WalletTab,
+    pub focus: usize,
+    pub wallets: Vec<WalletDirEntry>,
+    pub selected: usize,
+    // Create
+    pub name: TextInput,
+    pub descriptors: bool,
+    pub blank: bool,
+    pub no_private_keys: bool,
+    pub passphrase: TextInput,
+    pub confirm: TextInput,
+    // Backup
+    pub backup_wallet: TextInput,
+    pub backup_dir: TextInput,
+    // Restore
+    pub restore_name: TextInput,
+    pub restore_file: TextInput,
+    /// Running `restorewallet`, which rescans and can take a while.
+    pub restoring: Option<(String, Receiver<Result<String, String>>)>,
+    pub status: String,
+    /// Set when the loaded wallets changed; the main loop refreshes Wallet Info.
+    pub changed: bool,
+}
+
+impl WalletManager {
+    pub(crate) fn new(backup_dir: &Path) -> Self {
+        Self {
+            tab: WalletTab::List,
+            focus: 0,
+            wallets: Vec::new(),
+            selected: 0,
+            name: TextInput::new("", false),
+            descriptors: true,
+            blank: false,
+            no_private_keys: false,
+            passphrase: TextInput::secret(),
+            confirm: TextInput::secret(),
+            backup_wallet: TextInput::new("", false),
+            backup_dir: TextInput::new(&backup_dir.to_string_lossy(), true),
+            restore_name: TextInput::new("", false),
+            restore_file: TextInput::new("", true),
+            restoring: None,
+            status: String::new(),
+            changed: false,
+        }
+    }
+
+    pub(crate) fn open(&mut self) {
+        self.tab = WalletTab::List;
+        self.focus = 0;
+        self.status.clear();
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        match fetch_wallet_dir() {
+            Ok(w) => {
+                self.wallets = w;
+                self.selected = self.selected.min(self.wallets.len().saturating_sub(1));
+            }
+            Err(e) => self.status = format!("listwalletdir failed: {e}"),
+        }
+    }
+
+    fn switch(&mut self, tab: WalletTab) {
+        self.tab = tab;
+        self.focus = 0;
+        if tab == WalletTab::Backup
+            && self.backup_wallet.value.is_empty()
+            && let Some(w) = self.wallets.iter().find(|w| w.loaded)
+        {
+            self.backup_wallet.set(&w.name);
+        }
+    }
+
+    fn toggle_load(&mut self) {
+        let Some(w) = self.wallets.get(self.selected) else {
+            return;
+        };
+        let (method, done) = if w.loaded { ("unloadwallet", "Unloaded") } else { ("loadwallet", "Loaded") };
+        self.status = match rpc_result(run_bitcoin_cli_args(method, &[&w.name])) {
+            Ok(_) => format!("{done} {}", w.name),
+            Err(e) => format!("{method} failed: {e}"),
+        };
+        self.changed = true;
+        self.refresh();
+    }
+
+    fn create(&mut self) {
+        let name = self.name.value.trim().to_string();
+        if name.is_empty() {
+            self.status = "Enter a wallet name".to_string();
+            return;
+        }
+        if self.passphrase.value != self.confirm.value {
+            self.status = "The passphrases don't match".to_string();
+            return;
+        }
+        let flag = |b: bool| if b { "true" } else { "false" };
+        // createwallet name disable_private_keys blank passphrase avoid_reuse descriptors,
+        // on stdin so the passphrase stays off the command line
+        let result = run_bitcoin_cli_stdin(
+            "createwallet",
+            &[
+                &name,
+                flag(self.no_private_keys),
+                flag(self.blank),
+                &self.passphrase.value,
+                "false",
+                flag(self.descriptors),
+            ],
+        );
+        self.passphrase.clear();
+        self.confirm.clear();
+        self.status = match rpc_result(result) {
+            Ok(_) => {
+                self.name.clear();
+                self.changed = true;
+                self.refresh();
+                format!("Created and loaded {name}")
+            }
+            Err(e) => format!("createwallet failed: {e}"),
+        };
+    }
+
+    fn backup(&mut self) {
+        let wallet = self.backup_wallet.value.trim().to_string();
+        let dir = PathBuf::from(self.backup_dir.value.trim());
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            self.status = format!("{}: {e}", dir.display());
+            return;
+        }
+        let path = dir.join(backup_name(&wallet));
+        let target = path.to_string_lossy();
+        let result = if wallet.is_empty() {
+            run_bitcoin_cli_args("backupwallet", &[&target])
+        } else {
+            run_bitcoin_cli_wallet(&wallet, "backupwallet", &[&target])
+        };
+        self.status = match rpc_result(result) {
+            Ok(_) => format!("Backed up to {target}"),
+            Err(e) => format!("backupwallet failed: {e}"),
+        };
+    }
+
+    fn restore(&mut self) {
+        let name = self.restore_name.value.trim().to_string();
+        let file = self.restore_file.value.trim().to_string();
+        if name.is_empty() || file.is_empty() {
+            self.status = "Enter a wallet name and the backup file".to_string();
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn({
+            let name = name.clone();
+            move || {
+                let _ = tx.send(rpc_result(run_bitcoin_cli_args("restorewallet", &[&name, &file])));
+            }
+        });
+        self.status = format!("Restoring {name}… (rescanning, this can take a while)");
+        self.restoring = Some((name, rx));
+    }
+
+    pub(crate) fn restoring(&self) -> bool {
+        self.restoring.is_some()
+    }
+
+    /// Picks up the result of a finished restore.
+    pub(crate) fn poll(&mut self) {
+        let Some((name, rx)) = &self.restoring else {
+            return;
+        };
+        let Ok(result) = rx.try_recv() else {
+            return;
+        };
+        self.status = match result {
+            Ok(_) => format!("Restored and loaded {name}"),
+            Err(e) => format!("restorewallet failed: {e}"),
+        };
+        self.restoring = None;
+        self.changed = true;
+        self.refresh();
+    }
+
+    fn focused_input(&mut self) -> Option<&mut TextInput> {
+        match (self.tab, self.focus) {
+            (WalletTab::Create, 0) => Some(&mut self.name),
+            (WalletTab::Create, 4) => Some(&mut self.passphrase),
+            (WalletTab::Create, 5) => Some(&mut self.confirm),
+            (WalletTab::Backup, 0) => Some(&mut self.backup_wallet),
+            (WalletTab::Backup, _) => Some(&mut self.backup_dir),
+            (WalletTab::Restore, 0) => Some(&mut self.restore_name),
+            (WalletTab::Restore, _) => Some(&mut self.restore_file),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn handle_paste(&mut self, text: &str) {
+        if let Some(input) = self.focused_input() {
+            input.insert_str(text.trim());
+        }
+    }
+
+    /// Handles a key. Returns false when the screen should close.
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let fields = self.tab.fields();
+        let action = keymap.action(Scope::Wallets, key);
+        match (action, key.code) {
+            (Some(Action::WalletsClose), _) | (_, KeyCode::Esc) => return false,
+            (Some(Action::WalletsTab), _) => self.switch(self.tab.next()),
+            (_, KeyCode::Tab) => self.focus = (self.focus + 1) % fields,
+            (_, KeyCode::BackTab) => self.focus = (self.focus + fields - 1) % fields,
+            _ if self.tab == WalletTab::List => match (action, key.code) {
+                (Some(Action::WalletsRefresh), _) => self.refresh(),
+                (Some(Action::WalletsBackup), _) => {
+                    if let Some(w) = self.wallets.get(self.selected) {
+                        self.backup_wallet.set(&w.name.clone());
+                    }
+                    self.switch(WalletTab::Backup);
+                }
+                (Some(Action::WalletsCreate), _) => self.switch(WalletTab::Create),
+                (Some(Action::WalletsRestore), _) => self.switch(WalletTab::Restore),
+                (_, KeyCode::Up) => self.selected = self.selected.saturating_sub(1),
+                (_, KeyCode::Down) if self.selected + 1 < self.wallets.len() => self.selected += 1,
+                (_, KeyCode::Enter) => self.toggle_load(),
+                _ => {}
+            },
+            (_, KeyCode::Up) => self.focus = (self.focus + fields - 1) % fields,
+            (_, KeyCode::Down) => self.focus = (self.focus + 1) % fields,
+            (_, KeyCode::Char(' ')) if self.tab == WalletTab::Create && (1..=3).contains(&self.focus) => {
+                match self.focus {
+                    1 => self.descriptors = !self.descriptors,
+                    2 => self.blank = !self.blank,
+                    _ => self.no_private_keys = !self.no_private_keys,
+                }
+            }
+            (_, KeyCode::Enter) => match self.tab {
+                WalletTab::Create => self.create(),
+                WalletTab::Backup => self.backup(),
+                WalletTab::Restore if !self.restoring() => self.restore(),
+                _ => {}
+            },
+            (_, code) if !ctrl => {
+                if let Some(input) = self.focused_input() {
+                    input.handle_key(code);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub(crate) fn area(size: Rect) -> Rect {
+        centered_rect(70, 70, size)
+    }
+
+    pub(crate) fn draw(&self, f: &mut Frame, size: Rect, keymap: &Keymap, theme: &Theme) {
+        let area = Self::area(size);
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(theme.base()), area);
+
+        let mut outer = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.accent())
+            .title(" Wallet management ");
+        if !self.status.is_empty() {
+            outer = outer.title_bottom(Span::styled(format!(" {} ", self.status), theme.warn()));
+        }
+        f.render_widget(outer, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(2), Constraint::Min(3)])
+            .split(area);
+
+        let selected_tab = WalletTab::ALL.iter().position(|t| *t == self.tab).unwrap_or(0);
+        f.render_widget(
+            Tabs::new(WalletTab::ALL.iter().map(|t| t.title()).collect::<Vec<_>>())
+                .select(selected_tab)
+                .highlight_style(theme.focused())
+                .block(Block::default().borders(Borders::BOTTOM).title_bottom(keymap.hint(Action::WalletsTab).map(|h| format!(" {h} ")).unwrap_or_default())),
+            rows[0],
+        );
+
+        match self.tab {
+            WalletTab::List => self.draw_list(f, rows[1], keymap, theme),
+            WalletTab::Create => self.draw_create(f, rows[1], theme),
+            WalletTab::Backup => self.draw_form(
+                f,
+                rows[1],
+                &[
+                    (" Wallet (empty = default) ", &self.backup_wallet),
+                    (" Backup directory ", &self.backup_dir),
+                ],
+                format!(
+                    "Enter = back up to {}",
+                    Path::new(self.backup_dir.value.trim())
+                        .join(backup_name(self.backup_wallet.value.trim()))
+                        .display()
+                ),
+                theme,
+            ),
+            WalletTab::Restore => self.draw_form(
+                f,
+                rows[1],
+                &[(" New wallet name ", &self.restore_name), (" Backup file ", &self.restore_file)],
+                if self.restoring() {
+                    "Restoring…".to_string()
+                } else {
+                    "Enter = restore with restorewallet (the node rescans the chain)".to_string()
+                },
+                theme,
+            ),
+        }
+    }
+
+    fn draw_list(&self, f: &mut Frame, area: Rect, keymap: &Keymap, theme: &Theme) {
+        let items: Vec<ListItem> = self
+            .wallets
+            .iter()
+            .map(|w| {
+                let name = if w.name.is_empty() { "(default)" } else { w.name.as_str() };
+                let line = if w.loaded {
+                    Line::from(vec![Span::raw(format!("{name:<40}")), Span::styled("loaded", theme.ok())])
+                } else {
+                    Line::from(format!("{name:<40}"))
+                };
+                ListItem::new(line)
+            })
+            .collect();
+        let mut state = ListState::default();
+        if !self.wallets.is_empty() {
+            state.select(Some(self.selected));
+        }
+        let mut keys = vec!["Enter=load/unload".to_string()];
+        keys.extend(
+            [Action::WalletsBackup, Action::WalletsCreate, Action::WalletsRestore, Action::WalletsRefresh]
+                .iter()
+                .filter_map(|&a| keymap.hint(a)),
+        );
+        f.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!(" Wallet directory ({}) ", keys.join(" • "))),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut state,
+        );
+    }
+
+    fn draw_create(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(1),
+            ])
+            .split(area);
+        let style = |i: usize| {
+            if self.focus == i {
+                theme.focused()
+            } else {
+                theme.muted()
+            }
+        };
+        let input = |title: &str, i: usize, shown: String| {
+            Paragraph::new(shown).block(Block::default().borders(Borders::ALL).border_style(style(i)).title(title.to_string()))
+        };
+        f.render_widget(input(" Wallet name ", 0, self.name.value.clone()), rows[0]);
+        let checks = [
+            (self.descriptors, "Descriptor wallet"),
+            (self.blank, "Blank (no keys generated)"),
+            (self.no_private_keys, "Disable private keys (watch-only)"),
+        ];
+        for (i, (on, label)) in checks.iter().enumerate() {
+            let mark = if *on { "[x]" } else { "[ ]" };
+            let text_style = if self.focus == i + 1 { style(i + 1) } else { Style::default() };
+            f.render_widget(Paragraph::new(Span::styled(format!(" {mark} {label}"), text_style)), rows[i + 1]);
+        }
+        f.render_widget(
+            input(" Passphrase (optional, encrypts the wallet) ", 4, self.passphrase.shown()),
+            rows[4],
+        );
+        f.render_widget(input(" Repeat passphrase ", 5, self.confirm.shown()), rows[5]);
+        f.render_widget(
+            Paragraph::new("Space = toggle option • Enter = create with createwallet")
+                .style(theme.muted())
+                .wrap(Wrap { trim: true }),
+            rows[6],
+        );
+        let field = match self.focus {
+            0 => Some((&self.name, rows[0])),
+            4 => Some((&self.passphrase, rows[4])),
+            5 => Some((&self.confirm, rows[5])),
+            _ => None,
+        };
+        if let Some((field, rect)) = field {
+            let cursor_x = (rect.x + 1).saturating_add(field.cursor as u16);
+            f.set_cursor(cursor_x.min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+        }
+    }
+
+    fn draw_form(&self, f: &mut Frame, area: Rect, fields: &[(&str, &TextInput)], hint: String, theme: &Theme) {
+        let mut constraints = vec![Constraint::Length(3); fields.len()];
+        constraints.push(Constraint::Min(1));
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(area);
+        for (i, (title, field)) in fields.iter().enumerate() {
+            let style = if self.focus == i {
+                theme.focused()
+            } else {
+                theme.muted()
+            };
+            f.render_widget(
+                Paragraph::new(field.value.clone())
+                    .block(Block::default().borders(Borders::ALL).border_style(style).title(*title)),
+                rows[i],
+            );
+        }
+        f.render_widget(
+            Paragraph::new(hint).style(theme.muted()).wrap(Wrap { trim: false }),
+            rows[fields.len()],
+        );
+        let rect = rows[self.focus.min(fields.len() - 1)];
+        let cursor = fields[self.focus.min(fields.len() - 1)].1.cursor as u16;
+        f.set_cursor((rect.x + 1 + cursor).min(rect.x + rect.width.saturating_sub(2)), rect.y + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ctrl, key};
+
+    fn typed(manager: &mut WalletManager, text: &str) {
+        for c in text.chars() {
+            manager.handle_key(key(KeyCode::Char(c)), &Keymap::defaults());
+        }
+    }
+
+    #[test]
+    fn backup_names() {
+        let name = backup_name("my wallet/1");
+        assert!(name.starts_with("my_wallet_1-") && name.ends_with(".bak"), "{name}");
+        // <wallet>-YYYYmmdd-HHMMSS.bak
+        assert_eq!(name.len(), "my_wallet_1-".len() + 15 + 4);
+        assert!(backup_name("").starts_with("default-"));
+    }
+
+    #[test]
+    fn tabs_and_backup_prefill() {
+        let keymap = Keymap::defaults();
+        let ctrl_t = ctrl('t');
+        let mut m = WalletManager::new(Path::new("/backups"));
+        m.wallets = vec![
+            WalletDirEntry { name: "cold".into(), loaded: false },
+            WalletDirEntry { name: "hot".into(), loaded: true },
+        ];
+        m.handle_key(ctrl_t, &keymap);
+        assert_eq!(m.tab, WalletTab::Create);
+        m.handle_key(key(KeyCode::BackTab), &keymap);
+        assert_eq!(m.focus, 5);
+        m.handle_key(ctrl_t, &keymap);
+        // Focus resets and the first loaded wallet is filled in
+        assert_eq!((m.tab, m.focus), (WalletTab::Backup, 0));
+        assert_eq!(m.backup_wallet.value, "hot");
+        assert_eq!(m.backup_dir.value, "/backups");
+        m.handle_key(ctrl_t, &keymap);
+        m.handle_key(ctrl_t, &keymap);
+        assert_eq!(m.tab, WalletTab::List);
+        assert!(m.handle_key(key(KeyCode::Down), &keymap));
+        assert_eq!(m.selected, 1);
+        assert!(!m.handle_key(key(KeyCode::Esc), &keymap));
+    }
+
+    #[test]
+    fn create_form() {
+        let keymap = Keymap::defaults();
+        let mut m = WalletManager::new(Path::new("/backups"));
+        m.switch(WalletTab::Create);
+        m.handle_key(key(KeyCode::Enter), &keymap);
+        assert_eq!(m.status, "Enter a wallet name");
+
+        typed(&mut m, "spend");
+        for flag in 1..=3 {
+            m.focus = flag;
+            m.handle_key(key(KeyCode::Char(' ')), &keymap);
+        }
+        assert!(!m.descriptors && m.blank && m.no_private_keys);
+        // Space only toggles, it's not typed into the name
+        assert_eq!(m.name.value, "spend");
+
+        m.handle_key(key(KeyCode::Down), &keymap);
+        typed(&mut m, "one");
+        m.handle_key(key(KeyCode::Down), &keymap);
+        typed(&mut m, "two");
+        m.handle_key(key(KeyCode::Enter), &keymap);
+        assert_eq!(m.status, "The passphrases don't match");
+        assert_eq!((m.passphrase.value.as_str(), m.confirm.value.as_str()), ("one", "two"));
+    }
+
+    #[test]
+    fn restore_needs_both_fields() {
+        let keymap = Keymap::defaults();
+        let mut m = WalletManager::new(Path::new("/backups"));
+        m.switch(WalletTab::Restore);
+        typed(&mut m, "old");
+        m.handle_key(key(KeyCode::Enter), &keymap);
+        assert_eq!(m.status, "Enter a wallet name and the backup file");
+        assert!(!m.restoring());
+        m.handle_paste("/backups/old.bak");
+        assert_eq!(m.restore_name.value, "old/backups/old.bak");
+        m.handle_key(key(KeyCode::Tab), &keymap);
+        m.handle_paste("/backups/old.bak");
+        assert_eq!(m.restore_file.value, "/backups/old.bak");
+    }
+}
@@ -0,0 +1,210 @@
+use bitcoin::Amount;
+use serde_json::json;
+
+use crate::cli::{run_bitcoin_cli, run_bitcoin_cli_args, run_bitcoin_cli_wallet};
+use crate::node::json_amount;
+
+// ===== Watch-only wallet =====
+
+/// Descriptor wallet without private keys that holds everything marked
+/// watch-only in the address book.
+pub const WATCH_WALLET: &str = "bitatui-watch";
+
+/// One descriptor to import, with the checksum already attached.
+#[derive(Debug, Clone)]
+pub(crate) struct WatchRequest {
+    pub descriptor: String,
+    pub label: String,
+    pub range: bool,
+}
+
+fn rpc_json(out: String) -> Result<serde_json::Value, String> {
+    if out.starts_with("Error:") {
+        return Err(out.trim().to_string());
+    }
+    serde_json::from_str(&out).map_err(|e| e.to_string())
+}
+
+/// Descriptor for an address or descriptor input, without a checksum.
+/// Addresses become `addr(...)`.
+fn watch_descriptor(input: &str) -> String {
+    let input = input.trim();
+    if input.contains('(') {
+        // Drop any checksum the user pasted; the node recomputes it.
+        input.split('#').next().unwrap_or(input).to_string()
+    } else {
+        format!("addr({input})")
+    }
+}
+
+/// Turns an address or descriptor into a checksummed descriptor via
+/// `getdescriptorinfo`.
+pub(crate) fn watch_request(input: &str, label: &str) -> Result<WatchRequest, String> {
+    let desc = watch_descriptor(input);
+    let info = rpc_json(run_bitcoin_cli_args("getdescriptorinfo", &[&desc]).map_err(|e| e.to_string())?)?;
+    let checksum = info["checksum"].as_str().ok_or("getdescriptorinfo: no checksum")?;
+    if info["hasprivatekeys"].as_bool() == Some(true) {
+        return Err("descriptor contains private keys; use the public version".to_string());
+    }
+    Ok(WatchRequest {
+        descriptor: format!("{desc}#{checksum}"),
+        label: label.to_string(),
+        range: info["isrange"].as_bool().unwrap_or(false),
+    })
+}
+
+/// Loads the watch-only wallet, creating it (blank, descriptors, no
+/// private keys) the first time.
+pub(crate) fn ensure_watch_wallet() -> Result<(), String> {
+    let loaded = rpc_json(run_bitcoin_cli("listwallets").map_err(|e| e.to_string())?)?;
+    if loaded
+        .as_array()
+        .is_some_and(|w| w.iter().any(|n| n.as_str() == Some(WATCH_WALLET)))
+    {
+        return Ok(());
+    }
+    let out = run_bitcoin_cli_args("loadwallet", &[WATCH_WALLET]).map_err(|e| e.to_string())?;
+    if !out.starts_with("Error:") {
+        return Ok(());
+    }
+    // createwallet name disable_private_keys blank passphrase avoid_reuse descriptors
+    let out = run_bitcoin_cli_args(
+        "createwallet",
+        &[WATCH_WALLET, "true", "true", "", "false", "true"],
+    )
+    .map_err(|e| e.to_string())?;
+    rpc_json(out).map(|_| ())
+}
+
+/// Imports descriptors into the watch wallet. With `rescan` the node scans
+/// the chain from genesis; `importdescriptors` blocks until that is done,
+/// so call this from a background thread and follow `fetch_watch_status`.
+pub(crate) fn import_watch(requests: &[WatchRequest], rescan: bool) -> Result<String, String> {
+    ensure_watch_wallet()?;
+    let arg = serde_json::to_string(&import_items(requests, rescan)).map_err(|e| e.to_string())?;
+    let res = rpc_json(
+        run_bitcoin_cli_wallet(WATCH_WALLET, "importdescriptors", &[&arg]).map_err(|e| e.to_string())?,
+    )?;
+
+    let failed: Vec<String> = res
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["success"].as_bool() != Some(true))
+        .map(|r| r["error"]["message"].as_str().unwrap_or("unknown error").to_string())
+        .collect();
+    if failed.is_empty() {
+        Ok(format!("Imported {} descriptor(s) into {WATCH_WALLET}", requests.len()))
+    } else {
+        Err(failed.join("; "))
+    }
+}
+
+/// The `importdescriptors` request. Ranged descriptors cannot carry a
+/// label, so theirs is dropped.
+fn import_items(requests: &[WatchRequest], rescan: bool) -> Vec<serde_json::Value> {
+    requests
+        .iter()
+        .map(|r| {
+            let mut item = json!({
+                "desc": r.descriptor,
+                "timestamp": if rescan { json!(0) } else { json!("now") },
+            });
+            if r.range {
+                item["range"] = json!([0, 999]);
+            } else if !r.label.is_empty() {
+                item["label"] = json!(r.label);
+            }
+            item
+        })
+        .collect()
+}
+
+/// Snapshot of the watch-only wallet for the side panel.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WatchStatus {
+    /// Rescan progress 0.0..=1.0 while the node is scanning.
+    pub scanning: Option<f64>,
+    pub trusted: Amount,
+    pub pending: Amount,
+    pub immature: Amount,
+    pub tx_count: u64,
+}
+
+/// Reads `getwalletinfo.scanning` and `getbalances` of the watch wallet.
+/// Fails if the wallet is not loaded.
+pub(crate) fn fetch_watch_status() -> Result<WatchStatus, String> {
+    let info = rpc_json(run_bitcoin_cli_wallet(WATCH_WALLET, "getwalletinfo", &[]).map_err(|e| e.to_string())?)?;
+    let bal = rpc_json(run_bitcoin_cli_wallet(WATCH_WALLET, "getbalances", &[]).map_err(|e| e.to_string())?)?;
+    watch_status(&info, &bal)
+}
+
+fn watch_status(info: &serde_json::Value, bal: &serde_json::Value) -> Result<WatchStatus, String> {
+    let scanning = info["scanning"]["progress"].as_f64();
+    let tx_count = info["txcount"].as_u64().unwrap_or(0);
+    // Wallets without private keys report their coins under "mine".
+    let mine = &bal["mine"];
+    Ok(WatchStatus {
+        scanning,
+        trusted: json_amount(&mine["trusted"])?,
+        pending: json_amount(&mine["untrusted_pending"])?,
+        immature: json_amount(&mine["immature"])?,
+        tx_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(descriptor: &str, label: &str, range: bool) -> WatchRequest {
+        WatchRequest { descriptor: descriptor.to_string(), label: label.to_string(), range }
+    }
+
+    #[test]
+    fn descriptors_for_inputs() {
+        assert_eq!(watch_descriptor(" bc1qexample "), "addr(bc1qexample)");
+        assert_eq!(watch_descriptor("wpkh(xpub/0/*)#abcdefgh"), "wpkh(xpub/0/*)");
+        assert_eq!(watch_descriptor("addr(bc1qexample)"), "addr(bc1qexample)");
+    }
+
+    #[test]
+    fn builds_import_items() {
+        let requests = [request("addr(bc1q)#aaaa", "rent", false), request("wpkh(x/0/*)#bbbb", "cold", true)];
+        assert_eq!(
+            serde_json::Value::Array(import_items(&requests, false)),
+            json!([
+                {"desc": "addr(bc1q)#aaaa", "timestamp": "now", "label": "rent"},
+                {"desc": "wpkh(x/0/*)#bbbb", "timestamp": "now", "range": [0, 999]},
+            ])
+        );
+        let items = import_items(&[request("addr(bc1q)#aaaa", "", false)], true);
+        assert_eq!(items[0], json!({"desc": "addr(bc1q)#aaaa", "timestamp": 0}));
+    }
+
+    #[test]
+    fn reads_status() {
+        let info = json!({"txcount": 3, "scanning": {"duration": 10, "progress": 0.25}});
+        let bal: serde_json::Value = serde_json::from_str(
+            r#"{"mine": {"trusted": 0.00100000, "untrusted_pending": 0.00000546, "immature": 0}}"#,
+        )
+        .unwrap();
+        let status = watch_status(&info, &bal).unwrap();
+        assert_eq!(status.scanning, Some(0.25));
+        assert_eq!(status.tx_count, 3);
+        assert_eq!(status.trusted, Amount::from_sat(100_000));
+        assert_eq!(status.pending, Amount::from_sat(546));
+        assert_eq!(status.immature, Amount::ZERO);
+
+        let idle = watch_status(&json!({"scanning": false}), &bal).unwrap();
+        assert_eq!((idle.scanning, idle.tx_count), (None, 0));
+        assert!(watch_status(&info, &json!({"mine": {}})).is_err());
+    }
+
+    #[test]
+    fn rpc_errors() {
+        assert_eq!(rpc_json("Error: wallet not found\n".to_string()).unwrap_err(), "Error: wallet not found");
+        assert_eq!(rpc_json("[\"a\"]".to_string()).unwrap(), json!(["a"]));
+        assert!(rpc_json("not json".to_string()).is_err());
+    }
+}